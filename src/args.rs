@@ -1,4 +1,11 @@
-use clap::{ArgAction, Parser};
+use crate::gol::{format::Format, rule::Rule, DEFAULT_IMAGE_SIZE};
+use crate::record::gif::Palette;
+use crate::sdl::colour::{ColourMode, Gradient};
+use crate::sdl::compare::CompareLayout;
+use crate::tui::canvas::Glyphs;
+use crate::util::cell::CellCoord;
+use clap::{ArgAction, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
 
 #[derive(Clone, Debug, Parser)]
 #[clap(disable_help_flag = true)]
@@ -14,18 +21,45 @@ pub struct Args {
     #[arg(
         short = 'w',
         long = "width",
-        default_value_t = 512,
-        help = "Specify the width of the image."
+        help = "Specify the width of the image (read from the input image if not given, or 512 without one)."
     )]
-    pub image_width: usize,
+    pub width: Option<usize>,
 
     #[arg(
         short = 'h',
         long = "height",
-        default_value_t = 512,
-        help = "Specify the height of the image."
+        help = "Specify the height of the image (read from the input image if not given, or 512 without one)."
     )]
-    pub image_height: usize,
+    pub height: Option<usize>,
+
+    #[arg(
+        short = 'i',
+        long,
+        help = "Specify the PGM image, RLE or plaintext pattern to load instead of images/<width>x<height>.pgm, or - for stdin (the default when a board is piped in)."
+    )]
+    pub input: Option<PathBuf>,
+
+    #[arg(
+        short = 'o',
+        long,
+        default_value = "out",
+        help = "Specify the directory to write output images and recordings to."
+    )]
+    pub output_dir: PathBuf,
+
+    #[arg(
+        long,
+        value_enum,
+        help = "Write output boards to stdout in this format instead of writing images, running headless."
+    )]
+    pub stdout: Option<Format>,
+
+    #[arg(
+        long,
+        default_value_t = 0,
+        help = "Specify the number of turns between two boards written to stdout (0 for the final board only)."
+    )]
+    pub stdout_interval: usize,
 
     #[arg(
         short = 'f',
@@ -49,6 +83,217 @@ pub struct Args {
     )]
     pub headless: bool,
 
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Frontend::Sdl,
+        help = "Specify where to show the simulation when it is not headless."
+    )]
+    pub frontend: Frontend,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Glyphs::Braille,
+        help = "Specify the characters the terminal frontend draws cells with (press G to switch)."
+    )]
+    pub tui_glyphs: Glyphs,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = ColourMode::Plain,
+        help = "Specify what the colour of a cell shows in the SDL window (press C to cycle through the modes)."
+    )]
+    pub colour_mode: ColourMode,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Gradient::Viridis,
+        help = "Specify the palette that cells are coloured with by their age."
+    )]
+    pub age_palette: Gradient,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Gradient::Heat,
+        help = "Specify the palette that cells are coloured with by their activity."
+    )]
+    pub activity_palette: Gradient,
+
+    #[arg(
+        long,
+        default_value_t = 16,
+        help = "Specify the number of turns that the activity of a cell is counted over."
+    )]
+    pub activity_window: u32,
+
+    #[arg(
+        long,
+        help = "Show the population, births and deaths per turn in a graph below the SDL window's cells (press G to toggle)."
+    )]
+    pub graph: bool,
+
+    #[arg(
+        long,
+        default_value_t = 500,
+        help = "Specify the number of turns that the population graph shows (press [ and ] to change)."
+    )]
+    pub graph_window: u32,
+
+    #[arg(
+        long,
+        help = "Read the key bindings and colour theme of the SDL window from this JSON file, whose keys the terminal uses as well (press ? to list them)."
+    )]
+    pub config: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Run a second simulation with this rule in lockstep, to compare it with the first in the SDL window."
+    )]
+    pub compare_rule: Option<Rule>,
+
+    #[arg(
+        long,
+        value_name = "X,Y",
+        help = "Run a second simulation with this cell flipped in lockstep, to compare it with the first in the SDL window."
+    )]
+    pub compare_flip: Option<CellCoord>,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = CompareLayout::SideBySide,
+        help = "Specify how the SDL window shows the two simulations being compared."
+    )]
+    pub compare_layout: CompareLayout,
+
+    #[arg(
+        long,
+        help = "Write checkpoints to this file periodically and on quit."
+    )]
+    pub checkpoint: Option<PathBuf>,
+
+    #[arg(
+        long,
+        default_value_t = 300,
+        help = "Specify the number of seconds between two checkpoints."
+    )]
+    pub checkpoint_interval: u64,
+
+    #[arg(
+        long,
+        help = "Resume the run from this checkpoint instead of loading an image."
+    )]
+    pub resume: Option<PathBuf>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        help = "Record the run to an animated GIF (press R to toggle recording in the SDL window)."
+    )]
+    pub record: bool,
+
+    #[arg(
+        long,
+        default_value_t = 1,
+        help = "Specify the number of turns between two recorded GIF frames."
+    )]
+    pub record_stride: u32,
+
+    #[arg(
+        long,
+        default_value_t = 1,
+        help = "Specify the size in pixels of a cell in the recorded GIF."
+    )]
+    pub record_scale: u32,
+
+    #[arg(
+        long,
+        value_enum,
+        default_value_t = Palette::Classic,
+        help = "Specify the colour palette of the recorded GIF."
+    )]
+    pub record_palette: Palette,
+
+    #[arg(
+        long,
+        default_value_t = 60,
+        help = "Specify the maximum duration in seconds of the recorded GIF."
+    )]
+    pub record_max_secs: u32,
+
+    #[arg(
+        long,
+        help = "Log every event with its time to this JSON-lines file, for replaying with --replay."
+    )]
+    pub event_log: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Replay the events logged to this file instead of running a simulation."
+    )]
+    pub replay: Option<PathBuf>,
+
+    #[arg(
+        long,
+        default_value_t = 1.0,
+        help = "Specify how many times faster than logged to replay the events (0 for no delays)."
+    )]
+    pub replay_speed: f64,
+
+    #[arg(
+        long,
+        help = "Accept remote controllers on this address, e.g. 127.0.0.1:8030."
+    )]
+    pub listen: Option<String>,
+
+    #[arg(
+        long,
+        default_value_t = false,
+        requires = "listen",
+        help = "Keep the simulation and the server running without the SDL window until a controller shuts them down."
+    )]
+    pub persistent: bool,
+
+    #[arg(
+        long,
+        help = "Serve the status and the board over HTTP on this address, e.g. 127.0.0.1:8080."
+    )]
+    pub http: Option<String>,
+
+    #[arg(
+        long,
+        help = "Export metrics in the Prometheus text format on this address, e.g. 127.0.0.1:9090."
+    )]
+    pub metrics: Option<String>,
+
+    #[arg(
+        long,
+        conflicts_with = "listen",
+        help = "Attach the SDL window to the server on this address instead of running a simulation."
+    )]
+    pub connect: Option<String>,
+
+    #[arg(
+        long,
+        value_delimiter = ',',
+        help = "Distribute the world across the worker processes at these comma-separated addresses."
+    )]
+    pub workers: Vec<String>,
+
+    #[arg(
+        long,
+        default_value_t = 10,
+        help = "Specify the number of seconds to wait for a worker before reassigning its strip."
+    )]
+    pub worker_timeout: u64,
+
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     #[arg(
         long,
         action = ArgAction::HelpLong
@@ -56,6 +301,28 @@ pub struct Args {
     help: Option<bool>,
 }
 
+/// `Frontend` is where the simulation is shown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Frontend {
+    /// The SDL window.
+    Sdl,
+    /// The terminal, for when there is no display, e.g. over SSH.
+    Tui,
+}
+
+#[derive(Clone, Debug, Subcommand)]
+pub enum Command {
+    /// Compute strips of the world for a run started with --workers.
+    Worker {
+        #[arg(
+            long,
+            default_value = "127.0.0.1:8040",
+            help = "Specify the address to accept the run on."
+        )]
+        listen: String,
+    },
+}
+
 impl Default for Args {
     fn default() -> Self {
         Args::parse_from([""])
@@ -69,12 +336,42 @@ impl Args {
     }
 
     pub fn image_width(mut self, image_width: usize) -> Self {
-        self.image_width = image_width;
+        self.width = Some(image_width);
         self
     }
 
     pub fn image_height(mut self, image_height: usize) -> Self {
-        self.image_height = image_height;
+        self.height = Some(image_height);
+        self
+    }
+
+    /// The width of the image, which is the one given or 512 until it is read from the input image.
+    pub fn width(&self) -> usize {
+        self.width.unwrap_or(DEFAULT_IMAGE_SIZE)
+    }
+
+    /// The height of the image, which is the one given or 512 until it is read from the input image.
+    pub fn height(&self) -> usize {
+        self.height.unwrap_or(DEFAULT_IMAGE_SIZE)
+    }
+
+    pub fn input<P: Into<PathBuf>>(mut self, input: P) -> Self {
+        self.input = Some(input.into());
+        self
+    }
+
+    pub fn output_dir<P: Into<PathBuf>>(mut self, output_dir: P) -> Self {
+        self.output_dir = output_dir.into();
+        self
+    }
+
+    pub fn stdout(mut self, stdout: Format) -> Self {
+        self.stdout = Some(stdout);
+        self
+    }
+
+    pub fn stdout_interval(mut self, stdout_interval: usize) -> Self {
+        self.stdout_interval = stdout_interval;
         self
     }
 
@@ -92,4 +389,148 @@ impl Args {
         self.headless = headless;
         self
     }
+
+    pub fn frontend(mut self, frontend: Frontend) -> Self {
+        self.frontend = frontend;
+        self
+    }
+
+    pub fn tui_glyphs(mut self, tui_glyphs: Glyphs) -> Self {
+        self.tui_glyphs = tui_glyphs;
+        self
+    }
+
+    pub fn colour_mode(mut self, colour_mode: ColourMode) -> Self {
+        self.colour_mode = colour_mode;
+        self
+    }
+
+    pub fn age_palette(mut self, age_palette: Gradient) -> Self {
+        self.age_palette = age_palette;
+        self
+    }
+
+    pub fn activity_palette(mut self, activity_palette: Gradient) -> Self {
+        self.activity_palette = activity_palette;
+        self
+    }
+
+    pub fn activity_window(mut self, activity_window: u32) -> Self {
+        self.activity_window = activity_window;
+        self
+    }
+
+    pub fn graph(mut self, graph: bool) -> Self {
+        self.graph = graph;
+        self
+    }
+
+    pub fn graph_window(mut self, graph_window: u32) -> Self {
+        self.graph_window = graph_window;
+        self
+    }
+
+    pub fn config<P: Into<PathBuf>>(mut self, config: P) -> Self {
+        self.config = Some(config.into());
+        self
+    }
+
+    pub fn compare_rule(mut self, compare_rule: Rule) -> Self {
+        self.compare_rule = Some(compare_rule);
+        self
+    }
+
+    pub fn compare_flip(mut self, compare_flip: CellCoord) -> Self {
+        self.compare_flip = Some(compare_flip);
+        self
+    }
+
+    pub fn compare_layout(mut self, compare_layout: CompareLayout) -> Self {
+        self.compare_layout = compare_layout;
+        self
+    }
+
+    /// Whether a second simulation runs in lockstep with the first, to compare them.
+    pub fn compares(&self) -> bool {
+        self.compare_rule.is_some() || self.compare_flip.is_some()
+    }
+
+    pub fn checkpoint<P: Into<PathBuf>>(mut self, checkpoint: P) -> Self {
+        self.checkpoint = Some(checkpoint.into());
+        self
+    }
+
+    pub fn checkpoint_interval(mut self, checkpoint_interval: u64) -> Self {
+        self.checkpoint_interval = checkpoint_interval;
+        self
+    }
+
+    pub fn resume<P: Into<PathBuf>>(mut self, resume: P) -> Self {
+        self.resume = Some(resume.into());
+        self
+    }
+
+    pub fn record(mut self, record: bool) -> Self {
+        self.record = record;
+        self
+    }
+
+    pub fn record_stride(mut self, record_stride: u32) -> Self {
+        self.record_stride = record_stride;
+        self
+    }
+
+    pub fn record_scale(mut self, record_scale: u32) -> Self {
+        self.record_scale = record_scale;
+        self
+    }
+
+    pub fn record_palette(mut self, record_palette: Palette) -> Self {
+        self.record_palette = record_palette;
+        self
+    }
+
+    pub fn record_max_secs(mut self, record_max_secs: u32) -> Self {
+        self.record_max_secs = record_max_secs;
+        self
+    }
+
+    pub fn listen<S: Into<String>>(mut self, listen: S) -> Self {
+        self.listen = Some(listen.into());
+        self
+    }
+
+    pub fn event_log<P: Into<PathBuf>>(mut self, event_log: P) -> Self {
+        self.event_log = Some(event_log.into());
+        self
+    }
+
+    pub fn http<S: Into<String>>(mut self, http: S) -> Self {
+        self.http = Some(http.into());
+        self
+    }
+
+    pub fn metrics<S: Into<String>>(mut self, metrics: S) -> Self {
+        self.metrics = Some(metrics.into());
+        self
+    }
+
+    pub fn persistent(mut self, persistent: bool) -> Self {
+        self.persistent = persistent;
+        self
+    }
+
+    pub fn workers<I, S>(mut self, workers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.workers = workers.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn worker_timeout(mut self, worker_timeout: u64) -> Self {
+        self.worker_timeout = worker_timeout;
+        self
+    }
 }
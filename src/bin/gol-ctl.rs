@@ -0,0 +1,115 @@
+use anyhow::{bail, Context, Result};
+use clap::{Parser, Subcommand};
+use gol_rs::gol::{event::{Event, State}, format::{Board, Format}};
+use gol_rs::net::protocol::{Connection, Request, Response};
+use std::{io::Write, path::PathBuf};
+
+/// Control a gol-rs simulation started with --listen.
+#[derive(Debug, Parser)]
+struct CtlArgs {
+    #[arg(
+        short = 's',
+        long,
+        default_value = "127.0.0.1:8030",
+        help = "Specify the address of the server to connect to."
+    )]
+    server: String,
+
+    #[command(subcommand)]
+    command: CtlCommand,
+}
+
+#[derive(Debug, Subcommand)]
+enum CtlCommand {
+    /// Print the current turn, population and state.
+    Status,
+    /// Pause the simulation.
+    Pause,
+    /// Resume the simulation.
+    Resume,
+    /// Print the current board, or write it to a file.
+    Snapshot {
+        #[arg(short = 'f', long, value_enum, default_value_t = Format::Pgm, help = "Specify the format of the board.")]
+        format: Format,
+        #[arg(short = 'o', long, help = "Write the board to this file instead of stdout.")]
+        output: Option<PathBuf>,
+    },
+    /// Ask the server to write the current board to an image, as if S was pressed.
+    Save,
+    /// Print every event until the simulation quits.
+    Watch,
+    /// Finish the simulation, as if Q was pressed.
+    Quit,
+    /// Finish the simulation and stop the server, as if K was pressed.
+    Shutdown,
+}
+
+#[tokio::main(flavor = "current_thread")]
+async fn main() {
+    let args = CtlArgs::parse();
+    if let Err(e) = run(args).await {
+        eprintln!("gol-ctl: {:#}", e);
+        std::process::exit(1);
+    }
+}
+
+async fn run(args: CtlArgs) -> Result<()> {
+    let mut connection = Connection::connect(&args.server).await?;
+    match args.command {
+        CtlCommand::Status => match request(&mut connection, Request::Status).await? {
+            Response::Status { completed_turns, population, state } =>
+                println!("Complete Turns {:<8} Alive Cells {:<8} {}", completed_turns, population, state),
+            response => bail!("Unexpected response {:?}", response),
+        },
+        CtlCommand::Pause => { request(&mut connection, Request::Pause).await?; },
+        CtlCommand::Resume => { request(&mut connection, Request::Resume).await?; },
+        CtlCommand::Save => { request(&mut connection, Request::Snapshot).await?; },
+        CtlCommand::Quit => { request(&mut connection, Request::Quit).await?; },
+        CtlCommand::Shutdown => { request(&mut connection, Request::Shutdown).await?; },
+        CtlCommand::Snapshot { format, output } => {
+            let (width, height, rule) = match request(&mut connection, Request::Attach).await? {
+                Response::Attached { image_width, image_height, rule } => (image_width, image_height, rule),
+                response => bail!("Unexpected response {:?}", response),
+            };
+            // the snapshot is the first event after attaching
+            let alive = loop {
+                match connection.recv().await?.context("The server closed the connection")? {
+                    Response::Event { event: Event::CellsFlipped { cells, .. } } => break cells,
+                    Response::Error { message } => bail!("{}", message),
+                    _ => (),
+                }
+            };
+            let bytes = Board::from_alive_cells(width, height, &alive).encode(format, &rule);
+            match output {
+                Some(path) => std::fs::write(&path, bytes).with_context(|| format!("Cannot write {}", path.display()))?,
+                None => std::io::stdout().write_all(&bytes)?,
+            }
+        },
+        CtlCommand::Watch => {
+            request(&mut connection, Request::Subscribe).await?;
+            while let Some(response) = connection.recv().await? {
+                let Response::Event { event } = response else { continue };
+                let line = event.to_string();
+                if !line.is_empty() {
+                    println!("{}", line);
+                }
+                if let Event::StateChange { new_state: State::Quitting, .. } = event {
+                    break;
+                }
+            }
+        },
+    }
+    Ok(())
+}
+
+/// Send a request and wait for its answer, skipping any events that arrive in between.
+async fn request(connection: &mut Connection, request: Request) -> Result<Response> {
+    connection.send(&request).await?;
+    loop {
+        match connection.recv().await?.context("The server closed the connection")? {
+            Response::Event { .. } => (),
+            Response::Error { message } => bail!("{}", message),
+            response => return Ok(response),
+        }
+    }
+}
@@ -0,0 +1,302 @@
+use crate::gol::Params;
+use crate::net::{cluster::{PeerLink, WorkerRequest, WorkerResponse}, metrics::Metrics};
+use crate::util::cell::CellValue;
+use anyhow::{anyhow, bail, Context, Result};
+use std::io::{BufReader, BufWriter, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::ops::Range;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// `RemoteWorker` is the connection to a `gol-rs worker` process and the rows it is responsible for.
+struct RemoteWorker {
+    addr: String,
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+    rows: Range<usize>,
+}
+
+impl RemoteWorker {
+    /// Connect to the worker at `addr`, which is treated as failed whenever it takes longer than `timeout`.
+    fn connect(addr: &str, timeout: Duration) -> Result<Self> {
+        let socket_addr = addr.to_socket_addrs()?.next()
+            .with_context(|| format!("Cannot resolve worker {}", addr))?;
+        let stream = TcpStream::connect_timeout(&socket_addr, timeout)
+            .with_context(|| format!("Cannot connect to worker {}", addr))?;
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        Ok(RemoteWorker {
+            addr: addr.to_owned(),
+            reader: BufReader::new(stream.try_clone()?),
+            writer: BufWriter::new(stream),
+            rows: 0..0,
+        })
+    }
+
+    fn send(&mut self, request: &WorkerRequest) -> Result<()> {
+        request.write_to(&mut self.writer)
+            .and_then(|_| Ok(self.writer.flush()?))
+            .with_context(|| format!("Cannot send to worker {}", self.addr))
+    }
+
+    fn recv(&mut self, width: usize) -> Result<WorkerResponse> {
+        WorkerResponse::read_from(&mut self.reader, self.rows.len() * width)
+            .with_context(|| format!("Cannot receive from worker {}", self.addr))
+    }
+}
+
+/// `Failure` is a worker that did not answer a request properly, and why.
+/// A worker that only lost a halo link to a neighbour is kept and given a new strip.
+struct Failure {
+    index: usize,
+    error: anyhow::Error,
+    link: bool,
+}
+
+impl Failure {
+    fn new(index: usize, error: anyhow::Error) -> Self {
+        Failure { index, error, link: false }
+    }
+
+    /// The failure of a worker's response, which is only the worker's fault if it is not a broken halo link.
+    fn response(index: usize, response: WorkerResponse) -> Self {
+        match response {
+            WorkerResponse::LinkFailed { message } => Failure { index, error: anyhow!(message), link: true },
+            response => Failure::new(index, anyhow!("Unexpected response {:?}", response)),
+        }
+    }
+}
+
+/// How many times in a row the strips are assigned again only because of broken halo links, before giving up.
+const MAX_RELINKS: usize = 3;
+
+/// `Broker` splits the world into horizontal strips, one for each worker process,
+/// and steps them together. Workers keep their strip between turns and swap the rows
+/// around it directly with their neighbours, so only the cells that flipped are sent back.
+///
+/// When a worker fails, the world of the last completed turn is split again between
+/// the remaining workers and the turn is computed again, so no turn is lost or repeated.
+pub struct Broker {
+    width: usize,
+    height: usize,
+    params: Params,
+    workers: Vec<RemoteWorker>,
+    /// Whether every worker holds its strip of the world it was last stepped to.
+    assigned: bool,
+    /// The token of the next halo link, unique to this broker so stale links are never mixed up.
+    next_token: u64,
+    /// How many times in a row the strips were assigned again only because of broken halo links.
+    relinks: usize,
+}
+
+impl Broker {
+    /// Connect to the workers in `params.workers` and hand each of them a strip of `world`.
+    /// Workers that cannot be reached are left out, and those that fail are counted in `metrics`, if they are taken.
+    pub fn connect(params: &Params, world: &[CellValue], metrics: Option<&Metrics>) -> Result<Self> {
+        let mut workers = Vec::new();
+        for addr in &params.workers {
+            match RemoteWorker::connect(addr, params.worker_timeout) {
+                Ok(worker) => workers.push(worker),
+                Err(e) => log::warn!(target: "Broker", "{:#}", e),
+            }
+        }
+        if workers.is_empty() {
+            bail!("None of the workers {} can be reached", params.workers.join(", "));
+        }
+        // every worker needs at least one row
+        workers.truncate(params.image_height);
+        let mut broker = Broker {
+            width: params.image_width,
+            height: params.image_height,
+            params: params.clone(),
+            workers,
+            assigned: false,
+            next_token: first_token(),
+            relinks: 0,
+        };
+        broker.assign_all(world, metrics)?;
+        Ok(broker)
+    }
+
+    /// Compute the next turn of `world`, which has to be the world the workers were last stepped to.
+    /// The time each worker spent computing and the workers that failed are added to `metrics`, if they are taken.
+    ///
+    /// The workers send back the cells of their strip that flipped rather than only how many did, and the whole
+    /// world is copied here to apply them to. The distributor reports every flipped cell in its events, and hands
+    /// the world to outputs, checkpoints, edits and a failed worker's replacements between any two turns, so it
+    /// keeps its own copy up to date instead of gathering every strip over the network whenever one is needed.
+    pub fn step(&mut self, world: &[CellValue], metrics: Option<&Metrics>) -> Result<Vec<CellValue>> {
+        loop {
+            if !self.assigned {
+                self.assign_all(world, metrics)?;
+            }
+            match self.step_workers(world, metrics) {
+                Ok(new_world) => {
+                    self.relinks = 0;
+                    return Ok(new_world)
+                },
+                Err(failures) => self.remove(failures, metrics)?,
+            }
+        }
+    }
+
+    /// Hand every worker its strip again at the next step, as the world was edited since the last turn.
+    pub fn reload(&mut self) {
+        self.assigned = false;
+    }
+
+    /// Split `world` evenly between the workers, leaving out any that fail until the rest have their strips.
+    fn assign_all(&mut self, world: &[CellValue], metrics: Option<&Metrics>) -> Result<()> {
+        while let Err(failures) = self.assign(world) {
+            self.remove(failures, metrics)?;
+        }
+        Ok(())
+    }
+
+    fn assign(&mut self, world: &[CellValue]) -> Result<(), Vec<Failure>> {
+        let (width, height, count) = (self.width, self.height, self.workers.len());
+        let rule = self.params.rule;
+        for (i, worker) in self.workers.iter_mut().enumerate() {
+            worker.rows = i * height / count..(i + 1) * height / count;
+            log::debug!(target: "Broker", "Assigning rows {:?} to worker {}", worker.rows, worker.addr);
+        }
+
+        // the strip below a strip is always held by the next worker, wrapping around at the bottom
+        let mut links = vec![(None, None); count];
+        for i in 0..count {
+            let rows = &self.workers[i].rows;
+            if !rows.contains(&(rows.end % height)) {
+                let (token, next) = (self.next_token, (i + 1) % count);
+                self.next_token += 1;
+                links[i].1 = Some(PeerLink { addr: self.workers[next].addr.clone(), token });
+                links[next].0 = Some(token);
+            }
+        }
+
+        // halo links break whenever their neighbour does, so they are given up on before the broker does
+        let timeout = self.params.worker_timeout / 2;
+        let mut links = links.into_iter();
+        let responses = self.exchange(|worker| {
+            let (above, below) = links.next().unwrap();
+            WorkerRequest::Assign {
+                width,
+                height,
+                start: worker.rows.start,
+                rule,
+                timeout,
+                above,
+                below,
+                strip: world[worker.rows.start * width..worker.rows.end * width].to_vec(),
+            }
+        })?;
+        let failures = responses.into_iter().enumerate()
+            .filter(|(_, response)| *response != WorkerResponse::Assigned)
+            .map(|(index, response)| Failure::response(index, response))
+            .collect::<Vec<_>>();
+        if !failures.is_empty() {
+            return Err(failures)
+        }
+        self.assigned = true;
+        Ok(())
+    }
+
+    fn step_workers(&mut self, world: &[CellValue], metrics: Option<&Metrics>) -> Result<Vec<CellValue>, Vec<Failure>> {
+        let width = self.width;
+        let responses = self.exchange(|_| WorkerRequest::Step)?;
+
+        let mut new_world = world.to_vec();
+        let mut failures = Vec::new();
+        for (index, (worker, response)) in self.workers.iter().zip(responses).enumerate() {
+            let strip = &mut new_world[worker.rows.start * width..worker.rows.end * width];
+            match response {
+                WorkerResponse::Stepped { flipped, compute } => {
+                    if flipped.iter().any(|&i| i as usize >= strip.len()) {
+                        failures.push(Failure::new(index, anyhow!("Flipped a cell outside its strip")));
+                        continue;
+                    }
+                    if let Some(metrics) = metrics {
+                        metrics.add_compute_time(&worker.addr, compute);
+                    }
+                    for i in flipped {
+                        strip[i as usize].flip();
+                    }
+                },
+                response => failures.push(Failure::response(index, response)),
+            }
+        }
+        match failures.is_empty() {
+            true => Ok(new_world),
+            false => Err(failures),
+        }
+    }
+
+    /// Send every worker its request before waiting for any response, so the workers compute in parallel.
+    /// Every worker that was sent a request is waited for, even after another has failed,
+    /// so no response is left behind for the next exchange.
+    fn exchange<F>(&mut self, mut request: F) -> Result<Vec<WorkerResponse>, Vec<Failure>>
+    where
+        F: FnMut(&RemoteWorker) -> WorkerRequest,
+    {
+        let mut failures = Vec::new();
+        let mut sent = vec![false; self.workers.len()];
+        for (index, worker) in self.workers.iter_mut().enumerate() {
+            let request = request(worker);
+            match worker.send(&request) {
+                Ok(()) => sent[index] = true,
+                Err(error) => failures.push(Failure::new(index, error)),
+            }
+        }
+
+        let mut responses = Vec::with_capacity(self.workers.len());
+        for (index, worker) in self.workers.iter_mut().enumerate().filter(|(index, _)| sent[*index]) {
+            match worker.recv(self.width) {
+                Ok(response) => responses.push(response),
+                Err(error) => failures.push(Failure::new(index, error)),
+            }
+        }
+        match failures.is_empty() {
+            true => Ok(responses),
+            false => Err(failures),
+        }
+    }
+
+    /// Drop the workers that failed, so their rows are reassigned to the rest.
+    /// Workers that only lost a halo link are kept, as it was most likely their neighbour that failed.
+    fn remove(&mut self, mut failures: Vec<Failure>, metrics: Option<&Metrics>) -> Result<()> {
+        failures.sort_by_key(|failure| failure.index);
+        self.assigned = false;
+        let mut last_error = None;
+        let mut removed = false;
+        for failure in failures.into_iter().rev() {
+            let worker = &self.workers[failure.index];
+            if failure.link {
+                log::warn!(target: "Broker", "Worker {} lost a halo link: {:#}", worker.addr, failure.error);
+            } else {
+                log::warn!(
+                    target: "Broker",
+                    "Worker {} failed, reassigning rows {:?}: {:#}", worker.addr, worker.rows, failure.error
+                );
+                self.workers.remove(failure.index);
+                if let Some(metrics) = metrics {
+                    metrics.add_reassignment();
+                }
+                removed = true;
+            }
+            last_error = Some(failure.error);
+        }
+
+        self.relinks = if removed { 0 } else { self.relinks + 1 };
+        match (self.workers.is_empty(), self.relinks > MAX_RELINKS, last_error) {
+            (true, _, Some(error)) => Err(error.context("Every worker has failed")),
+            (_, true, Some(error)) => Err(error.context("The workers cannot link to each other")),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Pick the first token of a broker's halo links, so workers shared between brokers can tell their links apart.
+fn first_token() -> u64 {
+    let nanos = SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_nanos() as u64);
+    // tokens are never 0, which stands for no link, and leave plenty of room to count up
+    ((std::process::id() as u64) << 40 | nanos & 0xff_0000_0000) + 1
+}
@@ -0,0 +1,239 @@
+use crate::gol::event::Event;
+use crate::util::cell::CellCoord;
+use flume::{Receiver, Sender, TrySendError};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Notify;
+
+/// The number of events a subscriber that blocks the bus can fall behind by.
+const BLOCK_CAPACITY: usize = 1000;
+
+/// The most times a cell is listed in a coalesced frame. A cell that flipped more often is listed
+/// two or three times, whichever keeps it alive or dead as it ended up.
+const MAX_FLIPS_PER_CELL: usize = 3;
+
+/// `Policy` decides what happens to the events of a subscriber that falls behind.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Policy {
+    /// Keep every event, holding up the bus (and with it the distributor) once the subscriber is far behind.
+    Block,
+    /// Keep at most this many events, dropping the oldest to make room for the newest.
+    DropOldest(usize),
+    /// Keep every event except the flips and turn completions that queue up, which are merged into a single
+    /// frame that brings the subscriber straight to the latest board. The bus is never held up.
+    ///
+    /// A frame lists a cell once for every time it flipped, so a blinker does not look still, and ends with
+    /// the completion of its last turn, so a subscriber can tell how many turns it covers from the turn before.
+    Coalesce,
+}
+
+/// `EventBus` hands the events of a simulation to any number of subscribers,
+/// each buffering them by its own `Policy`.
+///
+/// ## Examples
+/// ``` ignore
+/// let mut bus = EventBus::new();
+/// let window = bus.subscribe(Policy::Coalesce);
+/// let harness = bus.subscribe(Policy::Block);
+/// tokio::spawn(gol::run(args, bus.sender(), key_presses));
+/// tokio::spawn(bus.run());
+/// ```
+pub struct EventBus {
+    events_tx: Sender<Event>,
+    events: Receiver<Event>,
+    subscribers: Vec<Subscriber>,
+}
+
+enum Subscriber {
+    Block(Sender<Event>),
+    /// The bus keeps a receiver of its own, to take the oldest event out of a full channel.
+    DropOldest(Sender<Event>, Receiver<Event>),
+    /// The sender is handed to the task forwarding the mailbox once the bus runs.
+    Coalesce(Arc<Mailbox>, Option<Sender<Event>>),
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        EventBus::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (events_tx, events) = flume::bounded(BLOCK_CAPACITY);
+        EventBus { events_tx, events, subscribers: Vec::new() }
+    }
+
+    /// The sender to publish events with, e.g. to hand to `gol::run`.
+    /// The bus stops once every sender is dropped.
+    pub fn sender(&self) -> Sender<Event> {
+        self.events_tx.clone()
+    }
+
+    /// Register a subscriber, which receives every event published after the bus starts running.
+    pub fn subscribe(&mut self, policy: Policy) -> Receiver<Event> {
+        match policy {
+            Policy::Block => {
+                let (tx, rx) = flume::bounded(BLOCK_CAPACITY);
+                self.subscribers.push(Subscriber::Block(tx));
+                rx
+            },
+            Policy::DropOldest(capacity) => {
+                let (tx, rx) = flume::bounded(capacity.max(1));
+                self.subscribers.push(Subscriber::DropOldest(tx, rx.clone()));
+                rx
+            },
+            Policy::Coalesce => {
+                // the subscriber only ever waits on a single event, the rest wait in the mailbox
+                let (tx, rx) = flume::bounded(1);
+                self.subscribers.push(Subscriber::Coalesce(Arc::new(Mailbox::default()), Some(tx)));
+                rx
+            },
+        }
+    }
+
+    /// Pass every event on to the subscribers until every sender is dropped, then let the subscribers go.
+    /// Subscribers that drop their receiver are forgotten.
+    pub async fn run(mut self) {
+        drop(self.events_tx);
+        for subscriber in self.subscribers.iter_mut() {
+            if let Subscriber::Coalesce(mailbox, tx) = subscriber {
+                if let Some(tx) = tx.take() {
+                    tokio::spawn(forward(Arc::clone(mailbox), tx));
+                }
+            }
+        }
+        while let Ok(event) = self.events.recv_async().await {
+            let mut gone = Vec::new();
+            for (i, subscriber) in self.subscribers.iter().enumerate() {
+                let delivered = match subscriber {
+                    Subscriber::Block(tx) => tx.send_async(event.clone()).await.is_ok(),
+                    Subscriber::DropOldest(tx, rx) => drop_oldest(tx, rx, event.clone()),
+                    Subscriber::Coalesce(mailbox, _) => mailbox.push(event.clone()),
+                };
+                if !delivered {
+                    gone.push(i);
+                }
+            }
+            for i in gone.into_iter().rev() {
+                self.subscribers.remove(i);
+            }
+        }
+        for subscriber in self.subscribers {
+            if let Subscriber::Coalesce(mailbox, _) = subscriber {
+                mailbox.close();
+            }
+        }
+    }
+}
+
+/// Send `event` without waiting, making room by dropping the oldest event if the channel is full.
+/// Returns `false` once the subscriber has dropped its receiver.
+fn drop_oldest(tx: &Sender<Event>, rx: &Receiver<Event>, mut event: Event) -> bool {
+    // the bus holds one of the receivers itself
+    if tx.receiver_count() <= 1 {
+        return false
+    }
+    loop {
+        match tx.try_send(event) {
+            Ok(()) => return true,
+            Err(TrySendError::Full(unsent)) => {
+                let _ = rx.try_recv();
+                event = unsent;
+            },
+            Err(TrySendError::Disconnected(_)) => return false,
+        }
+    }
+}
+
+/// `Mailbox` queues the events of a coalescing subscriber, merging flips while they wait.
+#[derive(Default)]
+struct Mailbox {
+    state: Mutex<MailboxState>,
+    changed: Notify,
+}
+
+#[derive(Default)]
+struct MailboxState {
+    queue: VecDeque<Event>,
+    /// Whether the bus has stopped, so no more events will arrive.
+    closed: bool,
+    /// Whether the subscriber has dropped its receiver.
+    gone: bool,
+}
+
+impl Mailbox {
+    /// Queue `event`, merging it into the frame waiting at the back of the queue if there is one.
+    /// Returns `false` once the subscriber has dropped its receiver.
+    fn push(&self, event: Event) -> bool {
+        let mut state = self.state.lock().unwrap();
+        if state.gone {
+            return false
+        }
+        let queue = &mut state.queue;
+        match event {
+            Event::CellsFlipped { completed_turns, cells } => {
+                // a frame is the flips of a turn followed by its completion, which the next completion replaces
+                let frame_waiting = matches!(queue.back(), Some(Event::TurnComplete { .. }))
+                    && matches!(queue.iter().nth_back(1), Some(Event::CellsFlipped { .. }));
+                if frame_waiting {
+                    queue.pop_back();
+                }
+                match queue.back_mut() {
+                    Some(Event::CellsFlipped { completed_turns: queued_turns, cells: queued }) => {
+                        *queued_turns = completed_turns;
+                        merge_flips(queued, cells);
+                    },
+                    _ => queue.push_back(Event::CellsFlipped { completed_turns, cells }),
+                }
+            },
+            event => queue.push_back(event),
+        }
+        drop(state);
+        self.changed.notify_one();
+        true
+    }
+
+    fn close(&self) {
+        self.state.lock().unwrap().closed = true;
+        self.changed.notify_one();
+    }
+}
+
+/// Merge the flips of a later turn into `queued`, listing a cell once for every time it flipped
+/// up to `MAX_FLIPS_PER_CELL`, in the order of the cells so the flips of a cell follow each other.
+fn merge_flips(queued: &mut Vec<CellCoord>, cells: Vec<CellCoord>) {
+    let mut flips = HashMap::<CellCoord, usize>::new();
+    for cell in queued.drain(..).chain(cells) {
+        *flips.entry(cell).or_default() += 1;
+    }
+    for (cell, count) in flips {
+        let listed = match count > MAX_FLIPS_PER_CELL {
+            true => MAX_FLIPS_PER_CELL - (count - MAX_FLIPS_PER_CELL) % 2,
+            false => count,
+        };
+        queued.extend(std::iter::repeat_n(cell, listed));
+    }
+    queued.sort_by_key(|cell| (cell.y, cell.x));
+}
+
+/// Hand the events in `mailbox` to the subscriber one at a time, until the bus stops or the subscriber goes.
+async fn forward(mailbox: Arc<Mailbox>, tx: Sender<Event>) {
+    loop {
+        let next = {
+            let mut state = mailbox.state.lock().unwrap();
+            match state.queue.pop_front() {
+                Some(event) => Some(event),
+                None if state.closed => return,
+                None => None,
+            }
+        };
+        match next {
+            Some(event) => if tx.send_async(event).await.is_err() {
+                mailbox.state.lock().unwrap().gone = true;
+                return
+            },
+            None => mailbox.changed.notified().await,
+        }
+    }
+}
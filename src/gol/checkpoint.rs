@@ -0,0 +1,132 @@
+use crate::gol::{rule::Rule, topology::Topology};
+use crate::util::cell::CellValue;
+use anyhow::{bail, ensure, Context, Result};
+use flate2::{read::ZlibDecoder, write::ZlibEncoder, Compression};
+use std::{io::{Read, Write}, sync::Arc};
+
+/// Every checkpoint starts with these bytes.
+const MAGIC: &[u8; 8] = b"GOLCKPT\0";
+
+/// The version of the checkpoint format written by this build.
+pub const CHECKPOINT_VERSION: u16 = 2;
+
+/// `CheckpointHeader` describes the run a checkpoint was taken from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CheckpointHeader {
+    pub image_width: usize,
+    pub image_height: usize,
+    pub completed_turns: u32,
+    pub rule: Rule,
+    pub topology: Topology,
+}
+
+/// `Checkpoint` is a snapshot of a run that can be written to disk and resumed from later.
+///
+/// All numbers are little endian, and the file is laid out as
+/// - the magic bytes `GOLCKPT\0` and the format version (`u16`),
+/// - the width, height and completed turns (`u32` each),
+/// - the topology (`u8`) and the rule in B/S notation (`u8` length followed by the text),
+/// - the length (`u64`) of the zlib-compressed board, packed 8 cells per byte row by row,
+/// - a CRC-32 (`u32`) of everything before it.
+#[derive(Debug, Clone)]
+pub struct Checkpoint {
+    pub header: CheckpointHeader,
+    pub world: Arc<Vec<CellValue>>,
+}
+
+impl CheckpointHeader {
+    /// Parse the header at the start of a checkpoint, without verifying the checksum.
+    pub fn from_bytes(reader: &mut impl Read) -> Result<Self> {
+        let mut magic = [0_u8; 8];
+        reader.read_exact(&mut magic).context("The checkpoint is truncated")?;
+        ensure!(&magic == MAGIC, "This is not a checkpoint");
+        let version = u16::from_le_bytes(read_array(reader)?);
+        ensure!(
+            version == CHECKPOINT_VERSION,
+            "Checkpoint version {} is not supported, expected version {}", version, CHECKPOINT_VERSION
+        );
+        let image_width = u32::from_le_bytes(read_array(reader)?) as usize;
+        let image_height = u32::from_le_bytes(read_array(reader)?) as usize;
+        let completed_turns = u32::from_le_bytes(read_array(reader)?);
+        let [topology] = read_array(reader)?;
+        let [rule_len] = read_array(reader)?;
+        let mut rule = vec![0_u8; rule_len as usize];
+        reader.read_exact(&mut rule).context("The checkpoint is truncated")?;
+        Ok(CheckpointHeader {
+            image_width,
+            image_height,
+            completed_turns,
+            rule: String::from_utf8(rule)?.parse().map_err(anyhow::Error::msg)?,
+            topology: topology.try_into()?,
+        })
+    }
+
+    fn to_bytes(&self, bytes: &mut Vec<u8>) -> Result<()> {
+        let rule = self.rule.to_string();
+        bytes.extend(MAGIC);
+        bytes.extend(CHECKPOINT_VERSION.to_le_bytes());
+        bytes.extend(u32::try_from(self.image_width)?.to_le_bytes());
+        bytes.extend(u32::try_from(self.image_height)?.to_le_bytes());
+        bytes.extend(self.completed_turns.to_le_bytes());
+        bytes.push(self.topology.as_u8());
+        bytes.push(u8::try_from(rule.len())?);
+        bytes.extend(rule.as_bytes());
+        Ok(())
+    }
+}
+
+impl Checkpoint {
+    /// Encode the checkpoint, compressing the world.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        ensure!(
+            self.world.len() == self.header.image_width * self.header.image_height,
+            "The world does not match the dimensions of the checkpoint"
+        );
+        let mut bytes = Vec::new();
+        self.header.to_bytes(&mut bytes)?;
+
+        let packed = self.world.chunks(8)
+            .map(|cells| cells.iter().enumerate()
+                .fold(0_u8, |byte, (i, cell)| byte | (cell.is_alive() as u8) << i))
+            .collect::<Vec<u8>>();
+        let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+        encoder.write_all(&packed)?;
+        let payload = encoder.finish()?;
+        bytes.extend((payload.len() as u64).to_le_bytes());
+        bytes.extend(payload);
+
+        let checksum = crc32fast::hash(&bytes);
+        bytes.extend(checksum.to_le_bytes());
+        Ok(bytes)
+    }
+
+    /// Decode a checkpoint, verifying its checksum.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        ensure!(bytes.len() > MAGIC.len() + 4, "The checkpoint is truncated");
+        let (content, checksum) = bytes.split_at(bytes.len() - 4);
+        if crc32fast::hash(content).to_le_bytes() != checksum {
+            bail!("The checksum of the checkpoint does not match, it may be corrupted");
+        }
+
+        let mut reader = content;
+        let header = CheckpointHeader::from_bytes(&mut reader)?;
+        let payload_len = u64::from_le_bytes(read_array(&mut reader)?) as usize;
+        ensure!(reader.len() == payload_len, "The checkpoint has a board of the wrong size");
+
+        let cells = header.image_width * header.image_height;
+        let mut packed = Vec::with_capacity(cells.div_ceil(8));
+        ZlibDecoder::new(reader).read_to_end(&mut packed)?;
+        ensure!(packed.len() == cells.div_ceil(8), "The checkpoint has a board of the wrong size");
+        let world = (0..cells)
+            .map(|i| if packed[i / 8] >> (i % 8) & 1 == 1 { CellValue::Alive } else { CellValue::Dead })
+            .collect();
+
+        Ok(Checkpoint { header, world: Arc::new(world) })
+    }
+}
+
+fn read_array<const N: usize>(reader: &mut impl Read) -> Result<[u8; N]> {
+    let mut buffer = [0_u8; N];
+    reader.read_exact(&mut buffer).context("The checkpoint is truncated")?;
+    Ok(buffer)
+}
@@ -1,91 +1,245 @@
+use crate::gol::broker::Broker;
+use crate::gol::checkpoint::{Checkpoint, CheckpointHeader};
+use crate::gol::topology::Topology;
+use crate::gol::edit::Edit;
 use crate::gol::event::{Event, State};
-use crate::gol::{Params, io::IoCommand};
+use crate::gol::lockstep::Lockstep;
+use crate::gol::pool::StripPool;
+use crate::gol::stats::{Statistics, TurnStats};
+use crate::net::metrics::Metrics;
+use crate::gol::{Params, io::{IoRequest, IoResponse}};
 use crate::util::cell::{CellCoord, CellValue};
 use crate::util::cell::CellValue::{Alive, Dead};
-use anyhow::Result;
-use flume::{Receiver, Sender};
+use anyhow::{bail, ensure, Context, Result};
+use flume::{Receiver, RecvError, Selector, Sender};
 use sdl2::keyboard::Keycode;
+use std::{sync::Arc, time::{Duration, Instant}};
+
+/// How often the number of alive cells is reported while the turns are executing.
+const ALIVE_CELLS_INTERVAL: Duration = Duration::from_secs(2);
 
 pub struct DistributorChannels {
     pub events: Option<Sender<Event>>,
     pub key_presses: Option<Receiver<Keycode>>,
-    pub io_command: Option<Sender<IoCommand>>,
-    pub io_idle: Option<Receiver<bool>>,
-    pub io_filename: Option<Sender<String>>,
-    pub io_input: Option<Receiver<CellValue>>,
-    pub io_output: Option<Sender<CellValue>>,
+    /// Edits to apply to the world between turns, if anything makes them.
+    pub edits: Option<Receiver<Edit>>,
+    pub io_requests: Option<Sender<IoRequest>>,
+    pub io_responses: Option<Receiver<IoResponse>>,
+    /// Where to add the time spent computing, if metrics are exported.
+    pub metrics: Option<Arc<Metrics>>,
+    /// Where to collect the statistics of every turn, if a frontend plots them.
+    pub statistics: Option<Arc<Statistics>>,
+    /// The other worlds to keep on the same turn as, if any.
+    pub lockstep: Option<Lockstep>,
+    /// Whether to report the cells that flip every turn, which takes a pass over the world.
+    pub flips: bool,
 }
 
-fn get_alive_cells(world: &Vec<Vec<CellValue>>, params: &Params) -> Vec<CellCoord> {
-    let mut alive_cells = Vec::new();
-    for y in 0..params.image_height {
-        for x in 0..params.image_width {
-            if world[y][x] == Alive {
-                alive_cells.push(CellCoord { x, y });
-            }
+/// The world is stored row by row, so the cell at (x, y) is `world[y * image_width + x]`.
+fn get_alive_cells(world: &[CellValue], params: &Params) -> Vec<CellCoord> {
+    world.iter().enumerate()
+        .filter(|(_, cell)| cell.is_alive())
+        .map(|(i, _)| CellCoord::new(i % params.image_width, i / params.image_width))
+        .collect()
+}
+
+fn get_flipped_cells(old: &[CellValue], new: &[CellValue], params: &Params) -> Vec<CellCoord> {
+    old.iter().zip(new).enumerate()
+        .filter(|(_, (old_cell, new_cell))| old_cell != new_cell)
+        .map(|(i, _)| CellCoord::new(i % params.image_width, i / params.image_width))
+        .collect()
+}
+
+/// `Engine` computes the turns, either on the distributor's own thread,
+/// on strips held by worker threads or on strips held by worker processes.
+enum Engine {
+    Single,
+    Threads(StripPool),
+    Distributed(Box<Broker>),
+}
+
+impl Engine {
+    fn start(params: &Params, world: &[CellValue], metrics: Option<&Metrics>) -> Result<Self> {
+        Ok(match (params.workers.is_empty(), params.threads) {
+            (false, _) => Engine::Distributed(Box::new(Broker::connect(params, world, metrics)?)),
+            (true, 0 | 1) => Engine::Single,
+            (true, _) => Engine::Threads(StripPool::start(params, world)),
+        })
+    }
+
+    fn step(&mut self, world: &[CellValue], params: &Params, metrics: Option<&Metrics>) -> Result<Vec<CellValue>> {
+        match self {
+            Engine::Single => {
+                let started = Instant::now();
+                let new_world = calculate_new_alive(world, params);
+                if let Some(metrics) = metrics {
+                    metrics.add_compute_time("distributor", started.elapsed());
+                }
+                Ok(new_world)
+            },
+            Engine::Threads(pool) => pool.step(world, metrics),
+            Engine::Distributed(broker) => broker.step(world, metrics),
+        }
+    }
+
+    /// Hand the strips out again, as `world` was edited since the last turn.
+    fn reload(&mut self, params: &Params, world: &[CellValue]) {
+        match self {
+            Engine::Single => (),
+            Engine::Threads(pool) => *pool = StripPool::start(params, world),
+            Engine::Distributed(broker) => broker.reload(),
+        }
+    }
+}
+
+/// Send a single request to the IO task and wait for its response.
+/// Checkpoints are saved without waiting for them, so their responses may come first and are only logged.
+/// A failed checkpoint does not stop the run, which keeps waiting for the response to `request`.
+fn io_request(channels: &DistributorChannels, request: IoRequest) -> Result<IoResponse> {
+    channels.io_requests.as_ref().context("The io_requests channel is None")?
+        .send(request).context("The IO task has stopped")?;
+    let responses = channels.io_responses.as_ref().context("The io_responses channel is None")?;
+    loop {
+        match responses.recv().context("The IO task has stopped")? {
+            IoResponse::CheckpointSaved { path } =>
+                log::debug!(target: "Distributor", "Checkpoint saved to {}", path.display()),
+            IoResponse::CheckpointFailed { path, error } =>
+                log::warn!(target: "Distributor", "Cannot save checkpoint to {}: {:#}", path.display(), error),
+            IoResponse::Failed(e) => return Err(e),
+            response => return Ok(response),
         }
     }
-    alive_cells
 }
 
 pub fn distributor(
-    params: Params,
+    mut params: Params,
     channels:  &DistributorChannels,
 ) -> Result<()> {
     //we need to use as_ref to access the value inside the option
     let events = channels.events.as_ref().expect("events channel missing").clone();
-    let io_command = channels.io_command.as_ref().expect("io_command channel missing").clone();
-    let io_filename = channels.io_filename.as_ref().expect("io_filename channel missing").clone();
+    let key_presses = channels.key_presses.as_ref().expect("key_presses channel missing");
 
-    // Create a 2D vector to store the initial world state
-    let mut matrix: Vec<Vec<CellValue>> = vec![vec![Dead; params.image_width]; params.image_height];
-    let imagename = format!("{}x{}", params.image_width, params.image_height);
-
-    // we have to use a block to avoid immutable borrowing
-    {
-        let io_input = channels.io_input.as_ref().expect("io_input channel missing");
-
-        io_command.send(IoCommand::IoInput)?;
-        io_filename.send(imagename)?;
-
-        for y in 0..params.image_height {
-            for x in 0..params.image_width {
-                matrix[y][x] = io_input.recv()?;
+    // Load the initial world state, which the IO task hands over in one piece,
+    // either from an image or from a checkpoint with the turns it had completed
+    let (mut world, mut turn) = match &params.resume {
+        Some(path) => match io_request(channels, IoRequest::LoadCheckpoint { path: path.clone() })? {
+            IoResponse::CheckpointLoaded(checkpoint) => {
+                ensure!(
+                    checkpoint.header == checkpoint_header(&params, checkpoint.header.completed_turns),
+                    "The checkpoint {} changed since the run was set up", path.display()
+                );
+                (checkpoint.world, checkpoint.header.completed_turns as usize)
+            },
+            response => bail!("Unexpected IO response {:?}", response),
+        },
+        None => {
+            let imagename = format!("{}x{}", params.image_width, params.image_height);
+            match io_request(channels, IoRequest::Input { filename: imagename })? {
+                IoResponse::Input(board) => {
+                    // boards read from stdin only know their dimensions once they are loaded
+                    params.image_width = board.width;
+                    params.image_height = board.height;
+                    (Arc::new(board.cells), 0)
+                },
+                response => bail!("Unexpected IO response {:?}", response),
             }
-        }
+        },
+    };
+
+    // let the GUI know about every cell that is alive in the loaded image
+    let alive = get_alive_cells(&world, &params);
+    if let Some(statistics) = &channels.statistics {
+        statistics.push(TurnStats { completed_turns: turn as u32, population: alive.len(), ..TurnStats::default() });
     }
+    events.send(Event::CellsFlipped {
+        completed_turns: turn as u32,
+        cells: alive,
+    })?;
 
     events.send(Event::StateChange {
-        completed_turns: 0,
+        completed_turns: turn as u32,
         new_state: State::Executing,
     })?;
 
-    let mut turn = 0;
+    // hand the world to the worker processes or threads, if it is split into strips
+    let mut engine = Engine::start(&params, &world, channels.metrics.as_deref())?;
+
+    let mut last_checkpoint = Instant::now();
+    let mut last_alive_cells = Instant::now();
     while turn < params.turns {
+        if let Some(edits) = &channels.edits {
+            let edits = edits.try_iter().collect::<Vec<Edit>>();
+            if apply_edits(&mut world, edits, turn as u32, &params, channels)? {
+                engine.reload(&params, &world);
+            }
+        }
+        let key_press = match &channels.lockstep {
+            Some(lockstep) => lockstep.try_key_press(key_presses),
+            None => key_presses.try_recv().ok(),
+        };
+        match key_press {
+            Some(Keycode::Q | Keycode::K) => break,
+            Some(Keycode::S) => make_output(&world, turn as u32, &params, channels)?,
+            Some(Keycode::P) if !pause(&mut world, &mut engine, turn as u32, &params, channels)? => break,
+            _ => (),
+        }
+
         // calculate new alive cells from the current world state
-        let new_alive = calculate_new_alive(&matrix, &params);
+        let new_alive = engine.step(&world, &params, channels.metrics.as_deref())?;
 
-        // output the current state
-        make_output(&new_alive, &params, channels)?;
+        // report the cells that changed in this turn before completing it
+        if channels.flips {
+            events.send(Event::CellsFlipped {
+                completed_turns: turn as u32 + 1,
+                cells: get_flipped_cells(&world, &new_alive, &params),
+            })?;
+        }
+        if let Some(statistics) = &channels.statistics {
+            statistics.push(TurnStats::between(&world, &new_alive, turn as u32 + 1));
+        }
 
         // update the current world state for the next iteration
-        matrix = new_alive;
+        world = Arc::new(new_alive);
         turn += 1;
+
+        events.send(Event::TurnComplete {
+            completed_turns: turn as u32,
+        })?;
+
+        if last_alive_cells.elapsed() >= ALIVE_CELLS_INTERVAL {
+            events.send(Event::AliveCellsCount {
+                completed_turns: turn as u32,
+                cells_count: world.iter().filter(|cell| cell.is_alive()).count() as u32,
+            })?;
+            last_alive_cells = Instant::now();
+        }
+
+        if params.stdout.is_some() && params.stdout_interval != 0
+            && turn % params.stdout_interval == 0 && turn < params.turns {
+            make_output(&world, turn as u32, &params, channels)?;
+        }
+
+        if params.checkpoint.is_some() && last_checkpoint.elapsed() >= params.checkpoint_interval {
+            // the last checkpoint has to be written before the next one is, so they never pile up
+            io_request(channels, IoRequest::CheckIdle)?;
+            make_checkpoint(&world, turn as u32, &params, channels)?;
+            last_checkpoint = Instant::now();
+        }
     }
 
     events.send(Event::FinalTurnComplete {
         completed_turns: turn as u32,
-        alive: get_alive_cells(&matrix, &params),
+        alive: get_alive_cells(&world, &params),
     })?;
 
+    // output the final state
+    make_output(&world, turn as u32, &params, channels)?;
+    if params.checkpoint.is_some() {
+        make_checkpoint(&world, turn as u32, &params, channels)?;
+    }
 
-    {
-        let io_idle = channels.io_idle.as_ref().expect("io_idle channel missing");
-
-        // Ensure Io has completed any output before exiting
-        io_command.send(IoCommand::IoCheckIdle)?;
-        io_idle.recv()?;
-    } // `io_idle` immutable borrow ends here
+    // Ensure Io has completed any output and checkpoint before exiting
+    io_request(channels, IoRequest::CheckIdle)?;
 
     events.send(Event::StateChange {
         completed_turns: turn as u32,
@@ -95,74 +249,135 @@ pub fn distributor(
     Ok(())
 }
 
-pub fn make_output(
-    world: &Vec<Vec<CellValue>>,
+/// Apply `edits` to `world` between turns, reporting the cells they flip with the turns completed so far.
+/// Returns whether any cell flipped.
+fn apply_edits(
+    world: &mut Arc<Vec<CellValue>>,
+    edits: Vec<Edit>,
+    turn: u32,
     params: &Params,
     channels: &DistributorChannels,
-) -> Result<()> {
-
-    let io_command = channels.io_command.as_ref().expect("io_command channel missing").clone();
-    let io_filename = channels.io_filename.as_ref().expect("io_filename channel missing").clone();
-    let io_output = channels.io_output.as_ref().expect("io_output channel missing").clone();
-
-    io_command.send(IoCommand::IoOutput)?;
-    io_filename.send("out".to_string())?;
+) -> Result<bool> {
+    let events = channels.events.as_ref().expect("events channel missing");
+    let mut flipped = Vec::new();
+    for edit in edits {
+        // the IO task may still hold the world for an output, in which case it is copied
+        let cells: &mut Vec<CellValue> = Arc::make_mut(world);
+        flipped.extend(edit.apply(cells, params.image_width, params.image_height));
+    }
+    if flipped.is_empty() {
+        return Ok(false)
+    }
+    events.send(Event::CellsFlipped { completed_turns: turn, cells: flipped })?;
+    Ok(true)
+}
 
-    for y in 0..params.image_height {
-        for x in 0..params.image_width {
-            io_output.send(world[y][x])?;
+/// Pause execution until `P` is pressed again, while still handling output requests and edits.
+/// Returns `false` if the user quit or shut down while paused.
+fn pause(
+    world: &mut Arc<Vec<CellValue>>,
+    engine: &mut Engine,
+    turn: u32,
+    params: &Params,
+    channels: &DistributorChannels,
+) -> Result<bool> {
+    let events = channels.events.as_ref().expect("events channel missing");
+    let key_presses = channels.key_presses.as_ref().expect("key_presses channel missing");
+    events.send(Event::StateChange { completed_turns: turn, new_state: State::Pause })?;
+    loop {
+        let (key_press, edit) = match (&channels.lockstep, &channels.edits) {
+            // worlds in lockstep wait for the key presses together, and only take edits along with them
+            (Some(lockstep), _) => (Some(lockstep.key_press(key_presses).ok_or(RecvError::Disconnected)), None),
+            // an edit wakes the world up to show it, until nothing can make edits any more
+            (None, Some(edits)) if !edits.is_disconnected() => Selector::new()
+                .recv(key_presses, |key_press| (Some(key_press), None))
+                .recv(edits, |edit| (None, edit.ok()))
+                .wait(),
+            _ => (Some(key_presses.recv()), None),
+        };
+        // every edit made so far comes before the key press, whichever the selector woke up for
+        if let Some(edits) = &channels.edits {
+            let edits = edit.into_iter().chain(edits.try_iter()).collect::<Vec<Edit>>();
+            if apply_edits(world, edits, turn, params, channels)? {
+                engine.reload(params, world);
+            }
+        }
+        let Some(key_press) = key_press else { continue };
+        match key_press {
+            Ok(Keycode::P) => {
+                events.send(Event::StateChange { completed_turns: turn, new_state: State::Executing })?;
+                return Ok(true)
+            },
+            Ok(Keycode::S) => make_output(world, turn, params, channels)?,
+            Ok(Keycode::Q | Keycode::K) | Err(_) => return Ok(false),
+            Ok(_) => (),
         }
     }
+}
 
+/// Ask the IO task to write `world` after `turn` turns, and report the output once it is complete.
+/// The world is shared with the IO task, so no cells are copied.
+pub fn make_output(
+    world: &Arc<Vec<CellValue>>,
+    turn: u32,
+    params: &Params,
+    channels: &DistributorChannels,
+) -> Result<()> {
+    let events = channels.events.as_ref().expect("events channel missing");
+    let filename = format!("{}x{}x{}", params.image_width, params.image_height, turn);
+    match io_request(channels, IoRequest::Output { filename, world: Arc::clone(world) })? {
+        IoResponse::OutputComplete { filename } => events.send(Event::ImageOutputComplete {
+            completed_turns: turn,
+            filename,
+        })?,
+        response => bail!("Unexpected IO response {:?}", response),
+    }
+    Ok(())
+}
 
-    {
-        let io_idle = channels.io_idle.as_ref().expect("io_idle channel missing");
-        io_command.send(IoCommand::IoCheckIdle)?;
-        io_idle.recv()?;
+fn checkpoint_header(params: &Params, completed_turns: u32) -> CheckpointHeader {
+    CheckpointHeader {
+        image_width: params.image_width,
+        image_height: params.image_height,
+        completed_turns,
+        rule: params.rule,
+        // the world always wraps around at its edges
+        topology: Topology::Torus,
     }
+}
 
+/// Ask the IO task to write a checkpoint of `world` after `turn` turns to the checkpoint path,
+/// without waiting for it to be compressed and synced. The world is shared with the IO task, so no cells are copied,
+/// and the response is collected by whichever request to the IO task comes next, which only logs a failure.
+pub fn make_checkpoint(
+    world: &Arc<Vec<CellValue>>,
+    turn: u32,
+    params: &Params,
+    channels: &DistributorChannels,
+) -> Result<()> {
+    let path = params.checkpoint.clone().context("No checkpoint path was given")?;
+    let checkpoint = Checkpoint { header: checkpoint_header(params, turn), world: Arc::clone(world) };
+    log::debug!(target: "Distributor", "Saving a checkpoint of turn {} to {}", turn, path.display());
+    channels.io_requests.as_ref().context("The io_requests channel is None")?
+        .send(IoRequest::SaveCheckpoint { path, checkpoint }).context("The IO task has stopped")?;
     Ok(())
 }
 
-fn calculate_new_alive(world: &Vec<Vec<CellValue>>, params: &Params) -> Vec<Vec<CellValue>> {
-    let mut neighbours = vec![vec![0; params.image_width]; params.image_height];
-
-    for y in 0..params.image_height {
-        for x in 0..params.image_width {
-            if world[y][x] == Alive {
-                for i in -1..=1 {
-                    for j in -1..=1 {
-                        if i == 0 && j == 0 {
-                            continue;
-                        }
-
-                        let x_coord = ((x as isize + i + params.image_width as isize)
-                            % params.image_width as isize) as usize;
-                        let y_coord = ((y as isize + j + params.image_height as isize)
-                            % params.image_height as isize) as usize;
-
-                        neighbours[y_coord][x_coord] += 1;
-                    }
-                }
-            }
-        }
-    }
+fn calculate_new_alive(world: &[CellValue], params: &Params) -> Vec<CellValue> {
+    let (width, height) = (params.image_width, params.image_height);
+    let mut new_world = vec![Dead; width * height];
 
-    let mut new_world = world.clone();
+    for y in 0..height {
+        // the world wraps around at its edges
+        let rows = [(y + height - 1) % height * width, y * width, (y + 1) % height * width];
+        for x in 0..width {
+            let columns = [(x + width - 1) % width, x, (x + 1) % width];
+            let num_neighbours = rows.iter()
+                .flat_map(|row| columns.iter().map(move |column| row + column))
+                .filter(|&i| i != y * width + x && world[i] == Alive)
+                .count();
 
-    for y in 0..params.image_height {
-        for x in 0..params.image_width {
-            let num_neighbours = neighbours[y][x];
-            if world[y][x] == Alive {
-                if num_neighbours < 2 || num_neighbours > 3 {
-                    new_world[y][x] = Dead;
-                }
-                //
-            } else {
-                if num_neighbours == 3 {
-                    new_world[y][x] = Alive;
-                }
-            }
+            new_world[y * width + x] = params.rule.next(world[y * width + x], num_neighbours);
         }
     }
 
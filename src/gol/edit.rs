@@ -0,0 +1,38 @@
+use crate::util::cell::{CellCoord, CellValue};
+
+/// `Edit` is a change to the world made between two turns, e.g. with the selection tools of the SDL window.
+/// Edits go through the distributor, which reports the cells they flip like those of any turn,
+/// so the world and everything following its events stay consistent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Edit {
+    /// Set every cell to the value given with it. Cells outside the world are left out.
+    Set { cells: Vec<(CellCoord, CellValue)> },
+    /// Flip every cell, so the dead ones come alive and the live ones die. Cells outside the world are left out.
+    Flip { cells: Vec<CellCoord> },
+}
+
+impl Edit {
+    /// Apply the edit to `world`, a `width`x`height` world stored row by row,
+    /// returning the cells it flipped.
+    pub fn apply(&self, world: &mut [CellValue], width: usize, height: usize) -> Vec<CellCoord> {
+        match self {
+            Edit::Set { cells } => cells.iter()
+                .filter(|(cell, _)| cell.x < width && cell.y < height)
+                .filter_map(|&(cell, value)| {
+                    let current = &mut world[cell.y * width + cell.x];
+                    (*current != value).then(|| {
+                        *current = value;
+                        cell
+                    })
+                })
+                .collect(),
+            Edit::Flip { cells } => cells.iter()
+                .filter(|cell| cell.x < width && cell.y < height)
+                .map(|&cell| {
+                    world[cell.y * width + cell.x].flip();
+                    cell
+                })
+                .collect(),
+        }
+    }
+}
@@ -1,8 +1,9 @@
 use crate::util::cell::CellCoord;
+use serde::{Deserialize, Serialize};
 use std::fmt::Display;
 
 /// State represents a change in the state of execution.
-#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum State {
     #[default]
     Executing,
@@ -18,7 +19,7 @@ impl Display for State {
 
 /// `Event` represents any Game of Life event that needs to be communicated to the user.
 #[allow(dead_code)]
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum Event {
     /// `AliveCellsCount` is an Event notifying the user about the number of currently alive cells.
     /// This Event should be sent every 2s.
@@ -0,0 +1,260 @@
+use crate::gol::rule::Rule;
+use crate::util::{cell::{CellCoord, CellValue}, traits::AsBytes};
+use anyhow::{anyhow, bail, ensure, Context, Result};
+use clap::ValueEnum;
+use std::{fmt::Write, path::Path};
+
+/// The most cells a pattern may claim in its header, so that a hostile file cannot take all the memory.
+pub const MAX_PATTERN_CELLS: usize = 1 << 26;
+
+/// Format represents the file formats a board can be read from and written to.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Binary portable grey map, with dead cells 0 and alive cells 255.
+    #[default]
+    Pgm,
+    /// Run length encoded pattern, as used by most Game of Life tools.
+    Rle,
+    /// Plaintext pattern, with `.` for dead cells and `O` for alive cells.
+    Cells,
+}
+
+impl Format {
+    /// Guess the format of a file from its content.
+    pub fn detect(bytes: &[u8]) -> Format {
+        let text = String::from_utf8_lossy(&bytes[..bytes.len().min(4096)]);
+        if bytes.starts_with(b"P5") || bytes.starts_with(b"P2") {
+            Format::Pgm
+        } else if text.lines().any(|line| line.trim_start().starts_with("x ") || line.trim_start().starts_with("x=")) {
+            Format::Rle
+        } else {
+            Format::Cells
+        }
+    }
+
+    /// Guess the format of a file from its extension.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<Format> {
+        match path.as_ref().extension()?.to_str()?.to_ascii_lowercase().as_str() {
+            "pgm" => Some(Format::Pgm),
+            "rle" => Some(Format::Rle),
+            "cells" | "txt" => Some(Format::Cells),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Format::Pgm => "pgm",
+            Format::Rle => "rle",
+            Format::Cells => "cells",
+        }
+    }
+}
+
+/// `Board` is a world read from or written to a file, stored row by row.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Board {
+    pub width: usize,
+    pub height: usize,
+    pub cells: Vec<CellValue>,
+}
+
+impl Board {
+    pub fn new(width: usize, height: usize) -> Self {
+        Board { width, height, cells: vec![CellValue::Dead; width * height] }
+    }
+
+    pub fn from_alive_cells(width: usize, height: usize, alive: &[CellCoord]) -> Self {
+        let mut board = Board::new(width, height);
+        alive.iter().for_each(|cell| board.cells[cell.y * width + cell.x] = CellValue::Alive);
+        board
+    }
+
+    pub fn alive_cells(&self) -> Vec<CellCoord> {
+        self.cells.iter().enumerate()
+            .filter(|(_, cell)| cell.is_alive())
+            .map(|(i, _)| CellCoord::new(i % self.width, i / self.width))
+            .collect()
+    }
+
+    /// Decode a board, guessing its format from its content.
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        Board::decode_as(bytes, Format::detect(bytes))
+    }
+
+    pub fn decode_as(bytes: &[u8], format: Format) -> Result<Self> {
+        match format {
+            Format::Pgm => {
+                let pgm = image::load_from_memory(bytes).context("Cannot decode the PGM image")?;
+                let (width, height) = (pgm.width() as usize, pgm.height() as usize);
+                let cells = pgm.into_luma8().into_raw().into_iter()
+                    .map(|byte| if byte > 127 { CellValue::Alive } else { CellValue::Dead })
+                    .collect();
+                Ok(Board { width, height, cells })
+            },
+            Format::Rle => decode_rle(&String::from_utf8_lossy(bytes)),
+            Format::Cells => decode_cells(&String::from_utf8_lossy(bytes)),
+        }
+    }
+
+    /// Encode the board, noting `rule` in the formats that can hold it.
+    pub fn encode(&self, format: Format, rule: &Rule) -> Vec<u8> {
+        Board::encode_world(&self.cells, self.width, self.height, format, rule)
+    }
+
+    /// Encode a `width`x`height` world stored row by row, without copying it into a `Board` first.
+    pub fn encode_world(
+        world: &[CellValue],
+        width: usize,
+        height: usize,
+        format: Format,
+        rule: &Rule,
+    ) -> Vec<u8> {
+        match format {
+            Format::Pgm => {
+                let mut bytes = format!("P5\n{} {}\n255\n", width, height).into_bytes();
+                bytes.extend_from_slice(world.as_bytes());
+                bytes
+            },
+            Format::Rle => encode_rle(world, width, height, rule).into_bytes(),
+            Format::Cells => world.chunks(width.max(1))
+                .fold(String::new(), |mut text, row| {
+                    text.extend(row.iter().map(|cell| if cell.is_alive() { 'O' } else { '.' }));
+                    text.push('\n');
+                    text
+                })
+                .into_bytes(),
+        }
+    }
+
+    /// Place the board in the middle of a `width`x`height` world.
+    /// Images have to match the world exactly, while smaller patterns are centred in it.
+    pub fn fit(self, width: usize, height: usize, format: Format) -> Result<Self> {
+        if (self.width, self.height) == (width, height) {
+            return Ok(self)
+        }
+        if format == Format::Pgm || self.width > width || self.height > height {
+            bail!(
+                "The board is {}x{}, but the given dimensions are {}x{}",
+                self.width, self.height, width, height
+            );
+        }
+        let (offset_x, offset_y) = ((width - self.width) / 2, (height - self.height) / 2);
+        let mut board = Board::new(width, height);
+        for (y, row) in self.cells.chunks(self.width.max(1)).enumerate() {
+            let start = (y + offset_y) * width + offset_x;
+            board.cells[start..start + row.len()].copy_from_slice(row);
+        }
+        Ok(board)
+    }
+}
+
+fn decode_cells(text: &str) -> Result<Board> {
+    let rows = text.lines()
+        .filter(|line| !line.starts_with('!'))
+        .map(|line| line.trim_end())
+        .collect::<Vec<&str>>();
+    let width = rows.iter().map(|row| row.chars().count()).max().unwrap_or_default();
+    ensure!(
+        width.saturating_mul(rows.len()) <= MAX_PATTERN_CELLS,
+        "The plaintext pattern is {}x{}, which is more than {} cells", width, rows.len(), MAX_PATTERN_CELLS
+    );
+    let mut board = Board::new(width, rows.len());
+    for (y, row) in rows.iter().enumerate() {
+        for (x, c) in row.chars().enumerate() {
+            board.cells[y * width + x] = match c {
+                '.' | ' ' => CellValue::Dead,
+                'O' | 'o' | '*' | 'X' | 'x' => CellValue::Alive,
+                _ => bail!("Unexpected '{}' at line {} of the plaintext pattern", c, y + 1),
+            };
+        }
+    }
+    Ok(board)
+}
+
+fn decode_rle(text: &str) -> Result<Board> {
+    let mut lines = text.lines().filter(|line| !line.trim_start().starts_with('#'));
+    let header = lines.next().context("The RLE pattern has no header")?;
+    let mut dimensions = header.split(',')
+        .filter_map(|field| field.split_once('='))
+        .map(|(key, value)| (key.trim(), value.trim()));
+    let mut dimension = |name: &str| -> Result<usize> {
+        dimensions.find(|(key, _)| *key == name)
+            .with_context(|| format!("The RLE header has no {}", name))?.1
+            .parse().with_context(|| format!("The RLE header has an invalid {}", name))
+    };
+    let (width, height) = (dimension("x")?, dimension("y")?);
+    ensure!(
+        width.checked_mul(height).is_some_and(|cells| cells <= MAX_PATTERN_CELLS),
+        "The RLE pattern is {}x{}, which is more than {} cells", width, height, MAX_PATTERN_CELLS
+    );
+
+    let mut board = Board::new(width, height);
+    let (mut x, mut y, mut count) = (0_usize, 0_usize, None::<usize>);
+    let too_long = || anyhow!("The RLE pattern has a run longer than it can hold");
+    'body: for c in lines.flat_map(str::chars) {
+        match c {
+            '0'..='9' => count = Some(
+                count.unwrap_or_default().checked_mul(10)
+                    .and_then(|count| count.checked_add(c.to_digit(10).unwrap() as usize))
+                    .ok_or_else(too_long)?
+            ),
+            '!' => break 'body,
+            '$' => {
+                y = y.checked_add(count.take().unwrap_or(1)).ok_or_else(too_long)?;
+                x = 0;
+            },
+            'b' | '.' => x = x.checked_add(count.take().unwrap_or(1)).ok_or_else(too_long)?,
+            c if c.is_ascii_alphabetic() => {
+                let run = count.take().unwrap_or(1);
+                ensure!(
+                    x.checked_add(run).is_some_and(|end| end <= width) && y < height,
+                    "The RLE pattern is larger than its header says"
+                );
+                board.cells[y * width + x..y * width + x + run].fill(CellValue::Alive);
+                x += run;
+            },
+            c if c.is_whitespace() => (),
+            _ => bail!("Unexpected '{}' in the RLE pattern", c),
+        }
+    }
+    Ok(board)
+}
+
+fn encode_rle(world: &[CellValue], width: usize, height: usize, rule: &Rule) -> String {
+    let mut runs = Vec::<(usize, char)>::new();
+    let push = |runs: &mut Vec<(usize, char)>, count: usize, tag: char| match runs.last_mut() {
+        Some((last_count, last_tag)) if *last_tag == tag => *last_count += count,
+        _ => runs.push((count, tag)),
+    };
+    for row in world.chunks(width.max(1)) {
+        // trailing dead cells of a row are implied
+        let len = row.iter().rposition(|cell| cell.is_alive()).map_or(0, |i| i + 1);
+        for cell in &row[..len] {
+            push(&mut runs, 1, if cell.is_alive() { 'o' } else { 'b' });
+        }
+        push(&mut runs, 1, '$');
+    }
+    // trailing empty rows are implied as well
+    while let Some((_, '$')) = runs.last() {
+        runs.pop();
+    }
+
+    let mut text = format!("x = {}, y = {}, rule = {}\n", width, height, rule);
+    let mut line_len = 0;
+    for (count, tag) in runs.into_iter().chain([(1, '!')]) {
+        let mut run = String::new();
+        if count > 1 {
+            let _ = write!(run, "{}", count);
+        }
+        run.push(tag);
+        if line_len + run.len() > 70 {
+            text.push('\n');
+            line_len = 0;
+        }
+        line_len += run.len();
+        text.push_str(&run);
+    }
+    text.push('\n');
+    text
+}
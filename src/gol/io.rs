@@ -1,90 +1,216 @@
-use crate::gol::Params;
-use crate::util::{cell::CellValue, traits::AsBytes};
-use anyhow::{Context, Result};
+use crate::gol::{checkpoint::{Checkpoint, CheckpointHeader}, format::{Board, Format}, Params};
+use crate::net::metrics::Metrics;
+use crate::util::cell::CellValue;
+use anyhow::{ensure, Context, Result};
 use flume::{Receiver, Sender};
-use tokio::{fs::{create_dir_all, File}, io::{AsyncReadExt, AsyncWriteExt, BufWriter}};
+use std::{borrow::Cow, os::{fd::AsFd, unix::fs::FileTypeExt}, path::{Path, PathBuf}, sync::Arc};
+use tokio::{fs::{create_dir_all, File}, io::{AsyncReadExt, AsyncWriteExt}, sync::OnceCell};
 
-#[derive(Debug, PartialEq, Eq)]
-pub enum IoCommand {
-    IoCheckIdle,
-    IoInput,
-    IoOutput,
+/// `IoRequest` is a request from the distributor to the IO task.
+/// Every request is answered by exactly one `IoResponse`, in the order the requests were sent.
+#[derive(Debug)]
+pub enum IoRequest {
+    /// Load the image named `filename` (or the input given in `Params`) and answer with `IoResponse::Input`.
+    Input { filename: String },
+    /// Write `world` to the image named `filename` (or to stdout when `Params` asks for it)
+    /// and answer with `IoResponse::OutputComplete`.
+    /// The world is shared with the distributor rather than copied.
+    Output { filename: String, world: Arc<Vec<CellValue>> },
+    /// Write `checkpoint` to `path` and answer with `IoResponse::CheckpointSaved`,
+    /// or with `IoResponse::CheckpointFailed` if it could not be written.
+    /// The file is replaced atomically, so a crash never leaves a half-written checkpoint behind.
+    SaveCheckpoint { path: PathBuf, checkpoint: Checkpoint },
+    /// Load the checkpoint at `path` and answer with `IoResponse::CheckpointLoaded`.
+    LoadCheckpoint { path: PathBuf },
+    /// Answer with `IoResponse::Idle` once every previous request has been handled.
+    CheckIdle,
+}
+
+/// `IoResponse` is the answer of the IO task to an `IoRequest`.
+#[derive(Debug)]
+pub enum IoResponse {
+    /// The loaded world, handed over to the distributor.
+    /// Its dimensions are the ones in `Params`, or the ones of the input if those were not given.
+    Input(Board),
+    OutputComplete { filename: String },
+    CheckpointSaved { path: PathBuf },
+    /// The checkpoint to `path` could not be written. The distributor does not wait for checkpoints,
+    /// so this is told apart from the failures of the requests it waits for.
+    CheckpointFailed { path: PathBuf, error: anyhow::Error },
+    CheckpointLoaded(Checkpoint),
+    Idle,
+    /// The request could not be handled.
+    Failed(anyhow::Error),
 }
 
 pub struct IoChannels {
-    pub command: Option<Receiver<IoCommand>>,
-    pub idle: Option<Sender<bool>>,
-    pub filename: Option<Receiver<String>>,
-    pub input: Option<Sender<CellValue>>,
-    pub output: Option<Receiver<CellValue>>,
+    pub requests: Option<Receiver<IoRequest>>,
+    pub responses: Option<Sender<IoResponse>>,
+    /// Where to count the bytes written, if metrics are exported.
+    pub metrics: Option<Arc<Metrics>>,
 }
 
 struct IoState {
     params: Params,
-    channels: IoChannels,
+    metrics: Option<Arc<Metrics>>,
 }
 
-pub async fn start_io(params: Params, channels: IoChannels) {
-    let mut io = IoState { params, channels };
-    let command = io.channels.command
-        .take().context("The command channel is None").unwrap();
-    let idle = io.channels.idle
-        .take().context("The idle channel is None").unwrap();
-    loop {
-        match command.recv_async().await {
-            Ok(IoCommand::IoInput) => io.read_pgm_image().await.unwrap(),
-            Ok(IoCommand::IoOutput) => io.write_pgm_image().await.unwrap(),
-            Ok(IoCommand::IoCheckIdle) => idle.send_async(true).await.unwrap(),
-            Err(_) => break,
+pub async fn start_io(params: Params, mut channels: IoChannels) {
+    let mut io = IoState { params, metrics: channels.metrics.take() };
+    let requests = channels.requests
+        .take().context("The requests channel is None").unwrap();
+    let responses = channels.responses
+        .take().context("The responses channel is None").unwrap();
+    while let Ok(request) = requests.recv_async().await {
+        let response = match request {
+            IoRequest::Input { filename } => io.read_board(&filename).await
+                .map(IoResponse::Input),
+            IoRequest::Output { filename, world } => io.write_board(&filename, &world).await
+                .map(|_| IoResponse::OutputComplete { filename }),
+            IoRequest::SaveCheckpoint { path, checkpoint } => match save_checkpoint(&path, checkpoint).await {
+                Ok(written) => {
+                    io.count_written(written);
+                    Ok(IoResponse::CheckpointSaved { path })
+                },
+                Err(error) => Ok(IoResponse::CheckpointFailed { path, error }),
+            },
+            IoRequest::LoadCheckpoint { path } => load_checkpoint(&path).await
+                .map(IoResponse::CheckpointLoaded),
+            IoRequest::CheckIdle => Ok(IoResponse::Idle),
+        };
+        if responses.send_async(response.unwrap_or_else(IoResponse::Failed)).await.is_err() {
+            break;
         }
     }
 }
 
+/// Check whether `path` stands for stdin or stdout, i.e. is `-`.
+pub fn is_std_stream<P: AsRef<Path>>(path: P) -> bool {
+    path.as_ref().as_os_str() == "-"
+}
+
+/// Check whether a board is piped in on stdin, i.e. stdin is a pipe or a file with something in it.
+/// A terminal or `/dev/null` is not, so a run started from either loads its image as usual.
+pub fn stdin_is_piped() -> bool {
+    let Ok(fd) = std::io::stdin().as_fd().try_clone_to_owned() else { return false };
+    match std::fs::File::from(fd).metadata() {
+        Ok(metadata) => metadata.file_type().is_fifo() || (metadata.is_file() && metadata.len() > 0),
+        Err(_) => false,
+    }
+}
+
+/// The board piped in on stdin, which can only be read once but is needed to resolve the dimensions
+/// before it is loaded.
+static STDIN: OnceCell<Vec<u8>> = OnceCell::const_new();
+
+/// Read the bytes of an image or pattern file, or of stdin if `path` is `-`.
+async fn read_input(path: &Path) -> Result<Cow<'static, [u8]>> {
+    if !is_std_stream(path) {
+        let bytes = tokio::fs::read(path).await
+            .with_context(|| format!("Cannot open {}", path.display()))?;
+        return Ok(Cow::Owned(bytes))
+    }
+    let bytes = STDIN.get_or_try_init(|| async {
+        let mut bytes = Vec::new();
+        tokio::io::stdin().read_to_end(&mut bytes).await.context("Cannot read stdin")?;
+        ensure!(!bytes.is_empty(), "Nothing was piped in on stdin, so give the board to load with --input");
+        Ok(bytes)
+    }).await?;
+    Ok(Cow::Borrowed(bytes))
+}
+
+/// Read the width and height of the board in an image or pattern file, or piped in on stdin.
+pub async fn read_board_dimensions<P: AsRef<Path>>(path: P) -> Result<(usize, usize)> {
+    let path = path.as_ref();
+    let bytes = read_input(path).await?;
+    let board = Board::decode(&bytes)
+        .with_context(|| format!("Cannot decode {}", path.display()))?;
+    Ok((board.width, board.height))
+}
+
+/// Read the header of a checkpoint without loading the whole world.
+pub async fn read_checkpoint_header<P: AsRef<Path>>(path: P) -> Result<CheckpointHeader> {
+    let path = path.as_ref();
+    let mut buffer = Vec::new();
+    File::open(path).await
+        .with_context(|| format!("Cannot open {}", path.display()))?
+        .take(512).read_to_end(&mut buffer).await?;
+    CheckpointHeader::from_bytes(&mut buffer.as_slice())
+        .with_context(|| format!("Cannot read checkpoint {}", path.display()))
+}
+
+/// Save `checkpoint` to `path` and return the number of bytes written.
+async fn save_checkpoint(path: &Path, checkpoint: Checkpoint) -> Result<usize> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        create_dir_all(dir).await?;
+    }
+    let bytes = tokio::task::spawn_blocking(move || checkpoint.to_bytes()).await??;
+    let mut temp_path = path.as_os_str().to_owned();
+    temp_path.push(".tmp");
+    let mut file = File::create(&temp_path).await
+        .with_context(|| format!("Cannot create {}", Path::new(&temp_path).display()))?;
+    file.write_all(&bytes).await?;
+    file.sync_all().await?;
+    tokio::fs::rename(&temp_path, path).await?;
+    Ok(bytes.len())
+}
+
+async fn load_checkpoint(path: &Path) -> Result<Checkpoint> {
+    let bytes = tokio::fs::read(path).await
+        .with_context(|| format!("Cannot open {}", path.display()))?;
+    tokio::task::spawn_blocking(move || Checkpoint::from_bytes(&bytes)).await?
+        .with_context(|| format!("Cannot read checkpoint {}", path.display()))
+}
+
 impl IoState {
-    async fn read_pgm_image(&mut self) -> Result<()> {
-        let filename = self.channels.filename
-            .as_mut().context("The filename channel is None")?
-            .recv_async().await.context("The filename channel has been closed")?;
-        let path = format!("images/{}.pgm", filename);
-        let mut buffer = Vec::new();
-        File::open(path).await?.read_to_end(&mut buffer).await?;
-        let pgm = image::load_from_memory(&buffer)?;
-        assert_eq!(pgm.width(), self.params.image_width as u32);
-        assert_eq!(pgm.height(), self.params.image_height as u32);
-
-        for byte in pgm.into_bytes() {
-            self.channels.input.as_ref().context("The input channel is None")?
-                .send_async(CellValue::from(byte)).await?;
+    fn count_written(&self, bytes: usize) {
+        if let Some(metrics) = &self.metrics {
+            metrics.add_io_bytes_written(bytes);
         }
-        Ok(())
     }
 
-    async fn write_pgm_image(&mut self) -> Result<()> {
-        create_dir_all("out").await?;
-        let filename = self.channels.filename
-            .as_mut().context("The filename channel is None")?
-            .recv_async().await.context("The filename channel has been closed")?;
-        let path = format!("out/{}.pgm", filename);
-        let file = File::create(path).await?;
-
-        let mut writer = BufWriter::new(file);
-        writer.write_all("P5".as_bytes()).await?;
-        writer.write_all("\n".as_bytes()).await?;
-        writer.write_all(self.params.image_width.to_string().as_bytes()).await?;
-        writer.write_all(" ".to_string().as_bytes()).await?;
-        writer.write_all(self.params.image_height.to_string().as_bytes()).await?;
-        writer.write_all("\n".as_bytes()).await?;
-        writer.write_all(255_usize.to_string().as_bytes()).await?;
-        writer.write_all("\n".as_bytes()).await?;
-
-        let mut world = vec![CellValue::Dead; self.params.image_width * self.params.image_height];
-        let output_rx = self.channels.output
-            .as_mut().context("The output channel is None")?;
-        for i in world.iter_mut() {
-            *i = output_rx.recv_async().await.context("The output channel has been closed")?;
+    async fn read_board(&mut self, filename: &str) -> Result<Board> {
+        let path = self.params.input.clone()
+            .unwrap_or_else(|| format!("images/{}.pgm", filename).into());
+        let bytes = read_input(&path).await?;
+        let format = Format::detect(&bytes);
+        let mut board = Board::decode_as(&bytes, format)
+            .with_context(|| format!("Cannot decode {}", path.display()))?;
+        if self.params.image_width != 0 || self.params.image_height != 0 {
+            let width = if self.params.image_width == 0 { board.width } else { self.params.image_width };
+            let height = if self.params.image_height == 0 { board.height } else { self.params.image_height };
+            board = board.fit(width, height, format)
+                .with_context(|| format!("Cannot load {}", path.display()))?;
+        }
+        self.params.image_width = board.width;
+        self.params.image_height = board.height;
+        Ok(board)
+    }
+
+    async fn write_board(&self, filename: &str, world: &[CellValue]) -> Result<()> {
+        let format = self.params.stdout.unwrap_or_default();
+        let bytes = Board::encode_world(
+            world,
+            self.params.image_width,
+            self.params.image_height,
+            format,
+            &self.params.rule
+        );
+        if self.params.stdout.is_some() {
+            let mut stdout = tokio::io::stdout();
+            stdout.write_all(&bytes).await?;
+            stdout.flush().await?;
+            self.count_written(bytes.len());
+            return Ok(())
         }
-        writer.write_all(world.as_bytes()).await?;
-        writer.flush().await?;
+
+        create_dir_all(&self.params.output_dir).await?;
+        let path = self.params.output_dir.join(format!("{}.{}", filename, format.extension()));
+        let mut file = File::create(&path).await
+            .with_context(|| format!("Cannot create {}", path.display()))?;
+        file.write_all(&bytes).await?;
+        file.flush().await?;
+        self.count_written(bytes.len());
         Ok(())
     }
 }
@@ -0,0 +1,100 @@
+use flume::Receiver;
+use sdl2::keyboard::Keycode;
+use std::sync::{Arc, Condvar, Mutex};
+
+/// `Lockstep` keeps the distributors of several worlds on the same turn, e.g. to compare two rules.
+/// Every turn they meet and take the same key press, which only the leading world reads,
+/// so they pause, output and quit together. Each distributor holds one `Lockstep`, and dropping it
+/// leaves the others to go on without it. Once the leading world is gone the others quit.
+#[derive(Debug)]
+pub struct Lockstep {
+    shared: Arc<Shared>,
+    leads: bool,
+}
+
+#[derive(Debug)]
+struct Shared {
+    state: Mutex<State>,
+    arrived: Condvar,
+}
+
+#[derive(Debug)]
+struct State {
+    /// The worlds still running.
+    members: usize,
+    /// The worlds waiting for the rest to arrive.
+    waiting: usize,
+    /// The number of times everyone arrived, which tells the waiting worlds when to go on.
+    generation: u64,
+    /// The key press every world takes this turn.
+    key_press: Option<Keycode>,
+}
+
+impl Lockstep {
+    /// A `Lockstep` for each of `worlds` worlds, the first of which leads.
+    pub fn new(worlds: usize) -> Vec<Lockstep> {
+        let shared = Arc::new(Shared {
+            state: Mutex::new(State { members: worlds, waiting: 0, generation: 0, key_press: None }),
+            arrived: Condvar::new(),
+        });
+        (0..worlds).map(|i| Lockstep { shared: Arc::clone(&shared), leads: i == 0 }).collect()
+    }
+
+    /// The key press every world takes between two turns, if one was made.
+    pub fn try_key_press(&self, key_presses: &Receiver<Keycode>) -> Option<Keycode> {
+        self.agree(|| key_presses.try_recv().ok())
+    }
+
+    /// Wait for the next key press every world takes while paused.
+    /// Returns `None` once no more key presses can be made.
+    pub fn key_press(&self, key_presses: &Receiver<Keycode>) -> Option<Keycode> {
+        self.agree(|| key_presses.recv().ok())
+    }
+
+    /// Wait for the other worlds, taking the key press the leading world reads with `read`.
+    fn agree(&self, read: impl FnOnce() -> Option<Keycode>) -> Option<Keycode> {
+        if self.leads {
+            let key_press = read();
+            self.shared.state.lock().unwrap().key_press = key_press;
+        }
+        self.wait();
+        let key_press = self.shared.state.lock().unwrap().key_press;
+        // the leading world reads the next key press only once every world has taken this one
+        self.wait();
+        key_press
+    }
+
+    fn wait(&self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.waiting += 1;
+        if state.waiting >= state.members {
+            self.shared.release(&mut state);
+            return
+        }
+        let generation = state.generation;
+        while state.generation == generation {
+            state = self.shared.arrived.wait(state).unwrap();
+        }
+    }
+}
+
+impl Shared {
+    fn release(&self, state: &mut State) {
+        state.waiting = 0;
+        state.generation += 1;
+        self.arrived.notify_all();
+    }
+}
+
+impl Drop for Lockstep {
+    fn drop(&mut self) {
+        let mut state = self.shared.state.lock().unwrap();
+        state.members -= 1;
+        if self.leads {
+            state.key_press = Some(Keycode::Q);
+        }
+        if state.waiting > 0 && state.waiting >= state.members {
+            self.shared.release(&mut state);
+        }
+    }
+}
@@ -1,16 +1,32 @@
 use crate::args::Args;
 use crate::gol::distributor::{DistributorChannels, distributor};
+use crate::gol::edit::Edit;
 use crate::gol::event::Event;
-use crate::gol::io::{start_io, IoChannels};
-use crate::util::cell::CellValue;
-use anyhow::Result;
+use crate::gol::lockstep::Lockstep;
+use crate::gol::io::{read_board_dimensions, read_checkpoint_header, start_io, IoChannels, IoRequest, IoResponse};
+use crate::gol::stats::Statistics;
+use crate::gol::{format::Format, rule::Rule};
+use crate::net::{http, metrics::{self, Metrics}};
+use anyhow::{bail, Context, Result};
 use flume::{Receiver, Sender};
-use io::IoCommand;
 use sdl2::keyboard::Keycode;
+use std::{path::PathBuf, sync::Arc, time::Duration};
+use tokio::net::TcpListener;
 
+pub mod broker;
+pub mod bus;
+pub mod checkpoint;
 pub mod distributor;
+pub mod edit;
 pub mod event;
+pub mod format;
 pub mod io;
+pub mod lockstep;
+pub mod pool;
+pub mod rule;
+pub mod stats;
+pub mod strip;
+pub mod topology;
 
 /// `Params` provides the details of how to run the Game of Life and which image to load.
 #[derive(Clone, Debug)]
@@ -19,6 +35,94 @@ pub struct Params {
     pub threads: usize,
     pub image_width: usize,
     pub image_height: usize,
+    pub input: Option<PathBuf>,
+    pub output_dir: PathBuf,
+    pub rule: Rule,
+    /// Where to write checkpoints, if at all.
+    pub checkpoint: Option<PathBuf>,
+    pub checkpoint_interval: Duration,
+    /// The checkpoint to resume the run from, instead of loading an image.
+    pub resume: Option<PathBuf>,
+    /// Write output boards to stdout in this format instead of writing images.
+    pub stdout: Option<Format>,
+    /// The number of turns between two boards written to stdout, or 0 for the final board only.
+    pub stdout_interval: usize,
+    /// The addresses of the worker processes to distribute the world across, or none to run locally.
+    pub workers: Vec<String>,
+    /// How long to wait for a worker before treating it as failed.
+    pub worker_timeout: Duration,
+    /// The address to serve the status and the board over HTTP on, if at all.
+    pub http: Option<String>,
+    /// The address to export metrics in the Prometheus text format on, if at all.
+    pub metrics: Option<String>,
+}
+
+/// The image size used when neither the dimensions nor an input image are given.
+pub const DEFAULT_IMAGE_SIZE: usize = 512;
+
+impl Params {
+    /// Fill in the image dimensions that were not given (i.e. are 0).
+    /// They are read from the input image or pattern, or default to 512x512 without one.
+    /// When resuming, the dimensions are taken from the checkpoint, whose rule must be the one to run.
+    pub async fn resolve(mut self) -> Result<Self> {
+        if let Some(resume) = &self.resume {
+            let header = read_checkpoint_header(resume).await?;
+            if (self.image_width != 0 && self.image_width != header.image_width)
+                || (self.image_height != 0 && self.image_height != header.image_height) {
+                bail!(
+                    "{} is {}x{}, but the given dimensions are {}x{}",
+                    resume.display(),
+                    header.image_width,
+                    header.image_height,
+                    self.image_width,
+                    self.image_height
+                );
+            }
+            if header.rule != self.rule {
+                bail!("{} was simulating {}, but the rule to run is {}", resume.display(), header.rule, self.rule);
+            }
+            self.image_width = header.image_width;
+            self.image_height = header.image_height;
+            return Ok(self)
+        }
+        if self.image_width != 0 && self.image_height != 0 {
+            return Ok(self)
+        }
+        match &self.input {
+            Some(input) => {
+                let (width, height) = read_board_dimensions(input).await?;
+                if self.image_width == 0 { self.image_width = width; }
+                if self.image_height == 0 { self.image_height = height; }
+            },
+            None => {
+                if self.image_width == 0 { self.image_width = DEFAULT_IMAGE_SIZE; }
+                if self.image_height == 0 { self.image_height = DEFAULT_IMAGE_SIZE; }
+            },
+        }
+        Ok(self)
+    }
+}
+
+/// `Hooks` are the ways into and out of a running simulation besides the key presses and the events.
+#[derive(Debug, Default)]
+pub struct Hooks {
+    /// Edits to apply to the world between turns.
+    pub edits: Option<Receiver<Edit>>,
+    /// Where the distributor collects the statistics of every turn.
+    pub statistics: Option<Arc<Statistics>>,
+    /// The other worlds to keep on the same turn as.
+    pub lockstep: Option<Lockstep>,
+    /// Leave the cells that flip every turn out of the events, as nothing follows them.
+    /// The HTTP API and the metrics exporter still get them when they are served.
+    pub without_flips: bool,
+}
+
+/// `World` is one of several simulations run side by side by `run_many`.
+#[derive(Debug)]
+pub struct World {
+    pub params: Params,
+    pub events: Sender<Event>,
+    pub hooks: Hooks,
 }
 
 pub async fn run<P: Into<Params>>(
@@ -26,35 +130,64 @@ pub async fn run<P: Into<Params>>(
     events: Sender<Event>,
     key_presses: Receiver<Keycode>,
 ) -> Result<()> {
-    let params: Params = params.into();
-    // TODO: Put the missing channels in here.
-    // TODO: DONE
-
-    let (io_command_tx, io_command_rx) = flume::unbounded::<IoCommand>();
-    let (io_idle_tx, io_idle_rx) = flume::unbounded::<bool>();
-    let (io_filename_tx,io_filename_rx) = flume::unbounded::<String>();
-    let (io_input_tx,io_input_rx) = flume::unbounded::<CellValue>();
-    let (io_output_tx,io_io_output_tx_rx) = flume::unbounded::<CellValue>();
+    run_with(params, events, key_presses, Hooks::default()).await
+}
 
+/// Run the Game of Life like `run`, with the `hooks` of whatever follows it more closely than the events.
+pub async fn run_with<P: Into<Params>>(
+    params: P,
+    events: Sender<Event>,
+    key_presses: Receiver<Keycode>,
+    hooks: Hooks,
+) -> Result<()> {
+    let params: Params = params.into().resolve().await?;
+    let (io_requests_tx, io_requests_rx) = flume::unbounded::<IoRequest>();
+    let (io_responses_tx, io_responses_rx) = flume::unbounded::<IoResponse>();
+    // the measurements that do not travel with the events are only taken while they are exported
+    let metrics = params.metrics.as_ref().map(|_| Arc::new(Metrics::default()));
 
     let io_channels = IoChannels {
-        command: Some(io_command_rx),
-        idle: Some(io_idle_tx),
-        filename: Some(io_filename_rx),
-        input: Some(io_input_tx),  
-        output: Some(io_io_output_tx_rx),
+        requests: Some(io_requests_rx),
+        responses: Some(io_responses_tx),
+        metrics: metrics.clone(),
     };
 
     tokio::spawn(start_io(params.clone(), io_channels));
 
+    // the HTTP API sits between the distributor and the caller, following the events on their way out
+    let (events, key_presses) = match &params.http {
+        Some(addr) => {
+            let listener = TcpListener::bind(addr).await.with_context(|| format!("Cannot listen on {}", addr))?;
+            let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+            let (key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+            tokio::spawn(http::serve(listener, params.clone(), events_rx, events, key_presses, key_presses_tx));
+            (events_tx, key_presses_rx)
+        },
+        None => (events, key_presses),
+    };
+
+    // the metrics exporter follows the events right as the distributor sends them
+    let events = match (&params.metrics, &metrics) {
+        (Some(addr), Some(metrics)) => {
+            let listener = TcpListener::bind(addr).await.with_context(|| format!("Cannot listen on {}", addr))?;
+            let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+            let (width, height) = (params.image_width, params.image_height);
+            tokio::spawn(metrics::serve(listener, width, height, Arc::clone(metrics), events_rx, events));
+            events_tx
+        },
+        _ => events,
+    };
+
     let distributor_channels = DistributorChannels {
         events: Some(events),
         key_presses: Some(key_presses),
-        io_command: Some(io_command_tx),
-        io_idle: Some(io_idle_rx),
-        io_filename: Some(io_filename_tx),
-        io_input: Some(io_input_rx),
-        io_output: Some(io_output_tx),
+        edits: hooks.edits,
+        io_requests: Some(io_requests_tx),
+        io_responses: Some(io_responses_rx),
+        metrics,
+        statistics: hooks.statistics,
+        lockstep: hooks.lockstep,
+        flips: !hooks.without_flips || params.http.is_some() || params.metrics.is_some(),
     };
 
     tokio::task::spawn_blocking(move ||
@@ -63,14 +196,43 @@ pub async fn run<P: Into<Params>>(
     Ok(())
 }
 
+/// Run several independent worlds in lockstep, each sending its own events. Every world completes
+/// a turn before any of them starts the next, and the key presses reach them all on the same turn.
+/// The worlds should not share where they write their output, checkpoints or servers.
+pub async fn run_many(worlds: Vec<World>, key_presses: Receiver<Keycode>) -> Result<()> {
+    let lockstep = Lockstep::new(worlds.len());
+    let runs = worlds.into_iter().zip(lockstep)
+        .map(|(world, lockstep)| {
+            let hooks = Hooks { lockstep: Some(lockstep), ..world.hooks };
+            tokio::spawn(run_with(world.params, world.events, key_presses.clone(), hooks))
+        })
+        .collect::<Vec<_>>();
+    for run in runs {
+        run.await??;
+    }
+    Ok(())
+}
 
 impl From<Args> for Params {
     fn from(args: Args) -> Self {
         Params {
             turns: args.turns,
             threads: args.threads,
-            image_width: args.image_width,
-            image_height: args.image_height,
+            // the dimensions that were not given are 0 until they are resolved
+            image_width: args.width.unwrap_or(0),
+            image_height: args.height.unwrap_or(0),
+            input: args.input,
+            output_dir: args.output_dir,
+            rule: Rule::default(),
+            checkpoint: args.checkpoint,
+            checkpoint_interval: Duration::from_secs(args.checkpoint_interval),
+            resume: args.resume,
+            stdout: args.stdout,
+            stdout_interval: args.stdout_interval,
+            workers: args.workers,
+            worker_timeout: Duration::from_secs(args.worker_timeout),
+            http: args.http,
+            metrics: args.metrics,
         }
     }
 }
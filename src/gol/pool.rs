@@ -0,0 +1,138 @@
+use crate::gol::{strip::Strip, Params};
+use crate::net::metrics::Metrics;
+use crate::util::cell::CellValue;
+use anyhow::{ensure, Context, Result};
+use flume::{Receiver, Sender};
+use std::{ops::Range, time::{Duration, Instant}};
+
+/// `Link` connects a strip thread to the thread holding the strip on one side of it.
+/// Each side sends its own boundary row and receives the other's as its halo.
+struct Link {
+    send: Sender<Vec<CellValue>>,
+    recv: Receiver<Vec<CellValue>>,
+}
+
+impl Link {
+    /// Link two adjacent strips, returning the end of the upper strip and the end of the lower strip.
+    fn pair() -> (Link, Link) {
+        let (down_tx, down_rx) = flume::unbounded();
+        let (up_tx, up_rx) = flume::unbounded();
+        (Link { send: down_tx, recv: up_rx }, Link { send: up_tx, recv: down_rx })
+    }
+}
+
+/// `StripThread` is the handle of a thread holding a strip of the world.
+struct StripThread {
+    rows: Range<usize>,
+    steps: Sender<()>,
+}
+
+/// `StripPool` splits the world into horizontal strips, one for each thread, and steps them together.
+/// Threads keep their strip between turns and swap halo rows with their neighbours directly,
+/// so only the cells that flipped and the time it took are reported back when a turn completes.
+pub struct StripPool {
+    width: usize,
+    threads: Vec<StripThread>,
+    stepped: Receiver<Stepped>,
+}
+
+/// `Stepped` is what a strip thread reports once it has computed a turn.
+struct Stepped {
+    index: usize,
+    flipped: Vec<u32>,
+    compute: Duration,
+}
+
+impl StripPool {
+    /// Hand a strip of `world` to each of `params.threads` threads.
+    pub fn start(params: &Params, world: &[CellValue]) -> Self {
+        let (width, height) = (params.image_width, params.image_height);
+        // every thread needs at least one row
+        let count = params.threads.clamp(1, height);
+        let mut strips = (0..count)
+            .map(|i| {
+                let rows = i * height / count..(i + 1) * height / count;
+                let cells = world[rows.start * width..rows.end * width].to_vec();
+                (Strip::new(width, height, rows.start, params.rule, cells), None, None)
+            })
+            .collect::<Vec<(Strip, Option<Link>, Option<Link>)>>();
+
+        // the strip below a strip is always the next one, wrapping around at the bottom
+        for i in 0..count {
+            if strips[i].0.below().is_some() {
+                let (upper, lower) = Link::pair();
+                strips[i].2 = Some(upper);
+                strips[(i + 1) % count].1 = Some(lower);
+            }
+        }
+
+        let (stepped_tx, stepped_rx) = flume::unbounded();
+        let threads = strips.into_iter().enumerate()
+            .map(|(index, (strip, above, below))| {
+                let (steps_tx, steps_rx) = flume::unbounded();
+                let rows = strip.rows();
+                let stepped = stepped_tx.clone();
+                std::thread::spawn(move || run(index, strip, above, below, steps_rx, stepped));
+                StripThread { rows, steps: steps_tx }
+            })
+            .collect();
+        StripPool { width, threads, stepped: stepped_rx }
+    }
+
+    /// Compute the next turn of `world`, which has to be the world the threads were last stepped to.
+    /// The time each thread spent computing is added to `metrics`, if they are taken.
+    ///
+    /// The flipped cells are applied to a full copy of `world`, as the distributor needs a whole new world
+    /// every turn just as it gets from a single thread: it reports the flips by comparing the two, collects the
+    /// statistics from them and shares the world with the IO task. The copy is a single pass in memory, and
+    /// the halo rows never go through it.
+    pub fn step(&mut self, world: &[CellValue], metrics: Option<&Metrics>) -> Result<Vec<CellValue>> {
+        for thread in &self.threads {
+            thread.steps.send(()).context("A strip thread has stopped")?;
+        }
+        let mut new_world = world.to_vec();
+        for _ in 0..self.threads.len() {
+            let Stepped { index, flipped, compute } = self.stepped.recv().context("Every strip thread has stopped")?;
+            if let Some(metrics) = metrics {
+                metrics.add_compute_time(&format!("thread-{}", index), compute);
+            }
+            let rows = &self.threads[index].rows;
+            let strip = &mut new_world[rows.start * self.width..rows.end * self.width];
+            ensure!(flipped.iter().all(|&i| (i as usize) < strip.len()), "Flipped a cell outside its strip");
+            for i in flipped {
+                strip[i as usize].flip();
+            }
+        }
+        Ok(new_world)
+    }
+}
+
+/// Step `strip` whenever asked to, until the pool is dropped or a neighbour stops.
+fn run(
+    index: usize,
+    mut strip: Strip,
+    above: Option<Link>,
+    below: Option<Link>,
+    steps: Receiver<()>,
+    stepped: Sender<Stepped>,
+) {
+    let send = |link: &Option<Link>, row: &[CellValue]| match link {
+        Some(link) => link.send.send(row.to_vec()).is_ok(),
+        None => true,
+    };
+    let receive = |link: &Option<Link>| match link {
+        Some(link) => link.recv.recv().ok(),
+        None => Some(Vec::new()),
+    };
+    while steps.recv().is_ok() {
+        if !send(&above, strip.top_row()) || !send(&below, strip.bottom_row()) {
+            return;
+        }
+        let (Some(above_row), Some(below_row)) = (receive(&above), receive(&below)) else { return };
+        let started = Instant::now();
+        let flipped = strip.step(&above_row, &below_row);
+        if stepped.send(Stepped { index, flipped, compute: started.elapsed() }).is_err() {
+            return;
+        }
+    }
+}
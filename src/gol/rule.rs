@@ -0,0 +1,72 @@
+use crate::util::cell::CellValue;
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use std::{fmt::Display, str::FromStr};
+
+/// `Rule` is a life-like rule in B/S notation, e.g. `B3/S23` for Conway's Game of Life.
+/// A dead cell with a number of alive neighbours listed after `B` is born,
+/// and an alive cell with a number of alive neighbours listed after `S` survives.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    birth: u16,
+    survival: u16,
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Rule { birth: 1 << 3, survival: 1 << 2 | 1 << 3 }
+    }
+}
+
+impl Rule {
+    /// Get the next value of a cell with `neighbours` alive neighbours.
+    pub fn next(&self, cell: CellValue, neighbours: usize) -> CellValue {
+        let mask = match cell {
+            CellValue::Dead => self.birth,
+            CellValue::Alive => self.survival,
+        };
+        if mask & (1 << neighbours) != 0 { CellValue::Alive } else { CellValue::Dead }
+    }
+}
+
+impl FromStr for Rule {
+    type Err = String;
+
+    /// Parse a rule such as `B3/S23`, `b36/s23` or the older `23/3` (survival/birth) notation.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let parse_counts = |counts: &str| counts.chars().try_fold(0_u16, |mask, c| match c.to_digit(10) {
+            Some(count) if count <= 8 => Ok(mask | 1 << count),
+            _ => Err(format!("Invalid neighbour count '{}' in rule {}", c, s)),
+        });
+        let (first, second) = s.trim().split_once('/')
+            .ok_or_else(|| format!("Rule {} should look like B3/S23", s))?;
+        let (birth, survival) = match (first.chars().next(), second.chars().next()) {
+            (Some('B' | 'b'), Some('S' | 's')) => (&first[1..], &second[1..]),
+            (Some('S' | 's'), Some('B' | 'b')) => (&second[1..], &first[1..]),
+            _ => (second, first),
+        };
+        Ok(Rule { birth: parse_counts(birth)?, survival: parse_counts(survival)? })
+    }
+}
+
+impl Display for Rule {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let counts = |mask: u16| (0..=8)
+            .filter(|count| mask & (1 << count) != 0)
+            .map(|count| count.to_string())
+            .collect::<String>();
+        write!(f, "B{}/S{}", counts(self.birth), counts(self.survival))
+    }
+}
+
+/// Rules are serialised in B/S notation, e.g. `"B3/S23"`.
+impl Serialize for Rule {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl<'de> Deserialize<'de> for Rule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?.parse().map_err(D::Error::custom)
+    }
+}
@@ -0,0 +1,56 @@
+use crate::util::cell::CellValue;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// The most turns of statistics kept for a frontend that has not taken them yet.
+const CAPACITY: usize = 100_000;
+
+/// `TurnStats` is the population of the world after a turn, with the cells born and the cells that died in it.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TurnStats {
+    pub completed_turns: u32,
+    pub population: usize,
+    pub births: usize,
+    pub deaths: usize,
+}
+
+impl TurnStats {
+    /// The statistics of the turn that took the world from `old` to `new`, ending with `completed_turns`.
+    pub fn between(old: &[CellValue], new: &[CellValue], completed_turns: u32) -> Self {
+        old.iter().zip(new).fold(
+            TurnStats { completed_turns, ..TurnStats::default() },
+            |mut stats, (old_cell, new_cell)| {
+                match (old_cell.is_alive(), new_cell.is_alive()) {
+                    (false, true) => stats.births += 1,
+                    (true, false) => stats.deaths += 1,
+                    _ => (),
+                }
+                stats.population += new_cell.is_alive() as usize;
+                stats
+            },
+        )
+    }
+}
+
+/// `Statistics` collects the `TurnStats` that the distributor computes every turn, for a frontend to take
+/// at its own pace. Like the metrics they never travel with the events, so a frontend whose events are
+/// coalesced still sees every turn. Once more turns wait than it keeps, the oldest are dropped.
+#[derive(Debug, Default)]
+pub struct Statistics {
+    turns: Mutex<VecDeque<TurnStats>>,
+}
+
+impl Statistics {
+    pub fn push(&self, stats: TurnStats) {
+        let mut turns = self.turns.lock().unwrap();
+        if turns.len() == CAPACITY {
+            turns.pop_front();
+        }
+        turns.push_back(stats);
+    }
+
+    /// Take the statistics of every turn since they were last taken, oldest first.
+    pub fn take(&self) -> Vec<TurnStats> {
+        self.turns.lock().unwrap().drain(..).collect()
+    }
+}
@@ -0,0 +1,88 @@
+use crate::gol::rule::Rule;
+use crate::util::cell::CellValue;
+use std::ops::Range;
+
+/// `Strip` is a horizontal band of the world, stored row by row, that can be stepped on its own
+/// given the halo rows of its neighbours: the row just above it and the row just below it.
+///
+/// Rows are addressed by their index in the whole world, which wraps around at its edges, so a strip
+/// that wraps onto itself (e.g. the only strip) reads its own rows instead of halos and counts every
+/// neighbour exactly like the single-threaded engine does.
+#[derive(Debug, Clone)]
+pub struct Strip {
+    width: usize,
+    height: usize,
+    start: usize,
+    rule: Rule,
+    cells: Vec<CellValue>,
+}
+
+impl Strip {
+    /// Create the strip of a `width`x`height` world whose first row is row `start`.
+    pub fn new(width: usize, height: usize, start: usize, rule: Rule, cells: Vec<CellValue>) -> Self {
+        Strip { width, height, start, rule, cells }
+    }
+
+    /// The rows of the world this strip holds.
+    pub fn rows(&self) -> Range<usize> {
+        self.start..self.start + self.cells.len() / self.width
+    }
+
+    /// The row of the world just above the strip, if it is held by another strip.
+    /// `None` means the strip reads that row itself.
+    pub fn above(&self) -> Option<usize> {
+        let above = (self.start + self.height - 1) % self.height;
+        (!self.rows().contains(&above)).then_some(above)
+    }
+
+    /// The row of the world just below the strip, if it is held by another strip.
+    pub fn below(&self) -> Option<usize> {
+        let below = self.rows().end % self.height;
+        (!self.rows().contains(&below)).then_some(below)
+    }
+
+    /// The first row of the strip, which the strip above needs as its bottom halo.
+    pub fn top_row(&self) -> &[CellValue] {
+        &self.cells[..self.width]
+    }
+
+    /// The last row of the strip, which the strip below needs as its top halo.
+    pub fn bottom_row(&self) -> &[CellValue] {
+        &self.cells[self.cells.len() - self.width..]
+    }
+
+    /// Advance the strip by one turn and return the indices of the cells that flipped.
+    /// `above` and `below` are only read when the strip needs them, see `Strip::above` and `Strip::below`.
+    pub fn step(&mut self, above: &[CellValue], below: &[CellValue]) -> Vec<u32> {
+        let (width, rows) = (self.width, self.rows());
+        let above_row = self.above();
+        let row = |y: usize| match y {
+            y if rows.contains(&y) => &self.cells[(y - rows.start) * width..(y - rows.start + 1) * width],
+            y if Some(y) == above_row => above,
+            _ => below,
+        };
+
+        let mut next = self.cells.clone();
+        let mut flipped = Vec::new();
+        for y in rows.clone() {
+            let neighbourhood = [(y + self.height - 1) % self.height, y, (y + 1) % self.height].map(|ny| (ny, row(ny)));
+            for x in 0..width {
+                let columns = [(x + width - 1) % width, x, (x + 1) % width];
+                let num_neighbours = neighbourhood.iter()
+                    .flat_map(|&(ny, cells)| columns.iter()
+                        .filter(move |&&column| ny != y || column != x)
+                        .map(move |&column| cells[column]))
+                    .filter(CellValue::is_alive)
+                    .count();
+
+                let i = (y - rows.start) * width + x;
+                next[i] = self.rule.next(self.cells[i], num_neighbours);
+                if next[i] != self.cells[i] {
+                    flipped.push(i as u32);
+                }
+            }
+        }
+        self.cells = next;
+        flipped
+    }
+}
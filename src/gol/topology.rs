@@ -0,0 +1,33 @@
+use std::fmt::Display;
+
+/// `Topology` decides what lies beyond the edges of the world.
+/// It is recorded in checkpoints so that they stay readable once other topologies are simulated.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Topology {
+    /// The world wraps around, so the left edge touches the right edge and the top touches the bottom.
+    #[default]
+    Torus,
+}
+
+impl Topology {
+    pub fn as_u8(&self) -> u8 {
+        *self as u8
+    }
+}
+
+impl TryFrom<u8> for Topology {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(Topology::Torus),
+            _ => Err(anyhow::anyhow!("Unknown topology {}", value)),
+        }
+    }
+}
+
+impl Display for Topology {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
@@ -1,4 +1,7 @@
 pub mod args;
 pub mod gol;
+pub mod net;
+pub mod record;
 pub mod sdl;
+pub mod tui;
 pub mod util;
@@ -1,39 +1,271 @@
 use clap::Parser;
-use flume::Sender;
+use anyhow::{bail, Result};
+use flume::{Receiver, Sender};
 use log::Level;
 use sdl2::keyboard::Keycode;
+use std::sync::Arc;
 use tokio::try_join;
-use gol_rs::args::Args;
-use gol_rs::gol::{self, event::Event};
+use gol_rs::args::{Args, Command, Frontend};
+use gol_rs::gol::{self, bus::{EventBus, Policy}, edit::Edit, event::Event, io::stdin_is_piped, stats::Statistics, Hooks, Params, World};
+use gol_rs::net::{controller, server::{self, ServerOptions}, worker};
+use gol_rs::record::event_log;
 use gol_rs::sdl;
+use gol_rs::tui;
 use gol_rs::util::logger;
 
 #[tokio::main(flavor = "multi_thread")]
 async fn main() {
     let args = Args::parse();
-    logger::init(Level::Info, false);
+    match (args.frontend, args.headless || args.persistent) {
+        // the terminal frontend owns the terminal while it draws on stdout, so the logs go to a file next to the output
+        (Frontend::Tui, false) => {
+            let path = args.output_dir.join("gol.log");
+            if let Err(e) = logger::init_to_file(Level::Info, false, &path) {
+                eprintln!("Cannot log to {}: {:#}", path.display(), e);
+                std::process::exit(1);
+            }
+        },
+        _ => logger::init(Level::Info, false),
+    }
+
+    if let Some(Command::Worker { listen }) = &args.command {
+        let listener = match std::net::TcpListener::bind(listen) {
+            Ok(listener) => listener,
+            Err(e) => {
+                log::error!(target: "Main", "Cannot listen on {}: {}", listen, e);
+                std::process::exit(1);
+            },
+        };
+        // the address goes to stdout, so whoever started the worker can find it when the port was 0
+        println!("{}", listener.local_addr().unwrap());
+        if let Err(e) = tokio::task::spawn_blocking(move || worker::serve(listener)).await.unwrap() {
+            log::error!(target: "Main", "{:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(addr) = &args.connect {
+        let attachment = match controller::attach(addr).await {
+            Ok(attachment) => attachment,
+            Err(e) => {
+                log::error!(target: "Main", "{:#}", e);
+                std::process::exit(1);
+            },
+        };
+        let args = args.clone().image_width(attachment.image_width).image_height(attachment.image_height);
+        if let Err(e) = show(args, attachment.events, attachment.key_presses, None, None).await {
+            log::error!(target: "Main", "{:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    if let Some(path) = &args.replay {
+        let header = match event_log::read_header(path).await {
+            Ok(header) => header,
+            Err(e) => {
+                log::error!(target: "Main", "{:#}", e);
+                std::process::exit(1);
+            },
+        };
+        let args = args.clone().image_width(header.image_width).image_height(header.image_height);
+        let (key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+        let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+        tokio::spawn(sigint(key_presses_tx.clone()));
+        let replay = event_log::replay(path.clone(), args.replay_speed, events_tx, key_presses_rx);
+        if let Err(e) = try_join!(replay, show(args, events_rx, key_presses_tx, None, None)) {
+            log::error!(target: "Main", "{:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
+    // a persistent simulation outlives any SDL window, which attaches with --connect instead
+    let args = if args.persistent { args.headless(true) } else { args };
+    // a board piped in without --input is read from stdin,
+    // and boards written to stdout leave it to the pipeline rather than a window
+    let args = match args.input.is_none() && args.resume.is_none() && stdin_is_piped() {
+        true => args.input("-"),
+        false => args,
+    };
+    let args = if args.stdout.is_some() { args.headless(true) } else { args };
+
+    let params = match Params::from(args.clone()).resolve().await {
+        Ok(params) => params,
+        Err(e) => {
+            log::error!(target: "Main", "{:#}", e);
+            std::process::exit(1);
+        },
+    };
+    if !args.headless && args.frontend == Frontend::Tui && args.stdout.is_some() {
+        log::error!(target: "Main", "The terminal frontend draws on stdout, so boards cannot be written to it as well");
+        std::process::exit(1);
+    }
+    let args = args.image_width(params.image_width).image_height(params.image_height);
 
     log::info!(target: "Main", "{:<10} {}", "Threads", args.threads);
-    log::info!(target: "Main", "{:<10} {}", "Width", args.image_width);
-    log::info!(target: "Main", "{:<10} {}", "Height", args.image_height);
+    log::info!(target: "Main", "{:<10} {}", "Width", args.width());
+    log::info!(target: "Main", "{:<10} {}", "Height", args.height());
     log::info!(target: "Main", "{:<10} {}", "Turns", args.turns);
 
+    if args.compares() {
+        if let Err(e) = compare(args).await {
+            log::error!(target: "Main", "{:#}", e);
+            std::process::exit(1);
+        }
+        return;
+    }
+
     let (key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
-    let (events_tx, events_rx) = flume::bounded::<Event>(1000);
 
-    tokio::spawn(sigint(key_presses_tx.clone()));
+    // a window that neither logs nor serves the events can fall behind without ever holding up the distributor,
+    // as the frames it is handed still tell it how many turns they cover and how often each cell flipped
+    let mut bus = EventBus::new();
+    let events_tx = bus.sender();
+    let policy = match args.headless || args.event_log.is_some() || args.listen.is_some() {
+        true => Policy::Block,
+        false => Policy::Coalesce,
+    };
+    let events_rx = bus.subscribe(policy);
+    // a recording needs every turn, so next to a frontend it subscribes on its own rather than slowing the window
+    let recording = (args.record && !args.headless).then(|| bus.subscribe(Policy::Block));
+    tokio::spawn(bus.run());
+
+    // a persistent server shuts everything down on its own when interrupted
+    if !args.persistent {
+        tokio::spawn(sigint(key_presses_tx.clone()));
+    }
+
+    // the server sits between the distributor and the frontend, passing every event on
+    let (events_rx, serving) = match &args.listen {
+        Some(addr) => {
+            let listener = match tokio::net::TcpListener::bind(addr).await {
+                Ok(listener) => listener,
+                Err(e) => {
+                    log::error!(target: "Main", "Cannot listen on {}: {}", addr, e);
+                    std::process::exit(1);
+                },
+            };
+            let (events_forward_tx, events_forward_rx) = flume::bounded::<Event>(1000);
+            let options = ServerOptions { rule: params.rule, ..ServerOptions::from(&args) };
+            let key_presses_tx = key_presses_tx.clone();
+            let serving = tokio::spawn(async move {
+                if let Err(e) = server::serve(listener, options, events_rx, events_forward_tx, key_presses_tx).await {
+                    log::error!(target: "Main", "The server stopped: {:#}", e);
+                }
+            });
+            (events_forward_rx, Some(serving))
+        },
+        None => (events_rx, None),
+    };
 
     if !args.headless {
-        try_join!(
-            gol::run(args.clone(), events_tx, key_presses_rx),
-            sdl::r#loop::run(args, events_rx, key_presses_tx)
-        ).unwrap();
+        // the selection tools of the window edit the world through the distributor,
+        // which keeps the statistics of every turn for the population graph
+        let (edits_tx, edits_rx) = flume::unbounded::<Edit>();
+        let statistics = (args.frontend == Frontend::Sdl).then(|| Arc::new(Statistics::default()));
+        let hooks = Hooks { edits: Some(edits_rx), statistics: statistics.clone(), ..Hooks::default() };
+        let running = try_join!(
+            gol::run_with(args.clone(), events_tx, key_presses_rx, hooks),
+            show(args.clone(), events_rx, key_presses_tx, Some(edits_tx), statistics),
+            record(args, recording)
+        );
+        if let Err(e) = running {
+            log::error!(target: "Main", "{:#}", e);
+            std::process::exit(1);
+        }
     } else {
-        try_join!(
-            gol::run(args, events_tx, key_presses_rx),
-            sdl::r#loop::run_headless(events_rx)
-        ).unwrap();
+        // only the recorder, the event log and the server follow the cells of a headless run
+        let without_flips = !args.record && args.event_log.is_none() && args.listen.is_none();
+        let hooks = Hooks { without_flips, ..Hooks::default() };
+        let running = try_join!(
+            gol::run_with(args.clone(), events_tx, key_presses_rx, hooks),
+            sdl::r#loop::run_headless(args, events_rx)
+        );
+        if let Err(e) = running {
+            log::error!(target: "Main", "{:#}", e);
+            std::process::exit(1);
+        }
+    }
+
+    // wait for the server to let its clients go, or to be shut down when it is persistent
+    if let Some(serving) = serving {
+        serving.await.unwrap();
+    }
+}
+
+/// Show the simulation in the frontend chosen with --frontend, or only log its events when headless.
+async fn show(
+    args: Args,
+    events: Receiver<Event>,
+    key_presses: Sender<Keycode>,
+    edits: Option<Sender<Edit>>,
+    statistics: Option<Arc<Statistics>>,
+) -> Result<()> {
+    match (args.headless, args.frontend) {
+        (true, _) => sdl::r#loop::run_headless(args, events).await,
+        (false, Frontend::Sdl) => sdl::r#loop::run(args, events, key_presses, edits, statistics, None).await,
+        (false, Frontend::Tui) => tui::r#loop::run(args, events, key_presses, edits).await,
+    }
+}
+
+/// Record the run to a GIF from the `events` of a subscriber of its own, if it is recorded next to a frontend.
+async fn record(args: Args, events: Option<Receiver<Event>>) -> Result<()> {
+    match events {
+        Some(events) => sdl::r#loop::run_recorder(args, events).await,
+        None => Ok(()),
+    }
+}
+
+/// Run a second simulation in lockstep with the first, with the rule given with --compare-rule
+/// or the cell given with --compare-flip flipped, and show them both in the SDL window.
+async fn compare(args: Args) -> Result<()> {
+    if args.headless || args.frontend != Frontend::Sdl || args.listen.is_some() || args.stdout.is_some() {
+        bail!("Simulations can only be compared in the SDL window, without --listen or --stdout");
+    }
+    let (key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    tokio::spawn(sigint(key_presses_tx.clone()));
+    let mut bus = EventBus::new();
+    let events_tx = bus.sender();
+    let events_rx = bus.subscribe(Policy::Block);
+    let recording = args.record.then(|| bus.subscribe(Policy::Block));
+    tokio::spawn(bus.run());
+    let (compared_events_tx, compared_events_rx) = flume::bounded::<Event>(1000);
+    let statistics = Arc::new(Statistics::default());
+
+    // the second world writes its output apart from the first, and serves nothing
+    let params = Params::from(args.clone());
+    let compared_params = Params {
+        rule: args.compare_rule.unwrap_or_default(),
+        output_dir: args.output_dir.join("compare"),
+        checkpoint: None,
+        http: None,
+        metrics: None,
+        ..params.clone()
+    };
+    let (flips_tx, flips_rx) = flume::unbounded::<Edit>();
+    if let Some(cell) = args.compare_flip {
+        flips_tx.send(Edit::Flip { cells: vec![cell] })?;
     }
+    let worlds = vec![
+        World {
+            params,
+            events: events_tx,
+            hooks: Hooks { statistics: Some(Arc::clone(&statistics)), ..Hooks::default() },
+        },
+        World {
+            params: compared_params,
+            events: compared_events_tx,
+            hooks: Hooks { edits: Some(flips_rx), ..Hooks::default() },
+        },
+    ];
+    try_join!(
+        gol::run_many(worlds, key_presses_rx),
+        sdl::r#loop::run(args.clone(), events_rx, key_presses_tx, None, Some(statistics), Some(compared_events_rx)),
+        record(args, recording)
+    )?;
+    Ok(())
 }
 
 async fn sigint(key_presses_tx: Sender<Keycode>) {
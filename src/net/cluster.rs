@@ -0,0 +1,224 @@
+use crate::gol::rule::Rule;
+use crate::util::{cell::CellValue, traits::AsBytes};
+use anyhow::{bail, ensure, Context, Result};
+use std::io::{ErrorKind, Read, Write};
+use std::time::Duration;
+
+/// `WorkerRequest` is a message from the broker to a worker, or from a worker to its neighbour.
+///
+/// Messages between the broker and its workers are framed as a tag (`u8`),
+/// the length of the payload (`u32`, little endian) and the payload itself.
+/// Workers swap the rows on the boundary of their strips directly with their neighbours over halo links,
+/// so between turns the broker only tells the workers to step and hears back which cells flipped.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkerRequest {
+    /// Hand the worker the rows of a `width`x`height` world starting at row `start`, replacing any previous strip.
+    /// The worker accepts the halo link of the strip above it and opens the halo link to the strip below it,
+    /// and treats a link as broken whenever its neighbour takes longer than `timeout`.
+    Assign {
+        width: usize,
+        height: usize,
+        start: usize,
+        rule: Rule,
+        timeout: Duration,
+        above: Option<u64>,
+        below: Option<PeerLink>,
+        strip: Vec<CellValue>,
+    },
+    /// Swap halo rows with the neighbours and compute the next turn of the strip.
+    Step,
+    /// Open the halo link identified by `token`, as the first message on a connection between two workers.
+    Peer { token: u64 },
+}
+
+/// `PeerLink` tells a worker where to open a halo link to, and the token its neighbour is waiting for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PeerLink {
+    pub addr: String,
+    pub token: u64,
+}
+
+/// `WorkerResponse` is the reply of a worker to a `WorkerRequest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WorkerResponse {
+    Assigned,
+    /// The indices within the strip of the cells that flipped in this turn, in order,
+    /// and the time the worker spent computing them.
+    Stepped { flipped: Vec<u32>, compute: Duration },
+    /// A halo link could not be opened or broke, so the worker needs a new strip before it can step.
+    LinkFailed { message: String },
+}
+
+/// The longest request a worker reads, which is mostly the strip it is assigned.
+const MAX_REQUEST_LEN: usize = 1 << 30;
+/// The longest message about a broken halo link a broker reads from a worker.
+const MAX_MESSAGE_LEN: usize = 64 * 1024;
+
+const ASSIGN: u8 = 1;
+const STEP: u8 = 2;
+const PEER: u8 = 3;
+const HALO: u8 = 4;
+const ASSIGNED: u8 = 128;
+const STEPPED: u8 = 129;
+const LINK_FAILED: u8 = 130;
+
+impl WorkerRequest {
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+        match self {
+            WorkerRequest::Assign { width, height, start, rule, timeout, above, below, strip } => {
+                let rule = rule.to_string();
+                let addr = below.as_ref().map_or("", |below| below.addr.as_str());
+                let mut payload = Vec::with_capacity(strip.len() + rule.len() + addr.len() + 34);
+                payload.extend(u32::try_from(*width)?.to_le_bytes());
+                payload.extend(u32::try_from(*height)?.to_le_bytes());
+                payload.extend(u32::try_from(*start)?.to_le_bytes());
+                payload.extend(u32::try_from(timeout.as_millis())?.to_le_bytes());
+                payload.push(u8::try_from(rule.len())?);
+                payload.extend(rule.as_bytes());
+                // tokens start at 1, so 0 stands for no link
+                payload.extend(above.unwrap_or(0).to_le_bytes());
+                payload.extend(below.as_ref().map_or(0, |below| below.token).to_le_bytes());
+                payload.push(u8::try_from(addr.len())?);
+                payload.extend(addr.as_bytes());
+                payload.extend(strip.as_bytes());
+                write_frame(writer, ASSIGN, &payload)
+            },
+            WorkerRequest::Step => write_frame(writer, STEP, &[]),
+            WorkerRequest::Peer { token } => write_frame(writer, PEER, &token.to_le_bytes()),
+        }
+    }
+
+    /// Read the next request, or `None` once the broker has closed the connection.
+    pub fn read_from(reader: &mut impl Read) -> Result<Option<Self>> {
+        let Some((tag, payload)) = read_frame(reader, MAX_REQUEST_LEN)? else { return Ok(None) };
+        let mut payload = payload.as_slice();
+        let request = match tag {
+            ASSIGN => {
+                let width = u32::from_le_bytes(take(&mut payload)?) as usize;
+                let height = u32::from_le_bytes(take(&mut payload)?) as usize;
+                let start = u32::from_le_bytes(take(&mut payload)?) as usize;
+                let timeout = Duration::from_millis(u32::from_le_bytes(take(&mut payload)?) as u64);
+                let [rule_len] = take(&mut payload)?;
+                let rule = take_str(&mut payload, rule_len as usize)?;
+                let above = u64::from_le_bytes(take(&mut payload)?);
+                let below = u64::from_le_bytes(take(&mut payload)?);
+                let [addr_len] = take(&mut payload)?;
+                let addr = take_str(&mut payload, addr_len as usize)?;
+                let strip = payload;
+                ensure!(width > 0 && strip.len() % width == 0, "The strip does not consist of whole rows");
+                WorkerRequest::Assign {
+                    width,
+                    height,
+                    start,
+                    rule: rule.parse().map_err(anyhow::Error::msg)?,
+                    timeout,
+                    above: (above != 0).then_some(above),
+                    below: (below != 0).then(|| PeerLink { addr: addr.to_owned(), token: below }),
+                    strip: cells_from_bytes(strip)?,
+                }
+            },
+            STEP => WorkerRequest::Step,
+            PEER => WorkerRequest::Peer { token: u64::from_le_bytes(take(&mut payload)?) },
+            _ => bail!("Unknown request {}", tag),
+        };
+        Ok(Some(request))
+    }
+}
+
+impl WorkerResponse {
+    pub fn write_to(&self, writer: &mut impl Write) -> Result<()> {
+        match self {
+            WorkerResponse::Assigned => write_frame(writer, ASSIGNED, &[]),
+            WorkerResponse::Stepped { flipped, compute } => {
+                let mut payload = Vec::with_capacity(flipped.len() * 4 + 8);
+                payload.extend(u64::try_from(compute.as_nanos())?.to_le_bytes());
+                payload.extend(flipped.iter().flat_map(|i| i.to_le_bytes()));
+                write_frame(writer, STEPPED, &payload)
+            },
+            WorkerResponse::LinkFailed { message } => write_frame(writer, LINK_FAILED, message.as_bytes()),
+        }
+    }
+
+    /// Read the response of a worker whose strip holds `strip_len` cells, which bounds how many can flip.
+    pub fn read_from(reader: &mut impl Read, strip_len: usize) -> Result<Self> {
+        let max_len = strip_len.saturating_mul(4).saturating_add(8).max(MAX_MESSAGE_LEN);
+        let (tag, payload) = read_frame(reader, max_len)?.context("The worker closed the connection")?;
+        match tag {
+            ASSIGNED => Ok(WorkerResponse::Assigned),
+            STEPPED => {
+                let mut payload = payload.as_slice();
+                let compute = Duration::from_nanos(u64::from_le_bytes(take(&mut payload)?));
+                ensure!(payload.len() % 4 == 0, "The flipped cells are truncated");
+                let flipped = payload.chunks_exact(4)
+                    .map(|bytes| u32::from_le_bytes(bytes.try_into().unwrap()))
+                    .collect();
+                Ok(WorkerResponse::Stepped { flipped, compute })
+            },
+            LINK_FAILED => Ok(WorkerResponse::LinkFailed { message: String::from_utf8(payload)? }),
+            _ => bail!("Unknown response {}", tag),
+        }
+    }
+}
+
+/// Send a boundary row of a strip over a halo link.
+pub fn write_halo(writer: &mut impl Write, row: &[CellValue]) -> Result<()> {
+    write_frame(writer, HALO, row.as_bytes())
+}
+
+/// Receive the boundary row of the neighbouring strip over a halo link, which is `width` cells long.
+pub fn read_halo(reader: &mut impl Read, width: usize) -> Result<Vec<CellValue>> {
+    match read_frame(reader, width)?.context("The neighbour closed the halo link")? {
+        (HALO, payload) => cells_from_bytes(&payload),
+        (tag, _) => bail!("Unknown halo message {}", tag),
+    }
+}
+
+fn write_frame(writer: &mut impl Write, tag: u8, payload: &[u8]) -> Result<()> {
+    writer.write_all(&[tag])?;
+    writer.write_all(&u32::try_from(payload.len())?.to_le_bytes())?;
+    writer.write_all(payload)?;
+    Ok(())
+}
+
+/// Read the next frame of at most `max_len` bytes, or `None` if the connection was closed before it started.
+/// The payload grows as it arrives rather than as long as the peer claims it is.
+fn read_frame(reader: &mut impl Read, max_len: usize) -> Result<Option<(u8, Vec<u8>)>> {
+    let mut tag = [0_u8];
+    match reader.read_exact(&mut tag) {
+        Ok(()) => (),
+        Err(e) if e.kind() == ErrorKind::UnexpectedEof => return Ok(None),
+        Err(e) => return Err(e.into()),
+    }
+    let mut len = [0_u8; 4];
+    reader.read_exact(&mut len)?;
+    let len = u32::from_le_bytes(len) as usize;
+    ensure!(len <= max_len, "The frame is {} bytes, longer than the {} expected", len, max_len);
+    let mut payload = Vec::new();
+    reader.take(len as u64).read_to_end(&mut payload)?;
+    ensure!(payload.len() == len, "The frame is truncated");
+    Ok(Some((tag[0], payload)))
+}
+
+fn take<const N: usize>(payload: &mut &[u8]) -> Result<[u8; N]> {
+    ensure!(payload.len() >= N, "The message is truncated");
+    let (bytes, rest) = payload.split_at(N);
+    *payload = rest;
+    Ok(bytes.try_into().unwrap())
+}
+
+fn take_str<'a>(payload: &mut &'a [u8], len: usize) -> Result<&'a str> {
+    ensure!(payload.len() >= len, "The message is truncated");
+    let (bytes, rest) = payload.split_at(len);
+    *payload = rest;
+    Ok(std::str::from_utf8(bytes)?)
+}
+
+fn cells_from_bytes(bytes: &[u8]) -> Result<Vec<CellValue>> {
+    bytes.iter()
+        .map(|&byte| match byte {
+            0 => Ok(CellValue::Dead),
+            255 => Ok(CellValue::Alive),
+            _ => bail!("{} is not a cell", byte),
+        })
+        .collect()
+}
@@ -0,0 +1,78 @@
+use crate::gol::event::{Event, State};
+use crate::net::protocol::{read_message, write_message, Connection, Request, Response};
+use anyhow::{bail, Result};
+use flume::{Receiver, Sender};
+use sdl2::keyboard::Keycode;
+use tokio::sync::watch;
+
+/// `Attachment` connects a frontend to a simulation running behind a server,
+/// as if the simulation was running locally.
+pub struct Attachment {
+    pub image_width: usize,
+    pub image_height: usize,
+    /// Every event of the simulation, starting with a snapshot of the board.
+    pub events: Receiver<Event>,
+    /// Key presses to control the simulation with. `P` and `S` behave as they do locally,
+    /// `Q` detaches without stopping the simulation and `K` shuts down the simulation and the server.
+    pub key_presses: Sender<Keycode>,
+}
+
+/// Attach to the server on `addr`.
+pub async fn attach(addr: &str) -> Result<Attachment> {
+    let mut connection = Connection::connect(addr).await?;
+    connection.send(&Request::Attach).await?;
+    let (image_width, image_height) = match connection.recv().await? {
+        Some(Response::Attached { image_width, image_height, .. }) => (image_width, image_height),
+        Some(Response::Error { message }) => bail!("The server cannot be attached to: {}", message),
+        response => bail!("Unexpected response {:?}", response),
+    };
+    log::info!(target: "Controller", "Attached to {}", addr);
+
+    let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+    let (key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    // `P` toggles between pausing and resuming, depending on the last state the server reported
+    let (state_tx, state_rx) = watch::channel(State::Executing);
+    let (mut reader, mut writer) = connection.into_split();
+
+    tokio::spawn(async move {
+        loop {
+            match read_message::<_, Response>(&mut reader).await {
+                Ok(Some(Response::Event { event })) => {
+                    if let Event::StateChange { new_state, .. } = event {
+                        let _ = state_tx.send(new_state);
+                    }
+                    if events_tx.send_async(event).await.is_err() {
+                        break;
+                    }
+                },
+                Ok(Some(Response::Error { message })) => log::warn!(target: "Controller", "{}", message),
+                Ok(Some(_)) => (),
+                Ok(None) => break,
+                Err(e) => {
+                    log::error!(target: "Controller", "{:#}", e);
+                    break;
+                },
+            }
+        }
+        log::info!(target: "Controller", "Detached from the server");
+    });
+
+    tokio::spawn(async move {
+        while let Ok(key) = key_presses_rx.recv_async().await {
+            let request = match key {
+                Keycode::P if *state_rx.borrow() == State::Pause => Request::Resume,
+                Keycode::P => Request::Pause,
+                Keycode::S => Request::Snapshot,
+                Keycode::K => Request::Shutdown,
+                // closing the connection detaches, which ends the events once the server lets go
+                Keycode::Q => break,
+                _ => continue,
+            };
+            if write_message(&mut writer, &request).await.is_err() {
+                break;
+            }
+        }
+    });
+
+    Ok(Attachment { image_width, image_height, events: events_rx, key_presses: key_presses_tx })
+}
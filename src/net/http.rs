@@ -0,0 +1,273 @@
+use crate::gol::event::{Event, State};
+use crate::gol::{format::{Board, Format}, rule::Rule, Params};
+use crate::util::{avgturns::AvgTurns, cell::CellCoord, traits::AsBytes};
+use anyhow::{bail, Context, Result};
+use flume::{Receiver, Sender};
+use image::{GrayImage, ImageFormat};
+use sdl2::keyboard::Keycode;
+use serde::Serialize;
+use std::{io::Cursor, sync::{Arc, Mutex}, time::Duration};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::{select, time::timeout};
+
+/// How often the measured turns per second are updated.
+const MEASURE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long a client may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The longest request line and headers a client may send, as requests are only a path and a few headers.
+const MAX_HEAD: u64 = 8 * 1024;
+
+/// `Status` is what the API knows about the simulation, as followed through its events.
+#[derive(Debug, Serialize)]
+struct Status {
+    completed_turns: u32,
+    population: usize,
+    state: State,
+    turns_per_second: u32,
+    image_width: usize,
+    image_height: usize,
+    /// The board is shared with the requests answering with it, which encode it without holding the lock.
+    #[serde(skip)]
+    board: Arc<Board>,
+}
+
+impl Status {
+    fn flip(&mut self, cells: &[CellCoord]) {
+        // a board still being encoded for a request is copied rather than changed under it
+        let board = Arc::make_mut(&mut self.board);
+        for cell in cells {
+            let Some(cell) = board.cells.get_mut(cell.y * board.width + cell.x) else { continue };
+            cell.flip();
+            match cell.is_alive() {
+                true => self.population += 1,
+                false => self.population -= 1,
+            }
+        }
+    }
+}
+
+/// Serve the status and the board of the simulation over HTTP on `listener`, and let clients control it.
+///
+/// Every event from `events` is passed on to `events_forward`, and every key press from `key_presses`
+/// is passed on to `key_presses_forward` along with the ones the clients ask for.
+/// The status is updated in memory as the events pass through, while the key presses pass through
+/// a task of their own and requests are answered from theirs, so clients never hold up the distributor.
+///
+/// - `GET /status` answers with the turn, population, state and measured turns per second as JSON.
+/// - `GET /board?format=pgm|png|rle|cells` answers with the current board, as PGM by default.
+/// - `POST /pause`, `/resume`, `/snapshot` and `/quit` control the simulation like the keys do.
+pub async fn serve(
+    listener: TcpListener,
+    params: Params,
+    events: Receiver<Event>,
+    events_forward: Sender<Event>,
+    key_presses: Receiver<Keycode>,
+    key_presses_forward: Sender<Keycode>,
+) {
+    if let Ok(addr) = listener.local_addr() {
+        log::info!(target: "Http", "Listening on {}", addr);
+    }
+    let status = Arc::new(Mutex::new(Status {
+        completed_turns: 0,
+        population: 0,
+        state: State::Executing,
+        turns_per_second: 0,
+        image_width: params.image_width,
+        image_height: params.image_height,
+        board: Arc::new(Board::new(params.image_width, params.image_height)),
+    }));
+    let mut avg_turns = AvgTurns::new();
+    let mut measure_interval = tokio::time::interval(MEASURE_INTERVAL);
+
+    let forwarding = key_presses_forward.clone();
+    tokio::spawn(async move {
+        while let Ok(key) = key_presses.recv_async().await {
+            if forwarding.send_async(key).await.is_err() {
+                break;
+            }
+        }
+    });
+    let mut following = tokio::spawn(follow(Arc::clone(&status), events, events_forward));
+
+    loop {
+        select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    let (status, key_presses) = (Arc::clone(&status), key_presses_forward.clone());
+                    let rule = params.rule;
+                    tokio::spawn(async move {
+                        if let Err(e) = handle(stream, &status, &key_presses, &rule).await {
+                            log::warn!(target: "Http", "Cannot answer a request: {:#}", e);
+                        }
+                    });
+                },
+                Err(e) => log::warn!(target: "Http", "Cannot accept a client: {}", e),
+            },
+            _ = measure_interval.tick() => {
+                let mut status = status.lock().unwrap();
+                status.turns_per_second = avg_turns.get(status.completed_turns);
+            },
+            _ = &mut following => break,
+        }
+    }
+}
+
+/// Update the status with every event on its way from the distributor to `events_forward`,
+/// until either side stops.
+async fn follow(status: Arc<Mutex<Status>>, events: Receiver<Event>, events_forward: Sender<Event>) {
+    while let Ok(event) = events.recv_async().await {
+        {
+            let mut status = status.lock().unwrap();
+            status.completed_turns = event.get_completed_turns();
+            match &event {
+                Event::StateChange { new_state, .. } => status.state = *new_state,
+                Event::CellFlipped { cell, .. } => status.flip(&[*cell]),
+                Event::CellsFlipped { cells, .. } => status.flip(cells),
+                _ => (),
+            }
+        }
+        if events_forward.send_async(event).await.is_err() {
+            break;
+        }
+    }
+}
+
+/// Answer a single request, closing the connection afterwards.
+async fn handle(
+    stream: TcpStream,
+    status: &Mutex<Status>,
+    key_presses: &Sender<Keycode>,
+    rule: &Rule,
+) -> Result<()> {
+    let mut stream = BufReader::new(stream);
+    let (method, target) = timeout(REQUEST_TIMEOUT, read_request_head(&mut stream)).await
+        .context("The request took too long")??;
+    let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+
+    let response = match (method.as_str(), path) {
+        ("GET", "/status") => Response::json(200, &*status.lock().unwrap()),
+        ("GET", "/board") => {
+            let format = query.split('&')
+                .find_map(|pair| pair.strip_prefix("format="))
+                .unwrap_or("pgm");
+            let encoding = match format {
+                "png" => Some(("image/png", None)),
+                "pgm" => Some(("image/x-portable-graymap", Some(Format::Pgm))),
+                "rle" => Some(("text/plain", Some(Format::Rle))),
+                "cells" => Some(("text/plain", Some(Format::Cells))),
+                _ => None,
+            };
+            match encoding {
+                Some((content_type, format)) => {
+                    let (board, rule) = (Arc::clone(&status.lock().unwrap().board), *rule);
+                    // a large board takes seconds to encode, which would hold up the events following on this thread
+                    let body = tokio::task::spawn_blocking(move || match format {
+                        Some(format) => Ok(board.encode(format, &rule)),
+                        None => encode_png(&board),
+                    }).await??;
+                    Response::new(200, content_type, body)
+                },
+                None => Response::error(400, &format!("Unknown format {}", format)),
+            }
+        },
+        ("POST", "/pause" | "/resume" | "/snapshot" | "/quit") => {
+            // the state is only ever the one the distributor last reported, as a frontend can press P too,
+            // so P is only pressed when that state is not already the one asked for
+            let state = status.lock().unwrap().state;
+            let key = match (path, state) {
+                (_, State::Quitting) => Err(()),
+                ("/pause", State::Executing) | ("/resume", State::Pause) => Ok(Some(Keycode::P)),
+                ("/pause" | "/resume", _) => Ok(None),
+                ("/snapshot", _) => Ok(Some(Keycode::S)),
+                _ => Ok(Some(Keycode::Q)),
+            };
+            match key {
+                Ok(key) => {
+                    if let Some(key) = key {
+                        key_presses.send_async(key).await.context("The simulation has stopped")?;
+                    }
+                    Response::json(202, &serde_json::json!({ "ok": true }))
+                },
+                Err(()) => Response::error(409, "The simulation has finished"),
+            }
+        },
+        (_, "/status" | "/board" | "/pause" | "/resume" | "/snapshot" | "/quit") =>
+            Response::error(405, &format!("{} is not allowed on {}", method, path)),
+        _ => Response::error(404, &format!("{} does not exist", path)),
+    };
+
+    response.write_to(stream.get_mut()).await
+}
+
+/// Read the request line and headers of a request, and return its method and target.
+/// Requests are not expected to have a body, and a head longer than `MAX_HEAD` is answered with 431.
+pub(crate) async fn read_request_head(stream: &mut BufReader<TcpStream>) -> Result<(String, String)> {
+    let mut head = (&mut *stream).take(MAX_HEAD);
+    let (mut request_line, mut line) = (String::new(), String::new());
+    head.read_line(&mut request_line).await?;
+    while head.limit() > 0 && head.read_line(&mut line).await? > 0 && !line.trim().is_empty() {
+        line.clear();
+    }
+    // a line cut short by the limit used all of it, so the client sent more than it may
+    if head.limit() == 0 {
+        Response::error(431, "The request head is too large").write_to(stream.get_mut()).await?;
+        bail!("Invalid request head longer than {} bytes", MAX_HEAD);
+    }
+    let mut parts = request_line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        bail!("Invalid request line {:?}", request_line.trim())
+    };
+    Ok((method.to_owned(), target.to_owned()))
+}
+
+fn encode_png(board: &Board) -> Result<Vec<u8>> {
+    let image = GrayImage::from_raw(board.width as u32, board.height as u32, board.cells.as_bytes().to_vec())
+        .context("The board does not match its dimensions")?;
+    let mut bytes = Vec::new();
+    image.write_to(&mut Cursor::new(&mut bytes), ImageFormat::Png)?;
+    Ok(bytes)
+}
+
+pub(crate) struct Response {
+    status: u16,
+    content_type: &'static str,
+    body: Vec<u8>,
+}
+
+impl Response {
+    pub(crate) fn new(status: u16, content_type: &'static str, body: Vec<u8>) -> Self {
+        Response { status, content_type, body }
+    }
+
+    pub(crate) fn json<T: Serialize>(status: u16, body: &T) -> Self {
+        Response::new(status, "application/json", serde_json::to_vec(body).unwrap_or_default())
+    }
+
+    pub(crate) fn error(status: u16, message: &str) -> Self {
+        Response::json(status, &serde_json::json!({ "error": message }))
+    }
+
+    pub(crate) async fn write_to(&self, stream: &mut TcpStream) -> Result<()> {
+        let reason = match self.status {
+            200 => "OK",
+            202 => "Accepted",
+            400 => "Bad Request",
+            404 => "Not Found",
+            405 => "Method Not Allowed",
+            409 => "Conflict",
+            431 => "Request Header Fields Too Large",
+            _ => "",
+        };
+        let head = format!(
+            "HTTP/1.1 {} {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            self.status, reason, self.content_type, self.body.len()
+        );
+        stream.write_all(head.as_bytes()).await?;
+        stream.write_all(&self.body).await?;
+        stream.shutdown().await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,211 @@
+use crate::gol::event::{Event, State};
+use crate::net::http::{read_request_head, Response};
+use crate::util::avgturns::AvgTurns;
+use flume::{Receiver, Sender};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+use tokio::{select, time::timeout};
+
+/// How often the measured turns per second are updated.
+const MEASURE_INTERVAL: Duration = Duration::from_secs(2);
+
+/// How long a scraper may take to send its request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// `Metrics` are the measurements that never travel with the events: the time each worker spends
+/// computing its strip, the workers whose strips were reassigned and the bytes the IO task writes.
+/// They are only kept while the exporter runs.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    io_bytes_written: AtomicU64,
+    reassignments: AtomicU64,
+    compute: Mutex<BTreeMap<String, Duration>>,
+}
+
+impl Metrics {
+    pub fn add_io_bytes_written(&self, bytes: usize) {
+        self.io_bytes_written.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    /// Count a failed worker process whose strip was reassigned to the others.
+    pub fn add_reassignment(&self) {
+        self.reassignments.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Add the time `worker` spent computing a turn of its strip.
+    pub fn add_compute_time(&self, worker: &str, time: Duration) {
+        let mut compute = self.compute.lock().unwrap();
+        match compute.get_mut(worker) {
+            Some(total) => *total += time,
+            None => { compute.insert(worker.to_owned(), time); },
+        }
+    }
+}
+
+/// `Gauges` is what the exporter knows about the simulation, as followed through its events.
+#[derive(Debug, Default)]
+struct Gauges {
+    completed_turns: u32,
+    population: usize,
+    state: Option<State>,
+    turns_per_second: u32,
+    births_total: u64,
+    deaths_total: u64,
+    /// The births and deaths of the last completed turn.
+    turn_births: usize,
+    turn_deaths: usize,
+    /// The births and deaths of the turn in progress, until it completes.
+    pending: (usize, usize),
+    cells: Vec<bool>,
+}
+
+impl Gauges {
+    fn flip(&mut self, i: usize) {
+        let Some(cell) = self.cells.get_mut(i) else { return };
+        *cell = !*cell;
+        match *cell {
+            true => {
+                self.population += 1;
+                self.pending.0 += 1;
+            },
+            false => {
+                self.population -= 1;
+                self.pending.1 += 1;
+            },
+        }
+    }
+
+    fn follow(&mut self, event: &Event, width: usize) {
+        self.completed_turns = event.get_completed_turns();
+        match event {
+            Event::CellFlipped { cell, .. } => self.flip(cell.y * width + cell.x),
+            Event::CellsFlipped { cells, .. } => cells.iter().for_each(|cell| self.flip(cell.y * width + cell.x)),
+            Event::TurnComplete { .. } => {
+                (self.turn_births, self.turn_deaths) = std::mem::take(&mut self.pending);
+                self.births_total += self.turn_births as u64;
+                self.deaths_total += self.turn_deaths as u64;
+            },
+            Event::StateChange { new_state, .. } => {
+                // the cells that were alive when the board was loaded were not born in a turn
+                self.pending = (0, 0);
+                self.state = Some(*new_state);
+            },
+            _ => (),
+        }
+    }
+}
+
+/// Export metrics of the simulation in the Prometheus text format on `listener`, at `GET /metrics`.
+///
+/// Every event from `events` is passed on to `events_forward`, and the gauges are updated as they pass.
+/// The depth of both channels is sampled whenever the metrics are scraped, so a frontend falling
+/// behind shows up as a growing queue on its side.
+pub async fn serve(
+    listener: TcpListener,
+    image_width: usize,
+    image_height: usize,
+    metrics: Arc<Metrics>,
+    events: Receiver<Event>,
+    events_forward: Sender<Event>,
+) {
+    if let Ok(addr) = listener.local_addr() {
+        log::info!(target: "Metrics", "Listening on {}", addr);
+    }
+    let gauges = Arc::new(Mutex::new(Gauges { cells: vec![false; image_width * image_height], ..Default::default() }));
+    let mut avg_turns = AvgTurns::new();
+    let mut measure_interval = tokio::time::interval(MEASURE_INTERVAL);
+
+    loop {
+        select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, _)) => {
+                    let (gauges, metrics) = (Arc::clone(&gauges), Arc::clone(&metrics));
+                    let depths = [("distributor", events.len()), ("frontend", events_forward.len())];
+                    tokio::spawn(async move {
+                        if let Err(e) = handle(stream, &gauges, &metrics, depths).await {
+                            log::warn!(target: "Metrics", "Cannot answer a scrape: {:#}", e);
+                        }
+                    });
+                },
+                Err(e) => log::warn!(target: "Metrics", "Cannot accept a scraper: {}", e),
+            },
+            _ = measure_interval.tick() => {
+                let mut gauges = gauges.lock().unwrap();
+                gauges.turns_per_second = avg_turns.get(gauges.completed_turns);
+            },
+            gol_event = events.recv_async() => {
+                let Ok(event) = gol_event else { break };
+                gauges.lock().unwrap().follow(&event, image_width);
+                if events_forward.send_async(event).await.is_err() {
+                    break;
+                }
+            },
+        }
+    }
+}
+
+/// Answer a single scrape, closing the connection afterwards.
+async fn handle(
+    stream: TcpStream,
+    gauges: &Mutex<Gauges>,
+    metrics: &Metrics,
+    depths: [(&str, usize); 2],
+) -> anyhow::Result<()> {
+    let mut stream = tokio::io::BufReader::new(stream);
+    let (method, target) = timeout(REQUEST_TIMEOUT, read_request_head(&mut stream)).await??;
+    let response = match (method.as_str(), target.as_str()) {
+        ("GET", "/metrics") => Response::new(
+            200,
+            "text/plain; version=0.0.4",
+            render(&gauges.lock().unwrap(), metrics, depths).into_bytes(),
+        ),
+        (_, "/metrics") => Response::error(405, &format!("{} is not allowed on /metrics", method)),
+        _ => Response::error(404, &format!("{} does not exist", target)),
+    };
+    response.write_to(stream.get_mut()).await
+}
+
+fn render(gauges: &Gauges, metrics: &Metrics, depths: [(&str, usize); 2]) -> String {
+    let mut text = String::new();
+    let mut metric = |name: &str, kind: &str, help: &str, samples: &[(String, String)]| {
+        let _ = writeln!(text, "# HELP {} {}\n# TYPE {} {}", name, help, name, kind);
+        for (labels, value) in samples {
+            let _ = writeln!(text, "{}{} {}", name, labels, value);
+        }
+    };
+    let single = |value: String| [(String::new(), value)];
+
+    metric("gol_completed_turns", "gauge", "The number of completed turns.", &single(gauges.completed_turns.to_string()));
+    metric("gol_population", "gauge", "The number of alive cells.", &single(gauges.population.to_string()));
+    let states = [State::Executing, State::Pause, State::Quitting];
+    metric(
+        "gol_state", "gauge", "Whether the simulation is in each state.",
+        &states.map(|state| (format!("{{state=\"{}\"}}", state), u8::from(gauges.state == Some(state)).to_string())),
+    );
+    metric("gol_turns_per_second", "gauge", "The measured turns per second.", &single(gauges.turns_per_second.to_string()));
+    metric("gol_turn_births", "gauge", "The cells born in the last completed turn.", &single(gauges.turn_births.to_string()));
+    metric("gol_turn_deaths", "gauge", "The cells that died in the last completed turn.", &single(gauges.turn_deaths.to_string()));
+    metric("gol_births_total", "counter", "The cells born in every completed turn.", &single(gauges.births_total.to_string()));
+    metric("gol_deaths_total", "counter", "The cells that died in every completed turn.", &single(gauges.deaths_total.to_string()));
+    let compute = metrics.compute.lock().unwrap().iter()
+        .map(|(worker, time)| (format!("{{worker=\"{}\"}}", worker), time.as_secs_f64().to_string()))
+        .collect::<Vec<_>>();
+    metric("gol_worker_compute_seconds_total", "counter", "The time each worker spent computing its strip.", &compute);
+    metric(
+        "gol_worker_reassignments_total", "counter", "The failed workers whose strips were reassigned.",
+        &single(metrics.reassignments.load(Ordering::Relaxed).to_string()),
+    );
+    metric(
+        "gol_event_channel_depth", "gauge", "The events waiting on each side of the exporter.",
+        &depths.map(|(side, depth)| (format!("{{side=\"{}\"}}", side), depth.to_string())),
+    );
+    metric(
+        "gol_io_bytes_written_total", "counter", "The bytes of images and checkpoints written.",
+        &single(metrics.io_bytes_written.load(Ordering::Relaxed).to_string()),
+    );
+    text
+}
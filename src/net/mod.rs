@@ -0,0 +1,7 @@
+pub mod cluster;
+pub mod controller;
+pub mod http;
+pub mod metrics;
+pub mod protocol;
+pub mod server;
+pub mod worker;
@@ -0,0 +1,140 @@
+use crate::gol::{event::{Event, State}, rule::Rule};
+use anyhow::{bail, Context, Result};
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{tcp::{OwnedReadHalf, OwnedWriteHalf}, TcpStream, ToSocketAddrs};
+
+/// `Request` is a message from a remote controller to the server.
+/// Messages are JSON objects, one per line, e.g. `{"command":"pause"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    /// Start receiving every `Event` of the simulation.
+    Subscribe,
+    /// Stop receiving events.
+    Unsubscribe,
+    /// Receive a snapshot of the current board and state, followed by every further `Event`.
+    Attach,
+    /// Receive the current turn, population and state.
+    Status,
+    /// Pause the simulation if it is executing.
+    Pause,
+    /// Resume the simulation if it is paused.
+    Resume,
+    /// Write the current world to an image.
+    Snapshot,
+    /// Finish the simulation as if `Q` was pressed.
+    Quit,
+    /// Finish the simulation as if `K` was pressed, and stop the server too.
+    Shutdown,
+}
+
+/// `Response` is a message from the server to a remote controller.
+/// Every `Request` is answered by `Ok` or `Error`, except `Attach` and `Status` which are answered
+/// by `Attached` and `Status`, and subscribers receive `Event`s in between.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Response {
+    Ok,
+    /// The dimensions of the world, followed by a `CellsFlipped` event with every alive cell
+    /// and a `StateChange` event with the current state.
+    Attached { image_width: usize, image_height: usize, rule: Rule },
+    Status { completed_turns: u32, population: usize, state: State },
+    Error { message: String },
+    Event { event: Event },
+}
+
+/// `Message` is either side of the protocol, with the longest line a peer may send of it.
+pub trait Message: Serialize + DeserializeOwned {
+    const MAX_LEN: u64;
+}
+
+impl Message for Request {
+    /// Requests are a single command, so anything longer is not a controller talking.
+    const MAX_LEN: u64 = 4 * 1024;
+}
+
+impl Message for Response {
+    /// Responses carry events, which can list every cell of the world.
+    const MAX_LEN: u64 = 1 << 30;
+}
+
+/// Write a message as a single line of JSON.
+pub async fn write_message<W, T>(writer: &mut W, message: &T) -> Result<()>
+where
+    W: AsyncWrite + Unpin,
+    T: Serialize,
+{
+    let mut line = serde_json::to_vec(message)?;
+    line.push(b'\n');
+    writer.write_all(&line).await?;
+    Ok(())
+}
+
+/// Read a message from a single line of JSON, or `None` once the connection is closed.
+/// A line longer than the message may be is skipped and refused, so the next message can still be read.
+pub async fn read_message<R, T>(reader: &mut R) -> Result<Option<T>>
+where
+    R: AsyncBufRead + Unpin,
+    T: Message,
+{
+    let mut line = Vec::new();
+    if (&mut *reader).take(T::MAX_LEN).read_until(b'\n', &mut line).await? == 0 {
+        return Ok(None)
+    }
+    if !line.ends_with(b"\n") && line.len() as u64 == T::MAX_LEN {
+        skip_line(reader).await?;
+        bail!("Invalid message longer than {} bytes", T::MAX_LEN);
+    }
+    Ok(Some(serde_json::from_slice(&line)
+        .with_context(|| format!("Invalid message {}", String::from_utf8_lossy(&line).trim()))?))
+}
+
+/// Discard the rest of a line without holding on to it.
+async fn skip_line<R: AsyncBufRead + Unpin>(reader: &mut R) -> Result<()> {
+    loop {
+        let buf = reader.fill_buf().await?;
+        if buf.is_empty() {
+            return Ok(())
+        }
+        match buf.iter().position(|&byte| byte == b'\n') {
+            Some(end) => {
+                reader.consume(end + 1);
+                return Ok(())
+            },
+            None => {
+                let len = buf.len();
+                reader.consume(len);
+            },
+        }
+    }
+}
+
+/// `Connection` is the controller side of a connection to a server.
+pub struct Connection {
+    reader: BufReader<OwnedReadHalf>,
+    writer: OwnedWriteHalf,
+}
+
+impl Connection {
+    pub async fn connect<A: ToSocketAddrs>(addr: A) -> Result<Self> {
+        let stream = TcpStream::connect(addr).await.context("Cannot connect to the server")?;
+        stream.set_nodelay(true)?;
+        let (reader, writer) = stream.into_split();
+        Ok(Connection { reader: BufReader::new(reader), writer })
+    }
+
+    pub async fn send(&mut self, request: &Request) -> Result<()> {
+        write_message(&mut self.writer, request).await
+    }
+
+    /// Receive the next message, or `None` once the server has closed the connection.
+    pub async fn recv(&mut self) -> Result<Option<Response>> {
+        read_message(&mut self.reader).await
+    }
+
+    /// Split the connection, so requests can be sent while waiting for a response.
+    pub fn into_split(self) -> (BufReader<OwnedReadHalf>, OwnedWriteHalf) {
+        (self.reader, self.writer)
+    }
+}
@@ -0,0 +1,251 @@
+use crate::args::Args;
+use crate::gol::{event::{Event, State}, rule::Rule};
+use crate::net::protocol::{read_message, write_message, Request, Response};
+use crate::util::cell::CellCoord;
+use anyhow::Result;
+use flume::{Receiver, Sender, TrySendError};
+use sdl2::keyboard::Keycode;
+use std::{collections::{HashMap, HashSet}, time::Duration};
+use tokio::{io::BufReader, net::{tcp::OwnedReadHalf, TcpListener}, select, task::JoinHandle};
+
+/// The number of messages queued for a client before it is considered too slow and disconnected.
+const CLIENT_BUFFER: usize = 4096;
+
+/// How long the last messages may take to reach the clients once the simulation has quit.
+const FLUSH_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// `ServerOptions` describes how a server treats its clients.
+#[derive(Debug, Clone, Default)]
+pub struct ServerOptions {
+    /// The dimensions of the world, which attaching clients are told about.
+    pub image_width: usize,
+    pub image_height: usize,
+    /// The rule of the simulation, which attaching clients write into the patterns they save.
+    /// Arguments give no rule, so it is the default until it is set from the `Params` of the run.
+    pub rule: Rule,
+    /// Keep serving after the simulation has finished, until a client asks for a shutdown
+    /// or the process is interrupted.
+    pub persistent: bool,
+}
+
+impl From<&Args> for ServerOptions {
+    fn from(args: &Args) -> Self {
+        ServerOptions {
+            image_width: args.width(),
+            image_height: args.height(),
+            rule: Rule::default(),
+            persistent: args.persistent,
+        }
+    }
+}
+
+enum ClientMessage {
+    Request { id: usize, request: Request },
+    Invalid { id: usize, message: String },
+    Disconnected { id: usize },
+}
+
+struct Client {
+    responses: Sender<Response>,
+    subscribed: bool,
+    writer: JoinHandle<()>,
+}
+
+/// Expose a running simulation to remote controllers connecting to `listener`.
+/// Every event is passed on to `events_forward`, so the server can sit in front of the SDL window
+/// or the headless loop. Clients can subscribe to the events and control the simulation through
+/// `key_presses`, and any of them may disconnect at any time without affecting the simulation.
+/// The server follows the board through the events, so clients attaching mid-run get a snapshot of it.
+pub async fn serve(
+    listener: TcpListener,
+    options: ServerOptions,
+    events: Receiver<Event>,
+    events_forward: Sender<Event>,
+    key_presses: Sender<Keycode>,
+) -> Result<()> {
+    log::info!(target: "Server", "Listening on {}", listener.local_addr()?);
+    let (messages_tx, messages_rx) = flume::unbounded::<ClientMessage>();
+    let mut clients = HashMap::<usize, Client>::new();
+    let mut next_id = 0;
+    let mut state = State::Executing;
+    let mut completed_turns = 0;
+    let mut alive = HashSet::<CellCoord>::new();
+    // whether the simulation has sent its last event, and whether the server should stop with it
+    let mut finished = false;
+    let mut shutting_down = false;
+
+    while !finished || (options.persistent && !shutting_down) {
+        select! {
+            accepted = listener.accept() => match accepted {
+                Ok((stream, addr)) => {
+                    log::info!(target: "Server", "Client {} connected from {}", next_id, addr);
+                    let _ = stream.set_nodelay(true);
+                    let (reader, mut writer) = stream.into_split();
+                    let (responses_tx, responses_rx) = flume::bounded::<Response>(CLIENT_BUFFER);
+                    tokio::spawn(read_requests(next_id, reader, messages_tx.clone()));
+                    let writer = tokio::spawn(async move {
+                        while let Ok(response) = responses_rx.recv_async().await {
+                            if write_message(&mut writer, &response).await.is_err() {
+                                break;
+                            }
+                        }
+                    });
+                    clients.insert(next_id, Client { responses: responses_tx, subscribed: false, writer });
+                    next_id += 1;
+                },
+                Err(e) => log::warn!(target: "Server", "Cannot accept a client: {}", e),
+            },
+            message = messages_rx.recv_async() => match message? {
+                ClientMessage::Request { id, request } => {
+                    let response = match request {
+                        Request::Attach => {
+                            send(&mut clients, id, Response::Attached {
+                                image_width: options.image_width,
+                                image_height: options.image_height,
+                                rule: options.rule,
+                            });
+                            let mut cells = alive.iter().copied().collect::<Vec<CellCoord>>();
+                            cells.sort_by_key(|cell| (cell.y, cell.x));
+                            send(&mut clients, id, Response::Event {
+                                event: Event::CellsFlipped { completed_turns, cells },
+                            });
+                            send(&mut clients, id, Response::Event {
+                                event: Event::StateChange { completed_turns, new_state: state },
+                            });
+                            if let Some(client) = clients.get_mut(&id) {
+                                client.subscribed = true;
+                            }
+                            continue;
+                        },
+                        Request::Status => Response::Status { completed_turns, population: alive.len(), state },
+                        Request::Subscribe | Request::Unsubscribe => {
+                            if let Some(client) = clients.get_mut(&id) {
+                                client.subscribed = request == Request::Subscribe;
+                            }
+                            Response::Ok
+                        },
+                        Request::Shutdown => {
+                            let response = match !finished && !shutting_down {
+                                true => press(&key_presses, Keycode::K).await,
+                                false => Response::Ok,
+                            };
+                            shutting_down = true;
+                            response
+                        },
+                        // the simulation stops reading key presses once it has finished
+                        _ if finished => Response::Error { message: "The simulation has finished".into() },
+                        Request::Pause | Request::Resume => {
+                            let target = if request == Request::Pause { State::Pause } else { State::Executing };
+                            if state == target || state == State::Quitting {
+                                Response::Ok
+                            } else {
+                                let response = press(&key_presses, Keycode::P).await;
+                                if let Response::Ok = response {
+                                    state = target;
+                                }
+                                response
+                            }
+                        },
+                        Request::Snapshot => press(&key_presses, Keycode::S).await,
+                        Request::Quit => press(&key_presses, Keycode::Q).await,
+                    };
+                    send(&mut clients, id, response);
+                },
+                ClientMessage::Invalid { id, message } => send(&mut clients, id, Response::Error { message }),
+                ClientMessage::Disconnected { id } => {
+                    log::info!(target: "Server", "Client {} disconnected", id);
+                    clients.remove(&id);
+                },
+            },
+            gol_event = events.recv_async(), if !finished => {
+                let Ok(event) = gol_event else {
+                    finished = true;
+                    continue;
+                };
+                completed_turns = event.get_completed_turns();
+                match &event {
+                    Event::StateChange { new_state, .. } => state = *new_state,
+                    Event::CellFlipped { cell, .. } => flip(&mut alive, *cell),
+                    Event::CellsFlipped { cells, .. } => cells.iter().for_each(|cell| flip(&mut alive, *cell)),
+                    _ => (),
+                }
+                let subscribers = clients.iter()
+                    .filter(|(_, client)| client.subscribed)
+                    .map(|(&id, _)| id)
+                    .collect::<Vec<usize>>();
+                for id in subscribers {
+                    send(&mut clients, id, Response::Event { event: event.clone() });
+                }
+                let _ = events_forward.send_async(event).await;
+                if state == State::Quitting {
+                    finished = true;
+                }
+            },
+            _ = tokio::signal::ctrl_c(), if options.persistent && !shutting_down => {
+                log::info!(target: "Server", "Interrupted, shutting down");
+                if !finished {
+                    press(&key_presses, Keycode::K).await;
+                }
+                shutting_down = true;
+            },
+        }
+    }
+
+    // Let the clients receive everything up to the final events before closing their connections.
+    let writers = clients.into_values().map(|client| client.writer).collect::<Vec<_>>();
+    let _ = tokio::time::timeout(FLUSH_TIMEOUT, async {
+        for writer in writers {
+            let _ = writer.await;
+        }
+    }).await;
+    Ok(())
+}
+
+/// Pass a key press on to the simulation. If it no longer takes key presses,
+/// only the client that asked for it is told, rather than the whole server stopping.
+async fn press(key_presses: &Sender<Keycode>, key: Keycode) -> Response {
+    match key_presses.send_async(key).await {
+        Ok(()) => Response::Ok,
+        Err(_) => {
+            log::warn!(target: "Server", "Cannot pass a key press on, as the simulation no longer takes them");
+            Response::Error { message: "The simulation no longer takes key presses".into() }
+        },
+    }
+}
+
+fn flip(alive: &mut HashSet<CellCoord>, cell: CellCoord) {
+    if !alive.remove(&cell) {
+        alive.insert(cell);
+    }
+}
+
+/// Queue a response for a client, disconnecting it if it cannot keep up.
+fn send(clients: &mut HashMap<usize, Client>, id: usize, response: Response) {
+    let Some(client) = clients.get(&id) else { return };
+    match client.responses.try_send(response) {
+        Ok(()) => (),
+        Err(TrySendError::Full(_)) => {
+            log::warn!(target: "Server", "Client {} cannot keep up and has been disconnected", id);
+            clients.remove(&id);
+        },
+        Err(TrySendError::Disconnected(_)) => {
+            clients.remove(&id);
+        },
+    }
+}
+
+async fn read_requests(id: usize, reader: OwnedReadHalf, messages: Sender<ClientMessage>) {
+    let mut reader = BufReader::new(reader);
+    loop {
+        let message = match read_message::<_, Request>(&mut reader).await {
+            Ok(Some(request)) => ClientMessage::Request { id, request },
+            Ok(None) => break,
+            Err(e) if e.is::<std::io::Error>() => break,
+            Err(e) => ClientMessage::Invalid { id, message: format!("{:#}", e) },
+        };
+        if messages.send_async(message).await.is_err() {
+            return;
+        }
+    }
+    let _ = messages.send_async(ClientMessage::Disconnected { id }).await;
+}
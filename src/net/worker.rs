@@ -0,0 +1,191 @@
+use crate::gol::strip::Strip;
+use crate::net::cluster::{read_halo, write_halo, PeerLink, WorkerRequest, WorkerResponse};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::io::{BufReader, BufWriter, Write};
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::{Arc, Condvar, Mutex};
+use std::time::{Duration, Instant};
+
+/// `HaloLink` is the connection to the worker holding the strip just above or just below this one.
+struct HaloLink {
+    reader: BufReader<TcpStream>,
+    writer: BufWriter<TcpStream>,
+}
+
+impl HaloLink {
+    fn new(reader: BufReader<TcpStream>, timeout: Duration) -> Result<Self> {
+        let stream = reader.get_ref();
+        stream.set_nodelay(true)?;
+        stream.set_read_timeout(Some(timeout))?;
+        stream.set_write_timeout(Some(timeout))?;
+        Ok(HaloLink { writer: BufWriter::new(stream.try_clone()?), reader })
+    }
+
+    /// Open the halo link to the worker below, which is waiting for `link.token`.
+    fn open(link: &PeerLink, timeout: Duration) -> Result<Self> {
+        let addr = link.addr.to_socket_addrs()?.next()
+            .with_context(|| format!("Cannot resolve worker {}", link.addr))?;
+        let stream = TcpStream::connect_timeout(&addr, timeout)
+            .with_context(|| format!("Cannot open a halo link to worker {}", link.addr))?;
+        let mut halo = HaloLink::new(BufReader::new(stream), timeout)?;
+        WorkerRequest::Peer { token: link.token }.write_to(&mut halo.writer)?;
+        halo.writer.flush()?;
+        Ok(halo)
+    }
+}
+
+/// `PendingLinks` holds the halo links opened by other workers until the strip they belong to claims them.
+#[derive(Default)]
+struct PendingLinks {
+    links: Mutex<HashMap<u64, BufReader<TcpStream>>>,
+    arrived: Condvar,
+}
+
+impl PendingLinks {
+    fn insert(&self, token: u64, reader: BufReader<TcpStream>) {
+        self.links.lock().unwrap().insert(token, reader);
+        self.arrived.notify_all();
+    }
+
+    /// Wait for the worker above to open the halo link identified by `token`.
+    fn claim(&self, token: u64, timeout: Duration) -> Result<HaloLink> {
+        let links = self.links.lock().unwrap();
+        let (mut links, _) = self.arrived
+            .wait_timeout_while(links, timeout, |links| !links.contains_key(&token))
+            .unwrap();
+        let reader = links.remove(&token).context("The worker above did not open its halo link in time")?;
+        HaloLink::new(reader, timeout)
+    }
+}
+
+/// `Assignment` is a strip together with the halo links to its neighbours, where it needs them.
+struct Assignment {
+    strip: Strip,
+    above: Option<HaloLink>,
+    below: Option<HaloLink>,
+}
+
+impl Assignment {
+    /// Swap the boundary rows with the neighbours and advance the strip by one turn,
+    /// returning the cells that flipped and the time spent computing them.
+    /// Rows are sent on another thread while the halos are received, so neither side can block the other.
+    fn step(&mut self) -> Result<(Vec<u32>, Duration)> {
+        let Assignment { strip, above, below } = self;
+        let (above_writer, above_reader) = above.as_mut().map(|link| (&mut link.writer, &mut link.reader)).unzip();
+        let (below_writer, below_reader) = below.as_mut().map(|link| (&mut link.writer, &mut link.reader)).unzip();
+        let (top, bottom) = (strip.top_row(), strip.bottom_row());
+        let (above_row, below_row) = std::thread::scope(|scope| {
+            let sending = scope.spawn(move || -> Result<()> {
+                for (writer, row) in [(above_writer, top), (below_writer, bottom)] {
+                    if let Some(writer) = writer {
+                        write_halo(writer, row)?;
+                        writer.flush()?;
+                    }
+                }
+                Ok(())
+            });
+            let width = top.len();
+            let receive = |reader: Option<&mut BufReader<TcpStream>>| match reader {
+                Some(reader) => read_halo(reader, width),
+                None => Ok(Vec::new()),
+            };
+            let halos = receive(above_reader)
+                .and_then(|above| Ok((above, receive(below_reader)?)));
+            sending.join().expect("The halo sender panicked")?;
+            halos
+        })?;
+        let started = Instant::now();
+        let flipped = strip.step(&above_row, &below_row);
+        Ok((flipped, started.elapsed()))
+    }
+}
+
+/// Compute strips of the world for any broker connecting to `listener`, until the process is stopped.
+/// Other workers connect to the same listener to open halo links.
+pub fn serve(listener: TcpListener) -> Result<()> {
+    log::info!(target: "Worker", "Listening on {}", listener.local_addr()?);
+    let pending = Arc::new(PendingLinks::default());
+    for stream in listener.incoming() {
+        let stream = match stream {
+            Ok(stream) => stream,
+            Err(e) => {
+                log::warn!(target: "Worker", "Cannot accept a connection: {}", e);
+                continue;
+            },
+        };
+        let pending = Arc::clone(&pending);
+        std::thread::spawn(move || {
+            let peer = stream.peer_addr().map(|addr| addr.to_string()).unwrap_or_default();
+            if let Err(e) = accept(stream, &peer, &pending) {
+                log::warn!(target: "Worker", "Connection {} failed: {:#}", peer, e);
+            }
+        });
+    }
+    Ok(())
+}
+
+/// Tell halo links from brokers by their first message.
+fn accept(stream: TcpStream, peer: &str, pending: &PendingLinks) -> Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    match WorkerRequest::read_from(&mut reader)? {
+        Some(WorkerRequest::Peer { token }) => pending.insert(token, reader),
+        Some(request) => {
+            log::info!(target: "Worker", "Broker {} connected", peer);
+            work(request, reader, stream, pending)?;
+            log::info!(target: "Worker", "Broker {} disconnected", peer);
+        },
+        None => (),
+    }
+    Ok(())
+}
+
+fn work(first: WorkerRequest, mut reader: BufReader<TcpStream>, stream: TcpStream, pending: &PendingLinks) -> Result<()> {
+    stream.set_nodelay(true)?;
+    let mut writer = BufWriter::new(stream);
+    let mut assignment = None;
+
+    let mut request = Some(first);
+    while let Some(next) = request {
+        let response = match next {
+            WorkerRequest::Assign { width, height, start, rule, timeout, above, below, strip: cells } => {
+                log::debug!(
+                    target: "Worker",
+                    "Assigned rows {}..{}", start, start + cells.len() / width
+                );
+                let strip = Strip::new(width, height, start, rule, cells);
+                // open the link below before waiting for the one above, as every worker does,
+                // so the workers never wait on each other in a circle
+                let links = below.map(|below| HaloLink::open(&below, timeout)).transpose()
+                    .and_then(|below| Ok((above.map(|token| pending.claim(token, timeout)).transpose()?, below)));
+                match links {
+                    Ok((above, below)) => {
+                        assignment = Some(Assignment { strip, above, below });
+                        WorkerResponse::Assigned
+                    },
+                    Err(e) => {
+                        assignment = None;
+                        WorkerResponse::LinkFailed { message: format!("{:#}", e) }
+                    },
+                }
+            },
+            WorkerRequest::Step => {
+                let current = assignment.as_mut().context("Asked to step before being assigned a strip")?;
+                match current.step() {
+                    Ok((flipped, compute)) => WorkerResponse::Stepped { flipped, compute },
+                    Err(e) => {
+                        // the strip may be a turn behind its neighbours now, so it has to be assigned again
+                        assignment = None;
+                        WorkerResponse::LinkFailed { message: format!("{:#}", e) }
+                    },
+                }
+            },
+            WorkerRequest::Peer { .. } => anyhow::bail!("A broker cannot open a halo link"),
+        };
+        response.write_to(&mut writer)?;
+        writer.flush()?;
+        request = WorkerRequest::read_from(&mut reader)?;
+    }
+    Ok(())
+}
+
@@ -0,0 +1,154 @@
+use crate::gol::event::{Event, State};
+use anyhow::{anyhow, Context, Result};
+use flume::{Receiver, Sender};
+use sdl2::keyboard::Keycode;
+use serde::{Deserialize, Serialize};
+use std::fs::{create_dir_all, File};
+use std::io::{BufWriter, Write};
+use std::{path::{Path, PathBuf}, thread::JoinHandle, time::{Duration, Instant}};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+/// `LogHeader` is the first line of an event log, describing the world the events belong to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LogHeader {
+    pub image_width: usize,
+    pub image_height: usize,
+}
+
+/// `LogEntry` is every further line of an event log: an event and when it was sent,
+/// in milliseconds since the log was started.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogEntry {
+    pub elapsed_ms: u64,
+    pub event: Event,
+}
+
+/// `EventLogger` writes every event it is fed to a JSON-lines log, which can be replayed later.
+/// Writing happens on a separate thread, so logging never holds up the event loop.
+pub struct EventLogger {
+    path: PathBuf,
+    started: Instant,
+    entries_tx: Option<Sender<LogEntry>>,
+    writer: Option<JoinHandle<Result<()>>>,
+}
+
+impl EventLogger {
+    /// Start a new log of the events of a `width`x`height` world.
+    pub fn start<P: Into<PathBuf>>(path: P, width: usize, height: usize) -> Result<Self> {
+        let path = path.into();
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir)?;
+        }
+        let file = File::create(&path)
+            .with_context(|| format!("Cannot create event log {}", path.display()))?;
+        let mut file = BufWriter::new(file);
+        serde_json::to_writer(&mut file, &LogHeader { image_width: width, image_height: height })?;
+        file.write_all(b"\n")?;
+
+        let (entries_tx, entries_rx) = flume::unbounded::<LogEntry>();
+        let writer = std::thread::spawn(move || {
+            for entry in entries_rx.iter() {
+                serde_json::to_writer(&mut file, &entry)?;
+                file.write_all(b"\n")?;
+            }
+            file.flush()?;
+            Ok(())
+        });
+
+        Ok(EventLogger {
+            path,
+            started: Instant::now(),
+            entries_tx: Some(entries_tx),
+            writer: Some(writer),
+        })
+    }
+
+    /// Feed a Game of Life event to the log.
+    pub fn on_event(&mut self, event: &Event) {
+        if let Some(entries_tx) = &self.entries_tx {
+            // The writer only goes away if it failed, which `finish` will report.
+            let _ = entries_tx.send(LogEntry {
+                elapsed_ms: self.started.elapsed().as_millis() as u64,
+                event: event.clone(),
+            });
+        }
+    }
+
+    /// Stop logging and wait for the remaining events to be written.
+    pub fn finish(mut self) -> Result<PathBuf> {
+        self.entries_tx.take();
+        self.writer.take().context("The event log has already been finished")?
+            .join().map_err(|_| anyhow!("The event log writer thread panicked"))??;
+        Ok(self.path.clone())
+    }
+}
+
+/// Read the header of the event log at `path`.
+pub async fn read_header<P: AsRef<Path>>(path: P) -> Result<LogHeader> {
+    let path = path.as_ref();
+    let file = tokio::fs::File::open(path).await
+        .with_context(|| format!("Cannot open event log {}", path.display()))?;
+    let line = BufReader::new(file).lines().next_line().await?
+        .with_context(|| format!("The event log {} is empty", path.display()))?;
+    serde_json::from_str(&line).with_context(|| format!("Invalid header in event log {}", path.display()))
+}
+
+/// Send the events of the log at `path` to `events`, as they were originally sent but `speed` times
+/// faster, or without any delay if `speed` is 0. Nothing is computed, so the replay matches the log exactly.
+/// `P` pauses and resumes the replay, and `Q` stops it.
+pub async fn replay<P: AsRef<Path>>(
+    path: P,
+    speed: f64,
+    events: Sender<Event>,
+    key_presses: Receiver<Keycode>,
+) -> Result<()> {
+    let path = path.as_ref();
+    let file = tokio::fs::File::open(path).await
+        .with_context(|| format!("Cannot open event log {}", path.display()))?;
+    let mut lines = BufReader::new(file).lines();
+    // the header has been read already to set up the frontend
+    lines.next_line().await?;
+
+    let mut started = tokio::time::Instant::now();
+    let mut paused_at: Option<tokio::time::Instant> = None;
+    let mut key_presses_open = true;
+    let mut line_number = 1;
+    while let Some(line) = lines.next_line().await? {
+        line_number += 1;
+        let entry: LogEntry = serde_json::from_str(&line)
+            .with_context(|| format!("Invalid event on line {} of {}", line_number, path.display()))?;
+        let due = match speed > 0.0 {
+            true => Duration::from_secs_f64(entry.elapsed_ms as f64 / 1000.0 / speed),
+            false => Duration::ZERO,
+        };
+
+        loop {
+            let sleep = tokio::time::sleep_until(started + due);
+            tokio::select! {
+                _ = sleep, if paused_at.is_none() => break,
+                key = key_presses.recv_async(), if key_presses_open => match key {
+                    Ok(Keycode::Q) => return Ok(()),
+                    Err(_) => {
+                        // nothing can resume the replay any more
+                        key_presses_open = false;
+                        if let Some(paused_at) = paused_at.take() {
+                            started += paused_at.elapsed();
+                        }
+                    },
+                    Ok(Keycode::P) => match paused_at.take() {
+                        // the time spent paused is added to every further event
+                        Some(paused_at) => started += paused_at.elapsed(),
+                        None => paused_at = Some(tokio::time::Instant::now()),
+                    },
+                    Ok(_) => (),
+                },
+            }
+        }
+
+        let quitting = matches!(entry.event, Event::StateChange { new_state: State::Quitting, .. });
+        if events.send_async(entry.event).await.is_err() || quitting {
+            break;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,208 @@
+use crate::args::Args;
+use crate::gol::event::{Event, State};
+use crate::util::cell::CellCoord;
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use flume::Sender;
+use std::{borrow::Cow, fs::{create_dir_all, File}, io::BufWriter, path::PathBuf, thread::JoinHandle};
+
+/// Palette represents the pair of colours used for dead and alive cells in a recording.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Palette {
+    /// White cells on a black background, as in the SDL window.
+    #[default]
+    Classic,
+    /// Black cells on a white background.
+    Inverted,
+    /// Green phosphor cells on a dark background.
+    Green,
+    /// Amber phosphor cells on a dark background.
+    Amber,
+}
+
+impl Palette {
+    /// Get the GIF colour table for the palette, dead cells first and alive cells second.
+    pub fn colours(&self) -> [u8; 6] {
+        match self {
+            Palette::Classic => [0x00, 0x00, 0x00, 0xFF, 0xFF, 0xFF],
+            Palette::Inverted => [0xFF, 0xFF, 0xFF, 0x00, 0x00, 0x00],
+            Palette::Green => [0x0A, 0x14, 0x0A, 0x33, 0xFF, 0x66],
+            Palette::Amber => [0x14, 0x0F, 0x05, 0xFF, 0xB0, 0x00],
+        }
+    }
+}
+
+/// `RecordOptions` describes how a run should be recorded into an animated GIF.
+#[derive(Debug, Clone)]
+pub struct RecordOptions {
+    /// Number of turns between two recorded frames.
+    pub stride: u32,
+    /// Size in pixels of a single cell in the recording.
+    pub scale: u32,
+    pub palette: Palette,
+    /// Delay between two frames in units of 10 ms.
+    pub delay: u16,
+    /// Maximum playback duration of the recording in seconds.
+    pub max_secs: u32,
+}
+
+impl From<&Args> for RecordOptions {
+    fn from(args: &Args) -> Self {
+        RecordOptions {
+            stride: args.record_stride.max(1),
+            scale: args.record_scale.max(1),
+            palette: args.record_palette,
+            delay: (100 / args.fps.max(1)).clamp(2, u16::MAX as usize) as u16,
+            max_secs: args.record_max_secs,
+        }
+    }
+}
+
+/// `GifRecorder` listens to `CellsFlipped`/`CellFlipped` and `TurnComplete` events
+/// and encodes every `stride`-th turn as a frame of an animated GIF.
+/// A recording started mid-run gets its first frame at the next recorded turn.
+/// Turns skipped by coalesced events repeat the board they end on, so the GIF keeps time.
+/// Encoding happens on a separate thread, so recording never holds up the event loop.
+pub struct GifRecorder {
+    path: PathBuf,
+    width: usize,
+    height: usize,
+    board: Vec<u8>,
+    stride: u32,
+    /// The last turn completed, once one has been.
+    turn: Option<u32>,
+    frames: u32,
+    max_frames: u32,
+    frames_tx: Option<Sender<Vec<u8>>>,
+    encoder: Option<JoinHandle<Result<()>>>,
+}
+
+impl GifRecorder {
+    /// Start a new recording of a `width`x`height` world whose currently alive cells are `alive`.
+    pub fn start<P: Into<PathBuf>>(
+        path: P,
+        options: &RecordOptions,
+        width: usize,
+        height: usize,
+        alive: &[CellCoord],
+    ) -> Result<Self> {
+        let path = path.into();
+        let (scaled_width, scaled_height) = (width * options.scale as usize, height * options.scale as usize);
+        let (scaled_width, scaled_height) = (
+            u16::try_from(scaled_width).context("The recording is too wide for a GIF")?,
+            u16::try_from(scaled_height).context("The recording is too high for a GIF")?,
+        );
+        if let Some(dir) = path.parent() {
+            create_dir_all(dir)?;
+        }
+        let file = File::create(&path)
+            .with_context(|| format!("Cannot create recording {}", path.display()))?;
+        let mut encoder = ::gif::Encoder::new(
+            BufWriter::new(file),
+            scaled_width,
+            scaled_height,
+            &options.palette.colours()
+        )?;
+        encoder.set_repeat(::gif::Repeat::Infinite)?;
+
+        let (frames_tx, frames_rx) = flume::unbounded::<Vec<u8>>();
+        let (scale, delay) = (options.scale as usize, options.delay);
+        let encoder = std::thread::spawn(move || {
+            let mut buffer = vec![0_u8; scaled_width as usize * scaled_height as usize];
+            for board in frames_rx.iter() {
+                scale_board(&board, width, scale, &mut buffer);
+                encoder.write_frame(&::gif::Frame {
+                    width: scaled_width,
+                    height: scaled_height,
+                    delay,
+                    buffer: Cow::Borrowed(&buffer),
+                    ..Default::default()
+                })?;
+            }
+            encoder.into_inner()?;
+            Ok(())
+        });
+
+        let mut board = vec![0_u8; width * height];
+        alive.iter().for_each(|cell| board[cell.y * width + cell.x] = 1);
+        Ok(GifRecorder {
+            path,
+            width,
+            height,
+            board,
+            stride: options.stride,
+            turn: None,
+            frames: 0,
+            max_frames: (options.max_secs * 100 / options.delay as u32).max(1),
+            frames_tx: Some(frames_tx),
+            encoder: Some(encoder),
+        })
+    }
+
+    /// Feed a Game of Life event to the recorder.
+    /// Returns `false` once the recording has reached its maximum duration and should be finished.
+    pub fn on_event(&mut self, event: &Event) -> bool {
+        match event {
+            Event::CellFlipped { cell, .. } => self.flip(cell),
+            Event::CellsFlipped { cells, .. } => cells.iter().for_each(|cell| self.flip(cell)),
+            Event::TurnComplete { completed_turns } => {
+                let recorded = match self.turn {
+                    Some(turn) if turn < *completed_turns => completed_turns / self.stride - turn / self.stride,
+                    _ => (completed_turns % self.stride == 0) as u32,
+                };
+                self.turn = Some(*completed_turns);
+                for _ in 0..recorded.min(self.max_frames - self.frames) {
+                    self.push_frame();
+                }
+            },
+            // The loaded image becomes the first frame once execution starts.
+            Event::StateChange { new_state: State::Executing, .. } if self.frames == 0 =>
+                self.push_frame(),
+            _ => (),
+        }
+        self.frames < self.max_frames
+    }
+
+    /// Stop recording and wait for the remaining frames to be written.
+    pub fn finish(mut self) -> Result<PathBuf> {
+        self.frames_tx.take();
+        self.encoder.take().context("The recording has already been finished")?
+            .join().map_err(|_| anyhow!("The GIF encoder thread panicked"))??;
+        Ok(self.path.clone())
+    }
+
+    fn flip(&mut self, cell: &CellCoord) {
+        assert!(
+            cell.x < self.width && cell.y < self.height,
+            "Cell flipped at ({}, {}) is outside the bounds of the recording.",
+            cell.x, cell.y
+        );
+        self.board[cell.y * self.width + cell.x] ^= 1;
+    }
+
+    fn push_frame(&mut self) {
+        if let Some(frames_tx) = &self.frames_tx {
+            if self.frames < self.max_frames {
+                // The encoder only goes away if it failed, which `finish` will report.
+                let _ = frames_tx.send(self.board.clone());
+                self.frames += 1;
+            }
+        }
+    }
+}
+
+fn scale_board(board: &[u8], width: usize, scale: usize, buffer: &mut [u8]) {
+    let scaled_width = width * scale;
+    for (y, row) in board.chunks(width).enumerate() {
+        let scaled_row = &mut buffer[y * scale * scaled_width..(y * scale + 1) * scaled_width];
+        for (x, &cell) in row.iter().enumerate() {
+            scaled_row[x * scale..(x + 1) * scale].fill(cell);
+        }
+        for i in 1..scale {
+            buffer.copy_within(
+                y * scale * scaled_width..(y * scale + 1) * scaled_width,
+                (y * scale + i) * scaled_width
+            );
+        }
+    }
+}
@@ -0,0 +1,239 @@
+use crate::args::Args;
+use clap::ValueEnum;
+use std::collections::VecDeque;
+use std::fmt;
+
+pub const ALIVE: u32 = 0xFF_FF_FF_FF;
+pub const DEAD: u32 = 0xFF_00_00_00;
+
+/// The age, in turns, at which a cell reaches the end of the age palette.
+const MAX_AGE: u32 = 1000;
+
+/// `ColourMode` decides what the colour of a cell in the SDL window shows.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ColourMode {
+    /// White alive cells on black.
+    #[default]
+    Plain,
+    /// Alive cells coloured by the turns since they were born, so stable debris stands out from new growth.
+    Age,
+    /// Cells coloured by how often they flipped over the last turns, so oscillators and chaos stand out.
+    /// Dead cells that flipped recently are shown dimmed.
+    Activity,
+}
+
+impl ColourMode {
+    /// The mode that follows this one when cycling through the modes.
+    pub fn next(self) -> Self {
+        match self {
+            ColourMode::Plain => ColourMode::Age,
+            ColourMode::Age => ColourMode::Activity,
+            ColourMode::Activity => ColourMode::Plain,
+        }
+    }
+}
+
+impl fmt::Display for ColourMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// `Gradient` is a palette that a colour mode spreads its values over, from low to high.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Gradient {
+    /// Dark red through orange and yellow to white.
+    Heat,
+    /// Dark blue through cyan to white.
+    Ice,
+    /// Purple through blue and green to yellow.
+    Viridis,
+    /// Dark grey to white.
+    Grey,
+}
+
+impl Gradient {
+    /// The evenly spaced ARGB colours the gradient passes through.
+    fn stops(&self) -> &'static [u32] {
+        match self {
+            Gradient::Heat => &[0xFF_50_00_00, 0xFF_D0_20_00, 0xFF_FF_90_00, 0xFF_FF_E0_40, 0xFF_FF_FF_FF],
+            Gradient::Ice => &[0xFF_00_20_50, 0xFF_00_70_D0, 0xFF_40_D0_FF, 0xFF_FF_FF_FF],
+            Gradient::Viridis => &[0xFF_44_01_54, 0xFF_3B_52_8B, 0xFF_21_91_8C, 0xFF_5E_C9_62, 0xFF_FD_E7_25],
+            Gradient::Grey => &[0xFF_40_40_40, 0xFF_FF_FF_FF],
+        }
+    }
+
+    /// The colour at `t`, from 0 for the start of the gradient to 1 for its end.
+    pub fn at(&self, t: f64) -> u32 {
+        let stops = self.stops();
+        let position = t.clamp(0.0, 1.0) * (stops.len() - 1) as f64;
+        let i = (position.floor() as usize).min(stops.len() - 2);
+        blend(stops[i], stops[i + 1], position - i as f64)
+    }
+}
+
+/// Mix the colours `from` and `to`, taking `t` of `to`.
+fn blend(from: u32, to: u32, t: f64) -> u32 {
+    [16, 8, 0].iter().fold(0xFF_00_00_00, |argb, shift| {
+        let (from, to) = ((from >> shift & 0xFF) as f64, (to >> shift & 0xFF) as f64);
+        argb | ((from + (to - from) * t).round() as u32) << shift
+    })
+}
+
+/// `ColourOptions` describes how the SDL window colours cells.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ColourOptions {
+    pub mode: ColourMode,
+    pub age_palette: Gradient,
+    pub activity_palette: Gradient,
+    /// The number of turns that the activity of a cell is counted over.
+    pub activity_window: u32,
+    /// The colour of the alive cells in plain mode, and of the dead cells in every mode.
+    pub alive: u32,
+    pub dead: u32,
+}
+
+impl Default for ColourOptions {
+    fn default() -> Self {
+        ColourOptions::from(&Args::default())
+    }
+}
+
+impl From<&Args> for ColourOptions {
+    fn from(args: &Args) -> Self {
+        ColourOptions {
+            mode: args.colour_mode,
+            age_palette: args.age_palette,
+            activity_palette: args.activity_palette,
+            activity_window: args.activity_window.max(1),
+            alive: ALIVE,
+            dead: DEAD,
+        }
+    }
+}
+
+/// `CellHistory` follows every cell of the world through its flips: whether it is alive and,
+/// once a colour mode needs them, the turn it was born in and how many times it flipped within the activity window.
+pub struct CellHistory {
+    alive: Vec<bool>,
+    /// The age and activity of the cells, which are only followed once a mode other than plain is selected,
+    /// as they take several times the memory of the cells themselves.
+    history: Option<History>,
+    /// The cells born and the cells that died in the turn in progress.
+    changes: (usize, usize),
+    turn: u32,
+    population: usize,
+}
+
+struct History {
+    born: Vec<u32>,
+    activity: Vec<u32>,
+    /// The cells flipped in each of the last turns of the activity window, oldest first.
+    recent: VecDeque<Vec<usize>>,
+    /// The cells flipped in the turn in progress.
+    flipped: Vec<usize>,
+}
+
+impl CellHistory {
+    pub fn new(cells: usize) -> Self {
+        CellHistory {
+            alive: vec![false; cells],
+            history: None,
+            changes: (0, 0),
+            turn: 0,
+            population: 0,
+        }
+    }
+
+    pub fn alive(&self) -> &[bool] {
+        &self.alive
+    }
+
+    /// The number of alive cells.
+    pub fn population(&self) -> usize {
+        self.population
+    }
+
+    /// Start following the age and activity of the cells if `mode` colours by them and they are not followed yet.
+    /// The cells alive by then are taken to be born in the turn in progress.
+    pub fn follow(&mut self, mode: ColourMode) {
+        if mode == ColourMode::Plain || self.history.is_some() {
+            return
+        }
+        self.history = Some(History {
+            born: vec![self.turn; self.alive.len()],
+            activity: vec![0; self.alive.len()],
+            recent: VecDeque::new(),
+            flipped: Vec::new(),
+        });
+    }
+
+    pub fn set(&mut self, i: usize, alive: bool) {
+        if self.alive[i] != alive {
+            self.flip(i);
+        }
+    }
+
+    /// Flip cell `i`. A frame that covers several turns lists a cell once for every time it flipped,
+    /// one after the other, so each of those flips is taken to be a turn later than the one before.
+    pub fn flip(&mut self, i: usize) {
+        self.alive[i] = !self.alive[i];
+        if self.alive[i] {
+            self.population += 1;
+            self.changes.0 += 1;
+        } else {
+            self.population -= 1;
+            self.changes.1 += 1;
+        }
+        if let Some(history) = &mut self.history {
+            if self.alive[i] {
+                let earlier_flips = history.flipped.iter().rev().take_while(|&&j| j == i).count();
+                history.born[i] = self.turn + earlier_flips as u32;
+            }
+            history.activity[i] += 1;
+            history.flipped.push(i);
+        }
+    }
+
+    /// The cells born and the cells that died in the turn in progress.
+    pub fn changes(&self) -> (usize, usize) {
+        self.changes
+    }
+
+    /// Close the turn in progress, forgetting the flips that fall out of an activity window of `window` turns.
+    /// A frame that skips turns ends the window with them, its flips counted in its last turn.
+    pub fn complete_turn(&mut self, completed_turns: u32, window: u32) {
+        let skipped = completed_turns.saturating_sub(self.turn).saturating_sub(1).min(window);
+        self.turn = completed_turns;
+        self.changes = (0, 0);
+        let Some(history) = &mut self.history else { return };
+        history.recent.extend((0..skipped).map(|_| Vec::new()));
+        history.recent.push_back(std::mem::take(&mut history.flipped));
+        while history.recent.len() > window as usize {
+            for i in history.recent.pop_front().unwrap_or_default() {
+                history.activity[i] -= 1;
+            }
+        }
+    }
+
+    /// The colour of cell `i` in the given colour options.
+    /// Until the age and activity of the cells are followed, every mode colours them as plain does.
+    pub fn colour(&self, i: usize, options: &ColourOptions) -> u32 {
+        match (options.mode, &self.history) {
+            (ColourMode::Age, Some(history)) if self.alive[i] => {
+                let age = self.turn.saturating_sub(history.born[i]).min(MAX_AGE);
+                options.age_palette.at((1.0 + age as f64).ln() / (1.0 + MAX_AGE as f64).ln())
+            },
+            (ColourMode::Age, Some(_)) => options.dead,
+            (ColourMode::Activity, Some(history)) => {
+                let colour = options.activity_palette.at(history.activity[i] as f64 / options.activity_window as f64);
+                match (self.alive[i], history.activity[i]) {
+                    (true, _) => colour,
+                    (false, 0) => options.dead,
+                    (false, _) => blend(options.dead, colour, 1.0 / 3.0),
+                }
+            },
+            _ => if self.alive[i] { options.alive } else { options.dead },
+        }
+    }
+}
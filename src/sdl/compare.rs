@@ -0,0 +1,113 @@
+use crate::sdl::colour::{CellHistory, ColourOptions};
+use crate::sdl::viewport::Viewport;
+use clap::ValueEnum;
+use std::fmt;
+
+/// The colour of the cells that are alive in one world and dead in the other.
+pub const DIFFERENT: u32 = 0xFF_FF_40_FF;
+
+/// The colour of the line between two worlds shown side by side.
+const SEPARATOR: u32 = 0xFF_60_60_60;
+
+/// The width in pixels of the line between two worlds shown side by side.
+const SEPARATOR_WIDTH: u32 = 2;
+
+/// `CompareLayout` is how the SDL window shows two worlds run in lockstep.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum CompareLayout {
+    /// The first world on the left and the second on the right, both panned and zoomed together.
+    #[default]
+    SideBySide,
+    /// Both worlds in the same place, where the cells they agree on look like those of a single world.
+    Overlay,
+}
+
+impl fmt::Display for CompareLayout {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// `Comparison` follows a second world run in lockstep with the first one of the SDL window.
+/// Either world shows the cells that differ between them in the `DIFFERENT` colour.
+pub struct Comparison {
+    pub layout: CompareLayout,
+    /// The cells of the second world row by row, with the history they are coloured by.
+    cells: CellHistory,
+    /// Where each world is rendered before it is copied into its half of the frame, when side by side.
+    scratch: Vec<u8>,
+}
+
+impl Comparison {
+    pub fn new(layout: CompareLayout, size: usize) -> Self {
+        Comparison { layout, cells: CellHistory::new(size), scratch: Vec::new() }
+    }
+
+    pub fn cells(&self) -> &CellHistory {
+        &self.cells
+    }
+
+    pub fn cells_mut(&mut self) -> &mut CellHistory {
+        &mut self.cells
+    }
+
+    /// The number of cells alive in one world and dead in the other.
+    pub fn differing(&self, cells: &CellHistory) -> usize {
+        cells.alive().iter().zip(self.cells.alive()).filter(|(a, b)| a != b).count()
+    }
+
+    /// The width of each half of a frame `view_width` pixels wide, when the worlds are side by side.
+    pub fn half_width(view_width: u32) -> u32 {
+        (view_width.saturating_sub(SEPARATOR_WIDTH) / 2).max(1)
+    }
+
+    /// Draw both worlds as seen through `viewport` into `pixels`, an ARGB8888 buffer `view_width` pixels wide
+    /// and as high as the viewport. The first world is given by `cells`, and `decorate` draws over each world.
+    pub fn render(
+        &mut self,
+        viewport: &Viewport,
+        cells: &CellHistory,
+        colours: &ColourOptions,
+        pixels: &mut [u8],
+        view_width: u32,
+        decorate: impl Fn(&mut [u8]),
+    ) {
+        let Comparison { layout, cells: compared, scratch } = self;
+        let differ = |i: usize| cells.alive()[i] != compared.alive()[i];
+        match layout {
+            CompareLayout::Overlay => {
+                viewport.render_with(|i| if differ(i) { DIFFERENT } else { cells.colour(i, colours) }, pixels);
+                decorate(pixels);
+            },
+            CompareLayout::SideBySide => {
+                let (width, height) = viewport.view_size();
+                scratch.resize((width * height * 4) as usize, 0);
+                let right = view_width.saturating_sub(width);
+                for (world, x) in [(cells, 0), (&*compared, right)] {
+                    viewport.render_with(|i| if differ(i) { DIFFERENT } else { world.colour(i, colours) }, scratch);
+                    decorate(scratch);
+                    blit(scratch, width, pixels, view_width, x);
+                }
+                separate(pixels, view_width, width.min(right), right);
+            },
+        }
+    }
+}
+
+/// Copy `from`, an ARGB8888 buffer `width` pixels wide, into `pixels`, one `view_width` pixels wide, from the column `x`.
+fn blit(from: &[u8], width: u32, pixels: &mut [u8], view_width: u32, x: u32) {
+    let (width, view_width, x) = (width as usize, view_width as usize, x as usize);
+    for (row, from) in from.chunks_exact(4 * width).enumerate() {
+        let at = 4 * (row * view_width + x);
+        pixels[at..at + 4 * width].copy_from_slice(from);
+    }
+}
+
+/// Fill the columns from `left` up to `right` of `pixels`, an ARGB8888 buffer `view_width` pixels wide.
+fn separate(pixels: &mut [u8], view_width: u32, left: u32, right: u32) {
+    let (view_width, left, right) = (view_width as usize, left as usize, right as usize);
+    for row in pixels.chunks_exact_mut(4 * view_width) {
+        row[4 * left..4 * right].chunks_exact_mut(4)
+            .for_each(|pixel| pixel.copy_from_slice(&SEPARATOR.to_ne_bytes()));
+    }
+}
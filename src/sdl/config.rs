@@ -0,0 +1,372 @@
+use crate::sdl::colour::{ALIVE, DEAD};
+use crate::sdl::hud::TEXT;
+use crate::sdl::tools::{Pattern, Tool, Transform};
+use anyhow::{bail, Context, Result};
+use sdl2::keyboard::{Keycode, Mod};
+use serde::{Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+use std::str::FromStr;
+
+/// The colour of the outline around the selected cells.
+const SELECTION: u32 = 0xFF_FF_D0_00;
+
+/// The keys known by name rather than by the character they type.
+const NAMED_KEYS: [(&str, Keycode); 27] = [
+    ("Escape", Keycode::Escape),
+    ("Enter", Keycode::Return),
+    ("Space", Keycode::Space),
+    ("Tab", Keycode::Tab),
+    ("Backspace", Keycode::Backspace),
+    ("Delete", Keycode::Delete),
+    ("Insert", Keycode::Insert),
+    ("Home", Keycode::Home),
+    ("End", Keycode::End),
+    ("PageUp", Keycode::PageUp),
+    ("PageDown", Keycode::PageDown),
+    ("Up", Keycode::Up),
+    ("Down", Keycode::Down),
+    ("Left", Keycode::Left),
+    ("Right", Keycode::Right),
+    ("F1", Keycode::F1),
+    ("F2", Keycode::F2),
+    ("F3", Keycode::F3),
+    ("F4", Keycode::F4),
+    ("F5", Keycode::F5),
+    ("F6", Keycode::F6),
+    ("F7", Keycode::F7),
+    ("F8", Keycode::F8),
+    ("F9", Keycode::F9),
+    ("F10", Keycode::F10),
+    ("F11", Keycode::F11),
+    ("F12", Keycode::F12),
+];
+
+/// `Config` is the JSON file given with --config, which changes the keys of the SDL window and the terminal,
+/// and the colours the window draws in.
+/// Both sections are optional, and an action the `keys` section leaves out keeps its default keys, e.g.
+///
+/// ```json
+/// { "keys": { "pause": ["Space"], "help": ["F1"] }, "theme": { "alive": "#00FF80", "grid": "#202020" } }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub keys: KeyBindings,
+    pub theme: Theme,
+}
+
+impl Config {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("Cannot read the config file {}", path.display()))?;
+        let config = serde_json::from_str::<Config>(&json)
+            .with_context(|| format!("Cannot parse the config file {}", path.display()))?;
+        config.keys.check().with_context(|| format!("Invalid key bindings in {}", path.display()))?;
+        Ok(config)
+    }
+}
+
+/// `Action` is something done in the SDL window at the press of a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Action {
+    Quit,
+    Pause,
+    Output,
+    Kill,
+    Record,
+    ColourMode,
+    Hud,
+    Fit,
+    Graph,
+    GraphNarrower,
+    GraphWider,
+    ExportGraph,
+    Help,
+    Copy,
+    Cut,
+    Paste,
+    Clear,
+    FillRandom,
+    Rotate,
+    FlipHorizontal,
+    FlipVertical,
+    Deselect,
+    StampGlider,
+    StampLwss,
+    StampGliderGun,
+}
+
+impl Action {
+    pub const ALL: [Action; 25] = [
+        Action::Quit, Action::Pause, Action::Output, Action::Kill, Action::Record, Action::ColourMode,
+        Action::Hud, Action::Fit, Action::Graph, Action::GraphNarrower, Action::GraphWider, Action::ExportGraph,
+        Action::Help, Action::Copy, Action::Cut, Action::Paste, Action::Clear, Action::FillRandom,
+        Action::Rotate, Action::FlipHorizontal, Action::FlipVertical, Action::Deselect,
+        Action::StampGlider, Action::StampLwss, Action::StampGliderGun,
+    ];
+
+    /// The keys the action is bound to when the config file does not say otherwise.
+    fn default_keys(&self) -> &'static [&'static str] {
+        match self {
+            Action::Quit => &["Escape", "Q"],
+            Action::Pause => &["P"],
+            Action::Output => &["S"],
+            Action::Kill => &["K"],
+            Action::Record => &["R"],
+            Action::ColourMode => &["C"],
+            Action::Hud => &["H"],
+            Action::Fit => &["F"],
+            Action::Graph => &["G"],
+            Action::GraphNarrower => &["["],
+            Action::GraphWider => &["]"],
+            Action::ExportGraph => &["E"],
+            // `?` is Shift and / on most keyboards, which SDL reports as the latter
+            Action::Help => &["?", "Shift+/"],
+            Action::Copy => &["Ctrl+C"],
+            Action::Cut => &["Ctrl+X"],
+            Action::Paste => &["Ctrl+V"],
+            Action::Clear => &["Delete", "Backspace"],
+            Action::FillRandom => &["Ctrl+N"],
+            Action::Rotate => &["Ctrl+T"],
+            Action::FlipHorizontal => &["Ctrl+M"],
+            Action::FlipVertical => &["Ctrl+U"],
+            Action::Deselect => &["Ctrl+D"],
+            Action::StampGlider => &["1"],
+            Action::StampLwss => &["2"],
+            Action::StampGliderGun => &["3"],
+        }
+    }
+
+    /// What the action does, as the help overlay lists it.
+    pub fn description(&self) -> &'static str {
+        match self {
+            Action::Quit => "Quit",
+            Action::Pause => "Pause or resume",
+            Action::Output => "Save a PGM image",
+            Action::Kill => "Quit and stop the server",
+            Action::Record => "Start or stop a GIF",
+            Action::ColourMode => "Next colour mode",
+            Action::Hud => "Show or hide the HUD",
+            Action::Fit => "Show the whole world",
+            Action::Graph => "Show or hide the graph",
+            Action::GraphNarrower => "Graph fewer turns",
+            Action::GraphWider => "Graph more turns",
+            Action::ExportGraph => "Export the graph",
+            Action::Help => "Show or hide this help",
+            Action::Copy => "Copy the selection",
+            Action::Cut => "Cut the selection",
+            Action::Paste => "Paste at the cursor",
+            Action::Clear => "Clear the selection",
+            Action::FillRandom => "Fill the selection",
+            Action::Rotate => "Rotate the selection",
+            Action::FlipHorizontal => "Mirror left to right",
+            Action::FlipVertical => "Mirror top to bottom",
+            Action::Deselect => "Deselect",
+            Action::StampGlider => "Stamp a glider",
+            Action::StampLwss => "Stamp a spaceship",
+            Action::StampGliderGun => "Stamp a glider gun",
+        }
+    }
+
+    /// The selection tool the action uses, if it edits the world.
+    pub fn tool(&self) -> Option<Tool> {
+        Some(match self {
+            Action::Copy => Tool::Copy,
+            Action::Cut => Tool::Cut,
+            Action::Paste => Tool::Paste,
+            Action::Clear => Tool::Clear,
+            Action::FillRandom => Tool::FillRandom,
+            Action::Rotate => Tool::Transform(Transform::Rotate),
+            Action::FlipHorizontal => Tool::Transform(Transform::FlipHorizontal),
+            Action::FlipVertical => Tool::Transform(Transform::FlipVertical),
+            Action::Deselect => Tool::Deselect,
+            Action::StampGlider => Tool::Stamp(Pattern::Glider),
+            Action::StampLwss => Tool::Stamp(Pattern::Lwss),
+            Action::StampGliderGun => Tool::Stamp(Pattern::GosperGliderGun),
+            _ => return None,
+        })
+    }
+}
+
+/// `Key` is a key pressed with or without Ctrl and Shift, written like `Ctrl+C`, `Shift+/`, `?` or `Escape`.
+/// Ctrl stands for Cmd as well, and must be held exactly when the key says so,
+/// while Shift may be held for a key that does not ask for it, so that `?` matches wherever it is typed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Key {
+    pub keycode: Keycode,
+    pub ctrl: bool,
+    pub shift: bool,
+}
+
+impl Key {
+    /// Whether pressing `keycode` with the modifiers `keymod` presses this key.
+    pub fn matches(&self, keycode: Keycode, keymod: Mod) -> bool {
+        let ctrl = keymod.intersects(Mod::LCTRLMOD | Mod::RCTRLMOD | Mod::LGUIMOD | Mod::RGUIMOD);
+        let shift = keymod.intersects(Mod::LSHIFTMOD | Mod::RSHIFTMOD);
+        self.keycode == keycode && self.ctrl == ctrl && (shift || !self.shift)
+    }
+}
+
+impl FromStr for Key {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        // the key is everything after the last `+` but its own last character, which may be a `+` itself
+        let last = s.char_indices().last().map_or(0, |(i, _)| i);
+        let start = s[..last].rfind('+').map_or(0, |i| i + 1);
+        let (modifiers, name) = (&s[..start], &s[start..]);
+        let (mut ctrl, mut shift) = (false, false);
+        for modifier in modifiers.split('+').filter(|modifier| !modifier.is_empty()) {
+            match modifier.to_ascii_lowercase().as_str() {
+                "ctrl" | "control" | "cmd" => ctrl = true,
+                "shift" => shift = true,
+                _ => bail!("Unknown modifier {} in the key {}", modifier, s),
+            }
+        }
+        let named = NAMED_KEYS.iter().find(|(known, _)| known.eq_ignore_ascii_case(name));
+        let keycode = match (named, name.chars().collect::<Vec<_>>().as_slice()) {
+            (Some(&(_, keycode)), _) => keycode,
+            // SDL gives the keys that type a character the code of that character, in lower case
+            (None, &[c]) if c.is_ascii_graphic() => Keycode::from_i32(c.to_ascii_lowercase() as i32)
+                .with_context(|| format!("Unknown key {}", s))?,
+            _ => bail!("Unknown key {}", s),
+        };
+        Ok(Key { keycode, ctrl, shift })
+    }
+}
+
+impl TryFrom<String> for Key {
+    type Error = anyhow::Error;
+
+    fn try_from(s: String) -> Result<Self> {
+        s.parse()
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.ctrl {
+            write!(f, "Ctrl+")?;
+        }
+        if self.shift {
+            write!(f, "Shift+")?;
+        }
+        if let Some((name, _)) = NAMED_KEYS.iter().find(|(_, keycode)| *keycode == self.keycode) {
+            return write!(f, "{}", name)
+        }
+        match u8::try_from(self.keycode.into_i32()).map(char::from) {
+            Ok(c) if c.is_ascii_graphic() => write!(f, "{}", c.to_ascii_uppercase()),
+            _ => write!(f, "Key {}", self.keycode.into_i32()),
+        }
+    }
+}
+
+/// `KeyBindings` are the keys that each action of the SDL window is bound to.
+/// Read from a config file, they replace the default keys of the actions they list.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(from = "BTreeMap<Action, Vec<Key>>")]
+pub struct KeyBindings(BTreeMap<Action, Vec<Key>>);
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        KeyBindings(Action::ALL.into_iter()
+            .map(|action| {
+                let keys = action.default_keys().iter()
+                    .map(|key| key.parse().expect("The default keys are valid"))
+                    .collect();
+                (action, keys)
+            })
+            .collect())
+    }
+}
+
+impl From<BTreeMap<Action, Vec<Key>>> for KeyBindings {
+    fn from(keys: BTreeMap<Action, Vec<Key>>) -> Self {
+        let mut bindings = KeyBindings::default();
+        bindings.0.extend(keys);
+        bindings
+    }
+}
+
+impl KeyBindings {
+    /// The keys bound to `action`, which may be none.
+    pub fn keys(&self, action: Action) -> &[Key] {
+        self.0.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Every action with the keys bound to it, in the order the help overlay lists them.
+    pub fn iter(&self) -> impl Iterator<Item = (Action, &[Key])> {
+        self.0.iter().map(|(&action, keys)| (action, keys.as_slice()))
+    }
+
+    /// The action bound to `keycode` pressed with the modifiers `keymod`, if any.
+    /// A key that asks for Shift wins over the same key without it.
+    pub fn action(&self, keycode: Keycode, keymod: Mod) -> Option<Action> {
+        self.iter()
+            .flat_map(|(action, keys)| keys.iter().map(move |key| (action, key)))
+            .filter(|(_, key)| key.matches(keycode, keymod))
+            .max_by_key(|(_, key)| key.shift)
+            .map(|(action, _)| action)
+    }
+
+    /// Check that no key is bound to two actions.
+    pub fn check(&self) -> Result<()> {
+        let mut bound = BTreeMap::new();
+        for (action, keys) in self.iter() {
+            for key in keys {
+                if let Some(other) = bound.insert(key.to_string(), action) {
+                    bail!("{} is bound to both {:?} and {:?}", key, other, action);
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `Theme` is the colours the SDL window draws in, each written as `#RRGGBB`.
+/// The cells are only outlined in the grid colour once it is given and they are large enough.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Theme {
+    /// The alive cells, when they are not coloured by age or activity.
+    #[serde(deserialize_with = "colour")]
+    pub alive: u32,
+    #[serde(deserialize_with = "colour")]
+    pub dead: u32,
+    #[serde(deserialize_with = "optional_colour")]
+    pub grid: Option<u32>,
+    /// The outline around the selected cells.
+    #[serde(deserialize_with = "colour")]
+    pub selection: u32,
+    /// The text of the HUD and the help overlay.
+    #[serde(deserialize_with = "colour")]
+    pub overlay: u32,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Theme { alive: ALIVE, dead: DEAD, grid: None, selection: SELECTION, overlay: TEXT }
+    }
+}
+
+/// Parse a colour written as `#RRGGBB` into an opaque ARGB colour.
+pub fn parse_colour(s: &str) -> Result<u32> {
+    match s.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) =>
+            Ok(0xFF_00_00_00 | u32::from_str_radix(hex, 16)?),
+        _ => bail!("Expected a colour written as #RRGGBB, not {}", s),
+    }
+}
+
+fn colour<'de, D: Deserializer<'de>>(deserializer: D) -> Result<u32, D::Error> {
+    parse_colour(&String::deserialize(deserializer)?).map_err(serde::de::Error::custom)
+}
+
+fn optional_colour<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u32>, D::Error> {
+    colour(deserializer).map(Some)
+}
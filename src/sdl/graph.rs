@@ -0,0 +1,171 @@
+use crate::gol::stats::TurnStats;
+use crate::sdl::font::{draw_text, text_size, GLYPH_HEIGHT};
+use std::collections::VecDeque;
+use std::fmt::Write;
+
+const BACKGROUND: u32 = 0xFF_10_10_10;
+const GRID: u32 = 0xFF_40_40_40;
+const TEXT: u32 = 0xFF_C0_C0_C0;
+const POPULATION: u32 = 0xFF_FF_FF_FF;
+const BIRTHS: u32 = 0xFF_40_D0_40;
+const DEATHS: u32 = 0xFF_E0_40_40;
+
+/// The most turns the graph remembers, and so can scroll back over or export.
+const MAX_HISTORY: usize = 100_000;
+
+/// The fewest turns the time window can be narrowed to.
+const MIN_WINDOW: u32 = 16;
+
+/// The pixels between the edges of the panel and the plot.
+const MARGIN: usize = 4;
+
+/// The pixels between the labels of the legend.
+const LEGEND_SPACING: usize = 12;
+
+/// `Graph` plots the population, births and deaths of the last turns in a panel of the SDL window.
+/// The time window scrolls along with the latest turn, and the y-axis scales to the largest value within it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Graph {
+    pub visible: bool,
+    /// The number of turns shown.
+    window: u32,
+    history: VecDeque<TurnStats>,
+}
+
+impl Graph {
+    pub fn new(visible: bool, window: u32) -> Self {
+        Graph { visible, window: window.max(MIN_WINDOW), history: VecDeque::new() }
+    }
+
+    /// The number of turns shown.
+    pub fn window(&self) -> u32 {
+        self.window
+    }
+
+    /// Widen the time window by `steps` doubling, or narrow it if negative.
+    pub fn zoom(&mut self, steps: i32) {
+        for _ in 0..steps.unsigned_abs() {
+            self.window = match steps > 0 {
+                true => self.window.saturating_mul(2).min(MAX_HISTORY as u32),
+                false => (self.window / 2).max(MIN_WINDOW),
+            };
+        }
+    }
+
+    /// Add the statistics of a turn. A turn that is not after the last one starts the series over,
+    /// as the simulation behind it was replaced.
+    pub fn push(&mut self, stats: TurnStats) {
+        if self.history.back().is_some_and(|last| last.completed_turns >= stats.completed_turns) {
+            self.history.clear();
+        }
+        if self.history.len() == MAX_HISTORY {
+            self.history.pop_front();
+        }
+        self.history.push_back(stats);
+    }
+
+    pub fn history(&self) -> &VecDeque<TurnStats> {
+        &self.history
+    }
+
+    /// The first and last turns of the time window, which ends at the latest turn.
+    pub fn turns(&self) -> (u32, u32) {
+        let last = self.history.back().map_or(0, |stats| stats.completed_turns);
+        (last.saturating_sub(self.window), last)
+    }
+
+    /// The statistics of the turns within the time window.
+    pub fn shown(&self) -> impl Iterator<Item = &TurnStats> {
+        let (first, _) = self.turns();
+        self.history.iter().skip_while(move |stats| stats.completed_turns < first)
+    }
+
+    /// The top of the y-axis: the largest value shown rounded up to 1, 2 or 5 times a power of ten.
+    pub fn scale(&self) -> usize {
+        let max = self.shown()
+            .map(|stats| stats.population.max(stats.births).max(stats.deaths))
+            .max()
+            .unwrap_or(0)
+            .max(1);
+        let mut power = 1;
+        loop {
+            for step in [1, 2, 5] {
+                if step * power >= max {
+                    return step * power
+                }
+            }
+            power *= 10;
+        }
+    }
+
+    /// The remembered statistics as CSV, a turn per row.
+    pub fn to_csv(&self) -> String {
+        self.history.iter().fold(
+            String::from("completed_turns,population,births,deaths\n"),
+            |mut csv, stats| {
+                let _ = writeln!(csv, "{},{},{},{}", stats.completed_turns, stats.population, stats.births, stats.deaths);
+                csv
+            },
+        )
+    }
+
+    /// Draw the panel, if it is visible, into the `height` rows of `pixels` from `top`,
+    /// where `pixels` is an ARGB8888 buffer `width` pixels wide.
+    pub fn draw(&self, pixels: &mut [u8], width: usize, top: usize, height: usize) {
+        if !self.visible || width == 0 || height == 0 {
+            return
+        }
+        let rows = &mut pixels[4 * top * width..4 * (top + height) * width];
+        rows.chunks_exact_mut(4).for_each(|pixel| pixel.copy_from_slice(&BACKGROUND.to_ne_bytes()));
+
+        // the plot leaves room for a line of text above and below it
+        let text_height = GLYPH_HEIGHT + MARGIN;
+        let (left, right) = (MARGIN, width.saturating_sub(MARGIN + 1));
+        let (plot_top, plot_bottom) = (MARGIN + text_height, height.saturating_sub(MARGIN + text_height + 1));
+        if right <= left || plot_bottom <= plot_top {
+            return
+        }
+        let mut set = |x: usize, y: usize, argb: u32| {
+            let i = 4 * (y * width + x);
+            rows[i..i + 4].copy_from_slice(&argb.to_ne_bytes());
+        };
+        for x in left..=right {
+            set(x, plot_top, GRID);
+            set(x, (plot_top + plot_bottom) / 2, GRID);
+            set(x, plot_bottom, GRID);
+        }
+
+        let (first, last) = self.turns();
+        let scale = self.scale();
+        let to_x = |turn: u32| left + ((turn - first) as usize * (right - left)) / self.window as usize;
+        let to_y = |value: usize| plot_bottom - (value.min(scale) * (plot_bottom - plot_top)) / scale;
+        for (series, argb) in [
+            (|stats: &TurnStats| stats.deaths) as fn(&TurnStats) -> usize,
+            |stats| stats.births,
+            |stats| stats.population,
+        ].into_iter().zip([DEATHS, BIRTHS, POPULATION]) {
+            let mut previous: Option<(usize, usize)> = None;
+            for stats in self.shown() {
+                let (x, y) = (to_x(stats.completed_turns), to_y(series(stats)));
+                let (px, py) = previous.unwrap_or((x, y));
+                let steps = x.abs_diff(px).max(y.abs_diff(py)).max(1);
+                for step in 0..=steps {
+                    let along = |from: usize, to: usize| from as f64 + (to as f64 - from as f64) * step as f64 / steps as f64;
+                    set(along(px, x).round() as usize, along(py, y).round() as usize, argb);
+                }
+                previous = Some((x, y));
+            }
+        }
+
+        let max = vec![format!("Max {}", scale)];
+        draw_text(rows, width, left, MARGIN, 1, &max, TEXT);
+        let mut legend_x = left + text_size(&max, 1).0 + LEGEND_SPACING;
+        for (label, argb) in [("Population", POPULATION), ("Births", BIRTHS), ("Deaths", DEATHS)] {
+            let label = vec![label.to_owned()];
+            draw_text(rows, width, legend_x, MARGIN, 1, &label, argb);
+            legend_x += text_size(&label, 1).0 + LEGEND_SPACING;
+        }
+        let turns = vec![format!("Turns {} - {}", first, last)];
+        draw_text(rows, width, left, plot_bottom + MARGIN, 1, &turns, TEXT);
+    }
+}
@@ -0,0 +1,42 @@
+use crate::sdl::config::KeyBindings;
+use crate::sdl::hud::{draw_panel, panel_size};
+
+/// `Help` is the overlay in the middle of the SDL window that lists what every key does.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Help {
+    pub visible: bool,
+    lines: Vec<String>,
+}
+
+impl Help {
+    /// A hidden overlay listing the actions bound to keys in `bindings`, each next to its keys.
+    pub fn new(bindings: &KeyBindings) -> Self {
+        let bound = bindings.iter().filter(|(_, keys)| !keys.is_empty()).collect::<Vec<_>>();
+        let width = bound.iter().map(|(action, _)| action.description().len()).max().unwrap_or(0);
+        let lines = std::iter::once("Keys".to_owned())
+            .chain(bound.into_iter().map(|(action, keys)| {
+                let keys = keys.iter().map(|key| key.to_string()).collect::<Vec<_>>().join(", ");
+                format!("{:<width$}  {}", action.description(), keys)
+            }))
+            .collect();
+        Help { visible: false, lines }
+    }
+
+    pub fn lines(&self) -> &[String] {
+        &self.lines
+    }
+
+    /// Draw the overlay, if it is visible, in the middle of `pixels`, an ARGB8888 buffer `width` pixels wide.
+    /// The text is drawn at twice its size where that still fits.
+    pub fn draw(&self, pixels: &mut [u8], width: usize, argb: u32) {
+        if !self.visible {
+            return
+        }
+        let height = pixels.len() / 4 / width.max(1);
+        let fits = |(panel_width, panel_height): (usize, usize)| panel_width <= width && panel_height <= height;
+        let scale = if fits(panel_size(&self.lines, 2)) { 2 } else { 1 };
+        let (panel_width, panel_height) = panel_size(&self.lines, scale);
+        let at = (width.saturating_sub(panel_width) / 2, height.saturating_sub(panel_height) / 2);
+        draw_panel(pixels, width, at, scale, &self.lines, argb);
+    }
+}
@@ -0,0 +1,101 @@
+use crate::gol::event::State;
+use crate::gol::rule::Rule;
+use crate::sdl::font::{draw_text, text_size};
+
+/// The colour of the text, unless the theme says otherwise.
+pub const TEXT: u32 = 0xFF_FF_FF_FF;
+
+/// The pixels between the edge of the panel and its text, before scaling.
+const PADDING: usize = 4;
+
+/// The width of the view from which the text is drawn at twice its size.
+const LARGE_TEXT_WIDTH: usize = 400;
+
+/// `Hud` is the overlay in the top left corner of the SDL window that shows how the simulation is doing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hud {
+    pub visible: bool,
+    pub completed_turns: u32,
+    pub population: usize,
+    pub turns_per_second: u32,
+    pub state: State,
+    pub rule: Rule,
+    /// The cells that differ between two worlds, when comparing them.
+    pub differing: Option<usize>,
+    /// A note on the last thing done in the window, e.g. a pattern file that could not be loaded.
+    pub message: Option<String>,
+    /// The colour of the text.
+    pub colour: u32,
+}
+
+impl Default for Hud {
+    fn default() -> Self {
+        Hud {
+            visible: true,
+            completed_turns: 0,
+            population: 0,
+            turns_per_second: 0,
+            state: State::Executing,
+            rule: Rule::default(),
+            differing: None,
+            message: None,
+            colour: TEXT,
+        }
+    }
+}
+
+impl Hud {
+    pub fn lines(&self) -> Vec<String> {
+        let state = match self.state {
+            State::Executing => "Running",
+            State::Pause => "Paused",
+            State::Quitting => "Quitting",
+        };
+        let mut lines = vec![
+            format!("Turn {}", self.completed_turns),
+            format!("Population {}", self.population),
+            format!("Turns/s {}", self.turns_per_second),
+            state.to_owned(),
+            format!("Rule {}", self.rule),
+        ];
+        if let Some(differing) = self.differing {
+            lines.push(format!("Differing {}", differing));
+        }
+        if let Some(message) = &self.message {
+            lines.push(message.clone());
+        }
+        lines
+    }
+
+    /// Draw the overlay, if it is visible, in the top left corner of `pixels`, an ARGB8888 buffer `width` pixels wide.
+    pub fn draw(&self, pixels: &mut [u8], width: usize) {
+        if !self.visible {
+            return
+        }
+        let scale = if width >= LARGE_TEXT_WIDTH { 2 } else { 1 };
+        draw_panel(pixels, width, (0, 0), scale, &self.lines(), self.colour);
+    }
+}
+
+/// The width and height in pixels of a panel around `lines` of text drawn at `scale`.
+pub fn panel_size(lines: &[String], scale: usize) -> (usize, usize) {
+    let (text_width, text_height) = text_size(lines, scale);
+    (text_width + 2 * PADDING * scale, text_height + 2 * PADDING * scale)
+}
+
+/// Draw `lines` of text in `argb` on a panel with its top left at `at` over `pixels`, an ARGB8888 buffer
+/// `width` pixels wide. The panel darkens what is behind it so the text stays readable in any colour mode.
+pub fn draw_panel(pixels: &mut [u8], width: usize, at: (usize, usize), scale: usize, lines: &[String], argb: u32) {
+    let height = pixels.len() / 4 / width.max(1);
+    let (panel_width, panel_height) = panel_size(lines, scale);
+    let (x, y) = (at.0.min(width), at.1.min(height));
+    let (right, bottom) = ((x + panel_width).min(width), (y + panel_height).min(height));
+    for row in y..bottom {
+        for pixel in pixels[4 * (row * width + x)..4 * (row * width + right)].chunks_exact_mut(4) {
+            let behind = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+            let darkened = 0xFF_00_00_00 | (behind >> 2 & 0x00_3F_3F_3F);
+            pixel.copy_from_slice(&darkened.to_ne_bytes());
+        }
+    }
+    draw_text(pixels, width, x + PADDING * scale, y + PADDING * scale, scale, lines, argb);
+}
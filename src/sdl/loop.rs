@@ -1,24 +1,64 @@
 use crate::args::Args;
-use crate::gol::event::{Event, State};
+use crate::gol::{edit::Edit, event::{Event, State}, format::{Board, Format}, rule::Rule, stats::Statistics};
+use crate::record::gif::{GifRecorder, RecordOptions};
+use crate::record::event_log::EventLogger;
+use crate::sdl::colour::ColourOptions;
+use crate::sdl::config::{Action, Config};
+use crate::sdl::graph::Graph;
+use crate::sdl::help::Help;
+use crate::sdl::tools::{place_file, stamp, Placement, Selection, Tool};
 use crate::sdl::window::Window;
 use crate::util::avgturns::AvgTurns;
-use anyhow::Result;
-use flume::{Receiver, Sender};
-use sdl2::keyboard::Keycode;
-use sdl2::event::Event as SdlEvent;
+use crate::util::cell::CellCoord;
+use anyhow::{Context, Result};
+use flume::{Receiver, RecvError, Sender};
+use sdl2::keyboard::{Keycode, Scancode};
+use sdl2::event::{Event as SdlEvent, WindowEvent};
+use sdl2::mouse::{MouseButton, MouseWheelDirection};
 use tokio::select;
-use std::time::Duration;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
+/// How long a message stays on the HUD.
+const MESSAGE_DURATION: Duration = Duration::from_secs(5);
+
+/// Show the simulation in the SDL window, sending key presses on to the distributor.
+/// The selection tools send their changes to the world through `edits`, when they can be made.
+/// The population graph plots the `statistics` of every turn, or without them only the turns the window sees.
+/// The `compared` events are those of a second world run in lockstep, which is shown as --compare-layout says.
+/// A pattern file dropped onto the window is pasted under the cursor, or replaces the world while Shift is held.
+/// The keys and colours are those of the --config file, or the defaults if there is none or it cannot be read.
 pub async fn run(
     args: Args,
     events: Receiver<Event>,
-    key_presses: Sender<Keycode>
+    key_presses: Sender<Keycode>,
+    edits: Option<Sender<Edit>>,
+    statistics: Option<Arc<Statistics>>,
+    mut compared: Option<Receiver<Event>>,
 ) -> Result<()> {
+    let (config, config_error) = match &args.config {
+        Some(path) => match Config::load(path) {
+            Ok(config) => (config, None),
+            Err(e) => {
+                log::error!(target: "Window", "{:#}", e);
+                (Config::default(), Some(format!("{:#}", e)))
+            },
+        },
+        None => (Config::default(), None),
+    };
     let mut sdl = Window::new(
         "Gol GUI",
-        args.image_width as u32,
-        args.image_height as u32,
-    )?;
+        args.width() as u32,
+        args.height() as u32,
+    )?
+        .with_colours(ColourOptions::from(&args))
+        .with_theme(config.theme)
+        .with_graph(Graph::new(args.graph, args.graph_window));
+    if compared.is_some() {
+        sdl = sdl.with_comparison(args.compare_layout)?;
+    }
+    *sdl.help_mut() = Help::new(&config.keys);
 
     let mut event_pump = sdl.take_event_pump()?;
     let mut dirty = false;
@@ -26,22 +66,143 @@ pub async fn run(
         Duration::from_secs_f64(1_f64 / args.fps as f64)
     );
     let mut avg_turns = AvgTurns::new();
+    let mut completed_turns = 0;
+    // a recording asked for with --record follows the events on its own, so only R starts one here
+    let mut recorder = None;
+    let mut logger = start_logging(&args);
+    // the mouse in window coordinates, the cell a selection is dragged from and the last copied cells
+    let mut cursor = (0, 0);
+    let mut selecting_from = None;
+    let mut copied = None;
+    let mut message_until = None;
+    if let Some(error) = config_error {
+        sdl.hud_mut().message = Some(error);
+        message_until = Some(Instant::now() + MESSAGE_DURATION);
+    }
 
     'sdl: loop {
         select! {
             _ = refresh_interval.tick() => {
-                match event_pump.poll_event() {
-                    Some(SdlEvent::Quit { .. } | SdlEvent::KeyDown { keycode: Some(Keycode::Escape), ..}) =>
-                        key_presses.send_async(Keycode::Q).await?,
-                    Some(SdlEvent::KeyDown { keycode: Some(Keycode::P), .. }) =>
-                        key_presses.send_async(Keycode::P).await?,
-                    Some(SdlEvent::KeyDown { keycode: Some(Keycode::S), .. }) =>
-                        key_presses.send_async(Keycode::S).await?,
-                    Some(SdlEvent::KeyDown { keycode: Some(Keycode::Q), .. }) =>
-                        key_presses.send_async(Keycode::Q).await?,
-                    Some(SdlEvent::KeyDown { keycode: Some(Keycode::K), .. }) =>
-                        key_presses.send_async(Keycode::K).await?,
-                    _ => (),
+                if let Some(statistics) = &statistics {
+                    let turns = statistics.take();
+                    dirty |= !turns.is_empty();
+                    turns.into_iter().for_each(|stats| sdl.graph_mut().push(stats));
+                }
+                if message_until.is_some_and(|until| Instant::now() >= until) {
+                    sdl.hud_mut().message = None;
+                    message_until = None;
+                    dirty = true;
+                }
+                for sdl_event in event_pump.poll_iter().collect::<Vec<SdlEvent>>() {
+                    let action = match sdl_event {
+                        SdlEvent::KeyDown { keycode: Some(keycode), keymod, .. } => config.keys.action(keycode, keymod),
+                        SdlEvent::Quit { .. } => Some(Action::Quit),
+                        _ => None,
+                    };
+                    if let Some(tool) = action.and_then(|action| action.tool()) {
+                        let edit = use_tool(&mut sdl, tool, cursor, &mut copied);
+                        match (edit, &edits) {
+                            (Some(edit), Some(edits)) => edits.send_async(edit).await?,
+                            (Some(_), None) =>
+                                log::warn!(target: "Window", "Only a simulation running in this process can be edited"),
+                            (None, _) => (),
+                        }
+                        dirty = true;
+                        continue;
+                    }
+                    match (action, sdl_event) {
+                        (Some(Action::Quit), _) => key_presses.send_async(Keycode::Q).await?,
+                        (Some(Action::Pause), _) => key_presses.send_async(Keycode::P).await?,
+                        (Some(Action::Output), _) => key_presses.send_async(Keycode::S).await?,
+                        (Some(Action::Kill), _) => key_presses.send_async(Keycode::K).await?,
+                        (Some(Action::Record), _) => match recorder.take() {
+                            Some(recording) => { tokio::spawn(stop_recording(recording)); },
+                            None => recorder = start_recording(&args, completed_turns, &sdl.alive_cells()),
+                        },
+                        (Some(Action::ColourMode), _) => {
+                            log::info!(target: "Window", "Colouring cells by {}", sdl.cycle_colour_mode());
+                            dirty = true;
+                        },
+                        (Some(Action::Hud), _) => {
+                            sdl.hud_mut().visible ^= true;
+                            dirty = true;
+                        },
+                        (Some(Action::Help), _) => {
+                            sdl.help_mut().visible ^= true;
+                            dirty = true;
+                        },
+                        (Some(Action::Fit), _) => {
+                            sdl.fit();
+                            dirty = true;
+                        },
+                        (Some(Action::Graph), _) => {
+                            sdl.toggle_graph();
+                            dirty = true;
+                        },
+                        (Some(Action::GraphNarrower), _) => {
+                            sdl.graph_mut().zoom(-1);
+                            dirty = true;
+                        },
+                        (Some(Action::GraphWider), _) => {
+                            sdl.graph_mut().zoom(1);
+                            dirty = true;
+                        },
+                        (Some(Action::ExportGraph), _) =>
+                            export_graph(&args, completed_turns, sdl.graph_mut()),
+                        // the actions left use the selection tools
+                        (Some(_), _) => (),
+                        (None, SdlEvent::MouseWheel { y, mouse_x, mouse_y, direction, .. }) => {
+                            let steps = match direction {
+                                MouseWheelDirection::Flipped => -y,
+                                _ => y,
+                            };
+                            sdl.zoom_at(mouse_x, mouse_y, steps);
+                            dirty = true;
+                        },
+                        (None, SdlEvent::MouseButtonDown { mouse_btn: MouseButton::Right, x, y, .. }) => {
+                            selecting_from = sdl.cell_at(x, y);
+                            sdl.set_selection(selecting_from.map(|cell| Selection::between(cell, cell)));
+                            dirty = true;
+                        },
+                        (None, SdlEvent::MouseButtonUp { mouse_btn: MouseButton::Right, .. }) => selecting_from = None,
+                        (None, SdlEvent::MouseMotion { mousestate, x, y, xrel, yrel, .. }) => {
+                            cursor = (x, y);
+                            if mousestate.left() {
+                                sdl.pan(xrel, yrel);
+                                dirty = true;
+                            }
+                            if let (true, Some(from), Some(to)) = (mousestate.right(), selecting_from, sdl.cell_at(x, y)) {
+                                sdl.set_selection(Some(Selection::between(from, to)));
+                                dirty = true;
+                            }
+                        },
+                        (None, SdlEvent::DropFile { filename, .. }) => {
+                            let keyboard = event_pump.keyboard_state();
+                            let placement = match keyboard.is_scancode_pressed(Scancode::LShift)
+                                || keyboard.is_scancode_pressed(Scancode::RShift) {
+                                true => Some(Placement::Replace),
+                                false => {
+                                    let mouse = event_pump.mouse_state();
+                                    sdl.cell_at(mouse.x(), mouse.y()).map(Placement::At)
+                                },
+                            };
+                            let message = match drop_file(&sdl, Path::new(&filename), placement, edits.as_ref()).await {
+                                Ok(message) => message,
+                                Err(e) => {
+                                    log::error!(target: "Window", "{:#}", e);
+                                    format!("{:#}", e)
+                                },
+                            };
+                            sdl.hud_mut().message = Some(message);
+                            message_until = Some(Instant::now() + MESSAGE_DURATION);
+                            dirty = true;
+                        },
+                        (None, SdlEvent::Window { win_event: WindowEvent::SizeChanged(..), .. }) => {
+                            sdl.resize()?;
+                            dirty = true;
+                        },
+                        (None, _) => (),
+                    }
                 }
                 if dirty {
                     sdl.render_frame()?;
@@ -49,42 +210,191 @@ pub async fn run(
                 }
             },
             gol_event = events.recv_async() => {
+                if let Ok(event) = &gol_event {
+                    record(&mut recorder, event);
+                    if let Some(logger) = logger.as_mut() {
+                        logger.on_event(event);
+                    }
+                }
                 match gol_event {
                     Ok(Event::CellFlipped { cell, .. }) =>
                         sdl.flip_pixel(cell.x as u32, cell.y as u32),
-                    Ok(Event::CellsFlipped { cells, ..}) =>
-                        cells.iter().for_each(|cell| sdl.flip_pixel(cell.x as u32, cell.y as u32)),
-                    Ok(Event::TurnComplete { .. }) =>
-                        dirty = true,
-                    Ok(Event::AliveCellsCount { completed_turns, .. }) =>
-                        log::info!(
-                            target: "Event", "{} Avg{:>5} turns/s",
-                            gol_event?,
-                            avg_turns.get(completed_turns)
-                        ),
+                    Ok(Event::CellsFlipped { cells, ..}) => {
+                        cells.iter().for_each(|cell| sdl.flip_pixel(cell.x as u32, cell.y as u32));
+                        // edits flip cells without completing a turn, even while paused
+                        dirty = true;
+                    },
+                    Ok(Event::TurnComplete { completed_turns: turns }) => {
+                        completed_turns = turns;
+                        let seen = sdl.complete_turn(turns);
+                        if statistics.is_none() {
+                            sdl.graph_mut().push(seen);
+                        }
+                        dirty = true;
+                    },
+                    Ok(Event::AliveCellsCount { completed_turns, .. }) => {
+                        let turns_per_second = avg_turns.get(completed_turns);
+                        sdl.hud_mut().turns_per_second = turns_per_second;
+                        log::info!(target: "Event", "{} Avg{:>5} turns/s", gol_event?, turns_per_second);
+                    },
                     Ok(Event::ImageOutputComplete { .. }) =>
                         log::info!(target: "Event", "{}", gol_event?),
                     Ok(Event::FinalTurnComplete { .. }) =>
                         log::info!(target: "Event", "{}", gol_event?),
                     Ok(Event::StateChange { new_state, .. }) => {
                         log::info!(target: "Event", "{}", gol_event?);
+                        sdl.hud_mut().state = new_state;
+                        dirty = true;
                         if let State::Quitting = new_state {
                             break 'sdl
                         }
                     },
                     Err(_) => break 'sdl,
                 };
+            },
+            compared_event = next_event(compared.as_ref()) => {
+                match compared_event {
+                    Ok(Event::CellFlipped { cell, .. }) =>
+                        sdl.flip_compared_pixel(cell.x as u32, cell.y as u32),
+                    Ok(Event::CellsFlipped { cells, .. }) => {
+                        cells.iter().for_each(|cell| sdl.flip_compared_pixel(cell.x as u32, cell.y as u32));
+                        dirty = true;
+                    },
+                    Ok(Event::TurnComplete { completed_turns }) => {
+                        sdl.complete_compared_turn(completed_turns);
+                        dirty = true;
+                    },
+                    Ok(Event::StateChange { new_state: State::Quitting, .. }) | Err(_) => compared = None,
+                    Ok(_) => (),
+                }
             }
         }
     }
 
+    // the second world quits on the same turn as the first, but its last events may still be on their way
+    if let Some(compared) = compared {
+        while let Ok(event) = compared.recv_async().await {
+            if let Event::StateChange { new_state: State::Quitting, .. } = event {
+                break
+            }
+        }
+    }
+    if let Some(recording) = recorder {
+        stop_recording(recording).await;
+    }
+    if let Some(logger) = logger {
+        stop_logging(logger).await;
+    }
     Ok(())
 }
 
-pub async fn run_headless(events: Receiver<Event>) -> Result<()> {
+/// The next event of a world, or never when there is no world to follow.
+async fn next_event(events: Option<&Receiver<Event>>) -> Result<Event, RecvError> {
+    match events {
+        Some(events) => events.recv_async().await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Load the pattern file dropped onto the window at `path` and send it to the world through `edits`,
+/// placed as `placement` says, or under no cell if it was dropped outside the world.
+/// Returns a note on what was done, or why the pattern could not be placed.
+async fn drop_file(
+    sdl: &Window,
+    path: &Path,
+    placement: Option<Placement>,
+    edits: Option<&Sender<Edit>>,
+) -> Result<String> {
+    let edits = edits.context("Only a simulation running in this process can be edited")?;
+    let placement = placement.context("Drop the pattern onto the world, or hold Shift to replace the world with it")?;
+    let (width, height) = (sdl.world_width(), sdl.world_height());
+    // a large file takes a while to read and decode, which the window should not wait on
+    let file = path.to_owned();
+    let edit = tokio::task::spawn_blocking(move || place_file(&file, placement, width, height)).await??;
+    edits.send_async(edit).await.context("The simulation has stopped")?;
+    let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+    let message = match placement {
+        Placement::Replace => format!("Replaced the world with {}", name),
+        Placement::At(at) => format!("Pasted {} at ({}, {})", name, at.x, at.y),
+    };
+    log::info!(target: "Window", "{}", message);
+    Ok(message)
+}
+
+/// Use `tool` on the selection or at the cell under `cursor`, returning the edit it makes to the world, if any.
+/// Copied cells go to the clipboard as an RLE pattern, and are kept in `copied` in case the clipboard cannot be used.
+fn use_tool(
+    sdl: &mut Window,
+    tool: Tool,
+    cursor: (i32, i32),
+    copied: &mut Option<Board>,
+) -> Option<Edit> {
+    let selection = sdl.selection();
+    match tool {
+        Tool::Copy | Tool::Cut => {
+            let selection = selection?;
+            let board = selection.copy(sdl.alive(), sdl.world_width());
+            let rle = String::from_utf8_lossy(&board.encode(Format::Rle, &Rule::default())).into_owned();
+            if let Err(e) = sdl.set_clipboard_text(&rle) {
+                log::warn!(target: "Window", "Cannot copy to the clipboard: {:#}", e);
+            }
+            *copied = Some(board);
+            (tool == Tool::Cut).then(|| selection.clear())
+        },
+        Tool::Paste => {
+            let at = sdl.cell_at(cursor.0, cursor.1)?;
+            let pasted = match sdl.clipboard_text() {
+                Some(text) => match Board::decode_as(text.as_bytes(), Format::Rle) {
+                    Ok(board) => Some(board),
+                    Err(e) => {
+                        log::warn!(target: "Window", "The clipboard does not hold an RLE pattern: {:#}", e);
+                        None
+                    },
+                },
+                None => copied.clone(),
+            };
+            pasted.map(|board| stamp(&board, at))
+        },
+        Tool::Clear => selection.map(|selection| selection.clear()),
+        Tool::FillRandom => selection.map(|selection| selection.fill_random()),
+        Tool::Transform(transform) => {
+            let (edit, moved) = selection?.transform(sdl.alive(), sdl.world_width(), transform);
+            sdl.set_selection(Some(moved));
+            Some(edit)
+        },
+        Tool::Deselect => {
+            sdl.set_selection(None);
+            None
+        },
+        Tool::Stamp(pattern) => {
+            let at = sdl.cell_at(cursor.0, cursor.1)?;
+            match pattern.board() {
+                Ok(board) => Some(stamp(&board, at)),
+                Err(e) => {
+                    log::error!(target: "Window", "Cannot stamp a {:?}: {:#}", pattern, e);
+                    None
+                },
+            }
+        },
+    }
+}
+
+pub async fn run_headless(args: Args, events: Receiver<Event>) -> Result<()> {
     let mut avg_turns = AvgTurns::new();
+    let mut recorder = if args.record { start_recording(&args, 0, &[]) } else { None };
+    let mut logger = start_logging(&args);
     loop {
         let gol_event = events.recv_async().await;
+        if let Ok(event) = &gol_event {
+            // the recording is saved before the run ends, as nothing waits for it afterwards
+            if recorder.as_mut().is_some_and(|recording| !recording.on_event(event)) {
+                log::info!(target: "Record", "Maximum recording duration reached");
+                stop_recording(recorder.take().unwrap()).await;
+            }
+            if let Some(logger) = logger.as_mut() {
+                logger.on_event(event);
+            }
+        }
         match gol_event {
             Ok(Event::AliveCellsCount { completed_turns, .. }) =>
                 log::info!(
@@ -106,5 +416,105 @@ pub async fn run_headless(events: Receiver<Event>) -> Result<()> {
             _ => (),
         };
     }
+
+    if let Some(recording) = recorder {
+        stop_recording(recording).await;
+    }
+    if let Some(logger) = logger {
+        stop_logging(logger).await;
+    }
     Ok(())
 }
+
+/// Record a run given --record to a GIF as a subscriber of its own, next to a window or a terminal frontend
+/// that can coalesce its events and fall behind, so the recording still gets every turn.
+pub async fn run_recorder(args: Args, events: Receiver<Event>) -> Result<()> {
+    let mut recorder = start_recording(&args, 0, &[]);
+    while let Some(recording) = recorder.as_mut() {
+        let Ok(event) = events.recv_async().await else { break };
+        if !recording.on_event(&event) {
+            log::info!(target: "Record", "Maximum recording duration reached");
+            break
+        }
+        if let Event::StateChange { new_state: State::Quitting, .. } = event {
+            break
+        }
+    }
+    if let Some(recording) = recorder {
+        stop_recording(recording).await;
+    }
+    Ok(())
+}
+
+/// Write the series of the population graph to a CSV file in the output directory, in the background.
+fn export_graph(args: &Args, completed_turns: u32, graph: &Graph) {
+    let path = args.output_dir
+        .join(format!("{}x{}x{}-population.csv", args.width(), args.height(), completed_turns));
+    let csv = graph.to_csv();
+    tokio::spawn(async move {
+        let export = async {
+            if let Some(dir) = path.parent() {
+                tokio::fs::create_dir_all(dir).await?;
+            }
+            tokio::fs::write(&path, csv).await
+        };
+        match export.await {
+            Ok(()) => log::info!(target: "Window", "Population graph exported to {}", path.display()),
+            Err(e) => log::error!(target: "Window", "Cannot export the population graph: {}", e),
+        }
+    });
+}
+
+pub(crate) fn start_recording(args: &Args, completed_turns: u32, alive: &[CellCoord]) -> Option<GifRecorder> {
+    let path = args.output_dir
+        .join(format!("{}x{}x{}.gif", args.width(), args.height(), completed_turns));
+    match GifRecorder::start(&path, &RecordOptions::from(args), args.width(), args.height(), alive) {
+        Ok(recording) => {
+            log::info!(target: "Record", "Recording to {}", path.display());
+            Some(recording)
+        },
+        Err(e) => {
+            log::error!(target: "Record", "Cannot start recording: {:#}", e);
+            None
+        },
+    }
+}
+
+pub(crate) fn record(recorder: &mut Option<GifRecorder>, event: &Event) {
+    if let Some(recording) = recorder.as_mut() {
+        if !recording.on_event(event) {
+            log::info!(target: "Record", "Maximum recording duration reached");
+            tokio::spawn(stop_recording(recorder.take().unwrap()));
+        }
+    }
+}
+
+pub(crate) async fn stop_recording(recording: GifRecorder) {
+    match tokio::task::spawn_blocking(move || recording.finish()).await {
+        Ok(Ok(path)) => log::info!(target: "Record", "Recording saved to {}", path.display()),
+        Ok(Err(e)) => log::error!(target: "Record", "Cannot save recording: {:#}", e),
+        Err(e) => log::error!(target: "Record", "Cannot save recording: {}", e),
+    }
+}
+
+pub(crate) fn start_logging(args: &Args) -> Option<EventLogger> {
+    let path = args.event_log.as_ref()?;
+    match EventLogger::start(path, args.width(), args.height()) {
+        Ok(logger) => {
+            log::info!(target: "Record", "Logging events to {}", path.display());
+            Some(logger)
+        },
+        Err(e) => {
+            log::error!(target: "Record", "Cannot start the event log: {:#}", e);
+            None
+        },
+    }
+}
+
+pub(crate) async fn stop_logging(logger: EventLogger) {
+    match tokio::task::spawn_blocking(move || logger.finish()).await {
+        Ok(Ok(path)) => log::info!(target: "Record", "Event log saved to {}", path.display()),
+        Ok(Err(e)) => log::error!(target: "Record", "Cannot save the event log: {:#}", e),
+        Err(e) => log::error!(target: "Record", "Cannot save the event log: {}", e),
+    }
+}
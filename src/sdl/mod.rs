@@ -1,2 +1,12 @@
+pub mod colour;
+pub mod compare;
+pub mod config;
+pub mod font;
+pub mod graph;
+pub mod help;
+pub mod hud;
 pub mod r#loop;
+pub mod offscreen;
+pub mod tools;
+pub mod viewport;
 pub mod window;
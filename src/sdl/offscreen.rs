@@ -0,0 +1,365 @@
+use crate::gol::stats::TurnStats;
+use crate::sdl::colour::{CellHistory, ColourMode, ColourOptions};
+use crate::sdl::compare::{CompareLayout, Comparison};
+use crate::sdl::config::Theme;
+use crate::sdl::graph::Graph;
+use crate::sdl::help::Help;
+use crate::sdl::hud::Hud;
+use crate::sdl::tools::Selection;
+use crate::sdl::viewport::Viewport;
+use crate::util::cell::CellCoord;
+use anyhow::{Context, Result};
+use image::RgbaImage;
+use std::path::Path;
+
+/// The share of the frame that the graph panel takes below the cells when it is shown.
+const GRAPH_SHARE: u32 = 4;
+
+/// `Offscreen` is everything the SDL window shows, drawn in software into an ARGB8888 buffer in memory.
+/// It follows the cells through their flips and turns and renders the frames that the window
+/// copies to its texture, so it can stand in for the window where there is no display.
+/// The cells take the whole frame, or the part of it above the graph panel when that is shown.
+/// When comparing two worlds, the frame follows the second one as well and shows it next to or over the first.
+pub struct Offscreen {
+    width: u32,
+    height: u32,
+    view_width: u32,
+    view_height: u32,
+    viewport: Viewport,
+    colours: ColourOptions,
+    theme: Theme,
+    /// The cells of the world row by row, with the history they are coloured by.
+    cells: CellHistory,
+    compare: Option<Comparison>,
+    hud: Hud,
+    help: Help,
+    graph: Graph,
+    selection: Option<Selection>,
+    pixels: Vec<u8>,
+}
+
+impl Offscreen {
+    /// A `view_width × view_height` frame showing the whole of a `width × height` world.
+    pub fn new(width: u32, height: u32, view_width: u32, view_height: u32) -> Self {
+        let viewport = Viewport::new(width as usize, height as usize, view_width, view_height);
+        let (view_width, view_height) = viewport.view_size();
+        Offscreen {
+            width,
+            height,
+            view_width,
+            view_height,
+            viewport,
+            colours: ColourOptions::default(),
+            theme: Theme::default(),
+            cells: CellHistory::new((width * height) as usize),
+            compare: None,
+            hud: Hud::default(),
+            help: Help::default(),
+            graph: Graph::new(false, 0),
+            selection: None,
+            pixels: vec![0_u8; (view_width * view_height * 4) as usize],
+        }
+    }
+
+    /// Plot the statistics of the turns in `graph`, below the cells when it is visible.
+    pub fn with_graph(mut self, graph: Graph) -> Self {
+        self.set_graph(graph);
+        self
+    }
+
+    pub fn set_graph(&mut self, graph: Graph) {
+        self.graph = graph;
+        self.layout();
+    }
+
+    /// Follow a second world of the same size, laid out with the first as `layout` says.
+    pub fn with_comparison(mut self, layout: CompareLayout) -> Self {
+        self.set_comparison(layout);
+        self
+    }
+
+    pub fn set_comparison(&mut self, layout: CompareLayout) {
+        self.compare = Some(Comparison::new(layout, (self.width * self.height) as usize));
+        self.follow_colour_mode();
+        self.layout();
+    }
+
+    /// Colour the cells with `colours` instead of white on black.
+    pub fn with_colours(mut self, colours: ColourOptions) -> Self {
+        self.set_colours(colours);
+        self
+    }
+
+    pub fn set_colours(&mut self, colours: ColourOptions) {
+        self.colours = ColourOptions { alive: self.theme.alive, dead: self.theme.dead, ..colours };
+        self.follow_colour_mode();
+    }
+
+    /// Follow the age and activity of the cells of both worlds once the colour mode needs them.
+    fn follow_colour_mode(&mut self) {
+        self.cells.follow(self.colours.mode);
+        if let Some(compare) = self.compare.as_mut() {
+            compare.cells_mut().follow(self.colours.mode);
+        }
+    }
+
+    /// Draw in the colours of `theme` instead of the default ones.
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.set_theme(theme);
+        self
+    }
+
+    pub fn set_theme(&mut self, theme: Theme) {
+        self.theme = theme;
+        self.colours = ColourOptions { alive: theme.alive, dead: theme.dead, ..self.colours };
+        self.hud.colour = theme.overlay;
+    }
+
+    /// The width and height of the frame in pixels.
+    pub fn view_size(&self) -> (u32, u32) {
+        (self.view_width, self.view_height)
+    }
+
+    /// The height in pixels of the graph panel at the bottom of the frame, or 0 when it is hidden.
+    fn panel_height(&self) -> u32 {
+        match self.graph.visible {
+            true => (self.view_height / GRAPH_SHARE).min(self.view_height - 1),
+            false => 0,
+        }
+    }
+
+    /// Fit the cells into the part of the frame the graph panel leaves them,
+    /// which the two worlds share between them when they are side by side.
+    fn layout(&mut self) {
+        let cells_width = match &self.compare {
+            Some(Comparison { layout: CompareLayout::SideBySide, .. }) => Comparison::half_width(self.view_width),
+            _ => self.view_width,
+        };
+        self.viewport.resize(cells_width, self.view_height - self.panel_height());
+    }
+
+    /// The column of the frame where the second world starts, when the worlds are side by side.
+    fn compared_left(&self) -> Option<u32> {
+        match &self.compare {
+            Some(Comparison { layout: CompareLayout::SideBySide, .. }) =>
+                Some(self.view_width.saturating_sub(self.viewport.view_size().0)),
+            _ => None,
+        }
+    }
+
+    /// The pixel of the viewport at the pixel (`px`, `py`) of the frame, if the cells are drawn there.
+    fn to_viewport(&self, px: f64, py: f64) -> Option<(f64, f64)> {
+        let (cells_width, cells_height) = self.viewport.view_size();
+        let px = match self.compared_left() {
+            Some(left) if px >= left as f64 => px - left as f64,
+            _ => px,
+        };
+        (px < cells_width as f64 && py < cells_height as f64).then_some((px, py))
+    }
+
+    /// Draw the cells, the grid, the selection, the graph and the overlays into the frame, returning its pixels.
+    pub fn render_frame(&mut self) -> &[u8] {
+        let (_, cells_height) = self.viewport.view_size();
+        let cells_pixels = &mut self.pixels[..(self.view_width * cells_height * 4) as usize];
+        let (viewport, cells, colours, theme) = (&self.viewport, &self.cells, &self.colours, self.theme);
+        let selection = self.selection;
+        let outline = |pixels: &mut [u8]| {
+            if let Some(grid) = theme.grid {
+                viewport.grid(grid, pixels);
+            }
+            if let Some(Selection { x, y, width, height }) = selection {
+                viewport.outline(x, y, width, height, theme.selection, pixels);
+            }
+        };
+        match self.compare.as_mut() {
+            Some(compare) => {
+                compare.render(viewport, cells, colours, cells_pixels, self.view_width, outline);
+                self.hud.differing = Some(compare.differing(cells));
+            },
+            None => {
+                viewport.render_with(|i| cells.colour(i, colours), cells_pixels);
+                outline(cells_pixels);
+            },
+        }
+        let panel_height = self.panel_height();
+        self.graph.draw(&mut self.pixels, self.view_width as usize, cells_height as usize, panel_height as usize);
+        self.hud.population = self.cells.population();
+        self.hud.draw(&mut self.pixels, self.view_width as usize);
+        self.help.draw(&mut self.pixels, self.view_width as usize, self.theme.overlay);
+        &self.pixels
+    }
+
+    /// The pixels of the last frame rendered, row by row.
+    pub fn pixels(&self) -> &[u8] {
+        &self.pixels
+    }
+
+    /// The ARGB colour of the pixel at (`x`, `y`) of the last frame rendered.
+    pub fn pixel(&self, x: u32, y: u32) -> u32 {
+        let i = 4 * (y * self.view_width + x) as usize;
+        u32::from_ne_bytes([self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3]])
+    }
+
+    /// Save the last frame rendered as a PNG image.
+    pub fn save_png<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let (view_width, view_height) = self.view_size();
+        let rgba = self.pixels.chunks_exact(4)
+            .flat_map(|pixel| {
+                let argb = u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]);
+                [(argb >> 16) as u8, (argb >> 8) as u8, argb as u8, (argb >> 24) as u8]
+            })
+            .collect::<Vec<u8>>();
+        let image = RgbaImage::from_raw(view_width, view_height, rgba).context("The frame is not the size of the view")?;
+        if let Some(dir) = path.as_ref().parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        image.save(path.as_ref())
+            .with_context(|| format!("Cannot save the frame to {}", path.as_ref().display()))
+    }
+
+    /// Zoom in by `steps`, or out if negative, around the pixel (`px`, `py`) of the frame.
+    pub fn zoom_at(&mut self, px: f64, py: f64, steps: i32) {
+        if let Some((px, py)) = self.to_viewport(px, py) {
+            self.viewport.zoom_at(px, py, steps);
+        }
+    }
+
+    /// Drag the world by (`dx`, `dy`) pixels of the frame.
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        self.viewport.pan(dx, dy);
+    }
+
+    /// The cell of the world drawn at the pixel (`px`, `py`) of the frame, if there is one.
+    pub fn cell_at(&self, px: f64, py: f64) -> Option<CellCoord> {
+        let (px, py) = self.to_viewport(px, py)?;
+        self.viewport.cell_at(px, py)
+    }
+
+    /// Show the whole world again.
+    pub fn fit(&mut self) {
+        self.viewport.fit();
+    }
+
+    /// Change the size of the frame, returning whether it changed.
+    pub fn resize(&mut self, view_width: u32, view_height: u32) -> bool {
+        let (view_width, view_height) = (view_width.max(1), view_height.max(1));
+        if (view_width, view_height) == self.view_size() {
+            return false
+        }
+        (self.view_width, self.view_height) = (view_width, view_height);
+        self.layout();
+        self.pixels = vec![0_u8; (view_width * view_height * 4) as usize];
+        true
+    }
+
+    /// Show the graph panel if it is hidden or hide it if it is shown, returning whether it is now shown.
+    pub fn toggle_graph(&mut self) -> bool {
+        self.graph.visible ^= true;
+        self.layout();
+        self.graph.visible
+    }
+
+    /// The graph of the statistics of the turns, which the frame does not feed by itself.
+    pub fn graph_mut(&mut self) -> &mut Graph {
+        &mut self.graph
+    }
+
+    pub fn selection(&self) -> Option<Selection> {
+        self.selection
+    }
+
+    pub fn set_selection(&mut self, selection: Option<Selection>) {
+        self.selection = selection;
+    }
+
+    /// Whether each cell of the world is alive, row by row.
+    pub fn alive(&self) -> &[bool] {
+        self.cells.alive()
+    }
+
+    pub fn world_width(&self) -> usize {
+        self.width as usize
+    }
+
+    pub fn world_height(&self) -> usize {
+        self.height as usize
+    }
+
+    /// Switch to the next colour mode, returning it.
+    pub fn cycle_colour_mode(&mut self) -> ColourMode {
+        self.colours.mode = self.colours.mode.next();
+        self.follow_colour_mode();
+        self.colours.mode
+    }
+
+    /// Close the turn the flips so far belong to, which ages the cells and moves the activity window on.
+    /// Returns the statistics of the turn as far as the frame saw its flips.
+    pub fn complete_turn(&mut self, completed_turns: u32) -> TurnStats {
+        let (births, deaths) = self.cells.changes();
+        self.cells.complete_turn(completed_turns, self.colours.activity_window);
+        self.hud.completed_turns = completed_turns;
+        TurnStats { completed_turns, population: self.cells.population(), births, deaths }
+    }
+
+    /// The overlay drawn over the cells. The frame keeps its turn and population up to date.
+    pub fn hud_mut(&mut self) -> &mut Hud {
+        &mut self.hud
+    }
+
+    /// The overlay listing the key bindings, which is hidden until it is toggled.
+    pub fn help_mut(&mut self) -> &mut Help {
+        &mut self.help
+    }
+
+    pub fn set_cell(&mut self, x: u32, y: u32, alive: bool) {
+        self.cells.set((y * self.width + x) as usize, alive);
+    }
+
+    pub fn flip_pixel(&mut self, x: u32, y: u32) {
+        assert!(
+            x < self.width && y < self.height,
+            "Cell flipped at ({}, {}) is outside the bounds of the window.",
+            x, y
+        );
+        self.cells.flip((y * self.width + x) as usize);
+    }
+
+    /// Flip a cell of the second world, when comparing two.
+    pub fn flip_compared_pixel(&mut self, x: u32, y: u32) {
+        let width = self.width;
+        if let Some(compare) = self.compare.as_mut() {
+            compare.cells_mut().flip((y * width + x) as usize);
+        }
+    }
+
+    /// Close the turn of the second world, when comparing two.
+    pub fn complete_compared_turn(&mut self, completed_turns: u32) {
+        let activity_window = self.colours.activity_window;
+        if let Some(compare) = self.compare.as_mut() {
+            compare.cells_mut().complete_turn(completed_turns, activity_window);
+        }
+    }
+
+    /// The number of cells alive in one world and dead in the other, when comparing two.
+    pub fn differing(&self) -> Option<usize> {
+        self.compare.as_ref().map(|compare| compare.differing(&self.cells))
+    }
+
+    /// The number of pixels drawn in the colour of alive cells in the last frame rendered.
+    pub fn count_pixels(&self) -> u32 {
+        self.pixels
+            .chunks_exact(4)
+            .filter(|pixel| u32::from_ne_bytes([pixel[0], pixel[1], pixel[2], pixel[3]]) == self.colours.alive)
+            .count() as u32
+    }
+
+    pub fn alive_cells(&self) -> Vec<CellCoord> {
+        self.cells
+            .alive()
+            .iter()
+            .enumerate()
+            .filter(|(_, &alive)| alive)
+            .map(|(i, _)| CellCoord::new(i % self.width as usize, i / self.width as usize))
+            .collect()
+    }
+}
@@ -0,0 +1,188 @@
+use crate::gol::{edit::Edit, format::{Board, Format}};
+use crate::util::cell::{CellCoord, CellValue};
+use anyhow::{Context, Result};
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// `Selection` is a rectangle of cells in the world, selected in the SDL window to copy or edit.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Selection {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Selection {
+    /// The selection spanning from cell `a` to cell `b`, both included, in any direction.
+    pub fn between(a: CellCoord, b: CellCoord) -> Self {
+        Selection {
+            x: a.x.min(b.x),
+            y: a.y.min(b.y),
+            width: a.x.abs_diff(b.x) + 1,
+            height: a.y.abs_diff(b.y) + 1,
+        }
+    }
+
+    fn cells(&self) -> impl Iterator<Item = CellCoord> + '_ {
+        (self.y..self.y + self.height)
+            .flat_map(move |y| (self.x..self.x + self.width).map(move |x| CellCoord::new(x, y)))
+    }
+
+    /// Copy the selected cells of `alive`, a world `world_width` cells wide stored row by row, into a board.
+    /// Whatever part of the selection lies outside the world is dead.
+    pub fn copy(&self, alive: &[bool], world_width: usize) -> Board {
+        let world_height = alive.len() / world_width.max(1);
+        let mut board = Board::new(self.width, self.height);
+        for cell in self.cells().filter(|cell| cell.x < world_width && cell.y < world_height) {
+            if alive[cell.y * world_width + cell.x] {
+                board.cells[(cell.y - self.y) * self.width + cell.x - self.x] = CellValue::Alive;
+            }
+        }
+        board
+    }
+
+    /// Kill every selected cell.
+    pub fn clear(&self) -> Edit {
+        Edit::Set { cells: self.cells().map(|cell| (cell, CellValue::Dead)).collect() }
+    }
+
+    /// Bring every selected cell to life or kill it at random, with even odds.
+    pub fn fill_random(&self) -> Edit {
+        let mut state = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos() as u64 | 1;
+        let cells = self.cells()
+            .map(|cell| {
+                // xorshift64, which is plenty for scattering cells
+                state ^= state << 13;
+                state ^= state >> 7;
+                state ^= state << 17;
+                (cell, if state & 1 == 1 { CellValue::Alive } else { CellValue::Dead })
+            })
+            .collect();
+        Edit::Set { cells }
+    }
+
+    /// Transform the selected cells in place, keeping the top left corner of the selection where it is.
+    /// Returns the edit and the selection the transformed cells end up in, which is turned on its side
+    /// when they are rotated.
+    pub fn transform(&self, alive: &[bool], world_width: usize, transform: Transform) -> (Edit, Selection) {
+        let board = transform.apply(&self.copy(alive, world_width));
+        let moved = Selection { x: self.x, y: self.y, width: board.width, height: board.height };
+        // every cell is set once, to its value in the transformed board or dead if it is left behind
+        let mut cells = self.cells().map(|cell| (cell, CellValue::Dead)).collect::<HashMap<_, _>>();
+        cells.extend(stamped_cells(&board, CellCoord::new(moved.x, moved.y)));
+        (Edit::Set { cells: cells.into_iter().collect() }, moved)
+    }
+}
+
+/// `Transform` is a way of turning or mirroring the cells of a selection.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    /// Rotate a quarter turn clockwise.
+    Rotate,
+    /// Mirror left to right.
+    FlipHorizontal,
+    /// Mirror top to bottom.
+    FlipVertical,
+}
+
+impl Transform {
+    pub fn apply(&self, board: &Board) -> Board {
+        let (width, height) = (board.width, board.height);
+        let mut transformed = match self {
+            Transform::Rotate => Board::new(height, width),
+            _ => Board::new(width, height),
+        };
+        for y in 0..height {
+            for x in 0..width {
+                let (tx, ty) = match self {
+                    Transform::Rotate => (height - 1 - y, x),
+                    Transform::FlipHorizontal => (width - 1 - x, y),
+                    Transform::FlipVertical => (x, height - 1 - y),
+                };
+                transformed.cells[ty * transformed.width + tx] = board.cells[y * width + x];
+            }
+        }
+        transformed
+    }
+}
+
+/// Set the cells of `board` into the world with its top left corner at `at`,
+/// overwriting the cells under it. The distributor leaves out whatever falls outside the world.
+pub fn stamp(board: &Board, at: CellCoord) -> Edit {
+    Edit::Set { cells: stamped_cells(board, at) }
+}
+
+/// The value of every cell of `board` where it lands when its top left corner is at `at`.
+fn stamped_cells(board: &Board, at: CellCoord) -> Vec<(CellCoord, CellValue)> {
+    board.cells.iter().enumerate()
+        .map(|(i, &value)| (CellCoord::new(at.x + i % board.width, at.y + i / board.width), value))
+        .collect()
+}
+
+/// `Placement` is where the pattern of a file dropped onto the SDL window goes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Placement {
+    /// Replace the whole world, with a smaller pattern centred in it.
+    Replace,
+    /// Stamp the pattern with its top left corner at the cell.
+    At(CellCoord),
+}
+
+/// Read the pattern in the `.rle`, `.cells` or `.pgm` file at `path`,
+/// returning the edit that places it in a `width × height` world as `placement` says.
+pub fn place_file(path: &Path, placement: Placement, width: usize, height: usize) -> Result<Edit> {
+    let name = path.file_name().unwrap_or(path.as_os_str()).to_string_lossy();
+    let format = Format::from_path(path)
+        .with_context(|| format!("{} is not an .rle, .cells or .pgm file", name))?;
+    let bytes = std::fs::read(path).with_context(|| format!("Cannot read {}", name))?;
+    let board = Board::decode_as(&bytes, format).with_context(|| format!("Cannot load {}", name))?;
+    Ok(match placement {
+        Placement::Replace => {
+            let board = board.fit(width, height, format)
+                .with_context(|| format!("Cannot replace the world with {}", name))?;
+            stamp(&board, CellCoord::new(0, 0))
+        },
+        Placement::At(at) => stamp(&board, at),
+    })
+}
+
+/// `Pattern` is a well known pattern that can be stamped under the cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Pattern {
+    Glider,
+    /// The lightweight spaceship.
+    Lwss,
+    GosperGliderGun,
+}
+
+impl Pattern {
+    pub fn rle(&self) -> &'static str {
+        match self {
+            Pattern::Glider => "x = 3, y = 3\nbo$2bo$3o!",
+            Pattern::Lwss => "x = 5, y = 4\nbo2bo$o4b$o3bo$4o!",
+            Pattern::GosperGliderGun => "x = 36, y = 9\n\
+                24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$\
+                2o8bo3bob2o4bobo$10bo5bo7bo$11bo3bo$12b2o!",
+        }
+    }
+
+    pub fn board(&self) -> Result<Board> {
+        Board::decode_as(self.rle().as_bytes(), Format::Rle)
+    }
+}
+
+/// `Tool` is an action on the selection or the cells under the cursor.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tool {
+    Copy,
+    Cut,
+    /// Paste the RLE pattern on the clipboard, or the last copied cells, under the cursor.
+    Paste,
+    Clear,
+    FillRandom,
+    Transform(Transform),
+    Deselect,
+    Stamp(Pattern),
+}
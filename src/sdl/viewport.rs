@@ -0,0 +1,285 @@
+use crate::sdl::colour::{ALIVE, DEAD};
+use crate::util::cell::CellCoord;
+
+/// The smallest side, in pixels, that a small board is upscaled towards when the window is opened.
+const MIN_WINDOW_SIDE: u32 = 512;
+
+/// The share of the display a window may take when it is opened.
+const MAX_DISPLAY_SHARE: f64 = 0.9;
+
+/// The most pixels a single cell can be zoomed to.
+const MAX_ZOOM: f64 = 64.0;
+
+/// The fewest pixels per cell at which the grid is drawn, below which it would hide the cells.
+const GRID_MIN_ZOOM: f64 = 4.0;
+
+/// The colour of the part of the view that lies beyond the edges of the world.
+const BACKGROUND: u32 = 0xFF_20_20_20;
+
+/// Choose the size of a window showing a `width × height` world on a display of `display` pixels.
+/// Small worlds are upscaled by a whole number of pixels per cell, while worlds larger than the display
+/// are shrunk to fit it, keeping their aspect ratio.
+pub fn window_size(width: u32, height: u32, display: (u32, u32)) -> (u32, u32) {
+    let max_width = ((display.0 as f64 * MAX_DISPLAY_SHARE) as u32).max(1);
+    let max_height = ((display.1 as f64 * MAX_DISPLAY_SHARE) as u32).max(1);
+    let (width, height) = (width.max(1), height.max(1));
+    let scale = (MIN_WINDOW_SIDE / width.max(height))
+        .min(max_width / width)
+        .min(max_height / height)
+        .max(1);
+    if width * scale <= max_width && height * scale <= max_height {
+        return (width * scale, height * scale)
+    }
+    let shrink = (max_width as f64 / width as f64).min(max_height as f64 / height as f64);
+    (
+        ((width as f64 * shrink) as u32).max(1),
+        ((height as f64 * shrink) as u32).max(1),
+    )
+}
+
+/// `Viewport` is the part of the world shown in a window, and how large each cell is drawn.
+///
+/// At a zoom of one or more every cell is a block of `zoom × zoom` pixels. Zoomed out, every pixel
+/// covers several cells and is drawn in the average of their colours, which for white alive cells
+/// on black is a grey level from black when they are all dead to white when they are all alive.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Viewport {
+    world_width: usize,
+    world_height: usize,
+    view_width: u32,
+    view_height: u32,
+    /// The pixels per cell.
+    zoom: f64,
+    /// The world coordinates shown at the top left of the view.
+    x: f64,
+    y: f64,
+}
+
+impl Viewport {
+    /// A view of `view_width × view_height` pixels, fitted to the whole world.
+    pub fn new(world_width: usize, world_height: usize, view_width: u32, view_height: u32) -> Self {
+        let mut viewport = Viewport {
+            world_width: world_width.max(1),
+            world_height: world_height.max(1),
+            view_width: view_width.max(1),
+            view_height: view_height.max(1),
+            zoom: 1.0,
+            x: 0.0,
+            y: 0.0,
+        };
+        viewport.fit();
+        viewport
+    }
+
+    pub fn view_size(&self) -> (u32, u32) {
+        (self.view_width, self.view_height)
+    }
+
+    pub fn zoom(&self) -> f64 {
+        self.zoom
+    }
+
+    /// The world coordinates shown at the top left of the view.
+    pub fn offset(&self) -> (f64, f64) {
+        (self.x, self.y)
+    }
+
+    /// The cell of the world drawn at the pixel (`px`, `py`), if there is one.
+    pub fn cell_at(&self, px: f64, py: f64) -> Option<CellCoord> {
+        let (x, y) = ((self.x + px / self.zoom).floor(), (self.y + py / self.zoom).floor());
+        let inside = x >= 0.0 && y >= 0.0 && x < self.world_width as f64 && y < self.world_height as f64;
+        inside.then(|| CellCoord::new(x as usize, y as usize))
+    }
+
+    /// Draw the outline of the `width × height` cells with their top left at (`x`, `y`) in `argb`
+    /// over `pixels`, an ARGB8888 buffer the size of the view. Edges outside the view are left out.
+    pub fn outline(&self, x: usize, y: usize, width: usize, height: usize, argb: u32, pixels: &mut [u8]) {
+        let view_width = self.view_width as i64;
+        let to_view = |cell: usize, offset: f64| ((cell as f64 - offset) * self.zoom).floor() as i64;
+        let (left, top) = (to_view(x, self.x), to_view(y, self.y));
+        // the far edges are drawn on the last pixel of the last cell
+        let (right, bottom) = (to_view(x + width, self.x) - 1, to_view(y + height, self.y) - 1);
+        let mut set = |px: i64, py: i64| {
+            if (0..view_width).contains(&px) && (0..self.view_height as i64).contains(&py) {
+                let i = 4 * (py * view_width + px) as usize;
+                pixels[i..i + 4].copy_from_slice(&argb.to_ne_bytes());
+            }
+        };
+        for px in left..=right.max(left) {
+            set(px, top);
+            set(px, bottom.max(top));
+        }
+        for py in top..=bottom.max(top) {
+            set(left, py);
+            set(right.max(left), py);
+        }
+    }
+
+    /// Draw a line in `argb` along the top and left edges of every cell in view over `pixels`,
+    /// an ARGB8888 buffer the size of the view, once the cells are zoomed to at least `GRID_MIN_ZOOM` pixels.
+    pub fn grid(&self, argb: u32, pixels: &mut [u8]) {
+        if self.zoom < GRID_MIN_ZOOM {
+            return
+        }
+        let columns = self.cells_along(self.x, self.view_width, self.world_width);
+        let rows = self.cells_along(self.y, self.view_height, self.world_height);
+        // a cell starts at the first pixel that shows it, unless it is cut off by the edge of the view
+        let starts = |cells: &[Option<usize>], p: usize| p > 0 && cells[p].is_some() && cells[p - 1] != cells[p];
+        for (py, _) in rows.iter().enumerate().filter(|(_, row)| row.is_some()) {
+            for (px, _) in columns.iter().enumerate().filter(|(_, column)| column.is_some()) {
+                if starts(&rows, py) || starts(&columns, px) {
+                    let i = 4 * (py * self.view_width as usize + px);
+                    pixels[i..i + 4].copy_from_slice(&argb.to_ne_bytes());
+                }
+            }
+        }
+    }
+
+    /// The largest zoom that shows the whole world, rounded down to whole pixels per cell once cells
+    /// are at least a pixel wide, so that small worlds are upscaled evenly.
+    fn fit_zoom(&self) -> f64 {
+        let zoom = (self.view_width as f64 / self.world_width as f64)
+            .min(self.view_height as f64 / self.world_height as f64);
+        match zoom >= 1.0 {
+            true => zoom.floor().min(MAX_ZOOM),
+            false => zoom,
+        }
+    }
+
+    /// Show the whole world, centred in the view.
+    pub fn fit(&mut self) {
+        self.zoom = self.fit_zoom();
+        self.clamp();
+    }
+
+    /// Change the size of the view, keeping the world coordinates at its centre where they are.
+    pub fn resize(&mut self, view_width: u32, view_height: u32) {
+        let centre = (
+            self.x + self.view_width as f64 / 2.0 / self.zoom,
+            self.y + self.view_height as f64 / 2.0 / self.zoom,
+        );
+        self.view_width = view_width.max(1);
+        self.view_height = view_height.max(1);
+        self.zoom = self.zoom.max(self.fit_zoom().min(1.0));
+        self.x = centre.0 - self.view_width as f64 / 2.0 / self.zoom;
+        self.y = centre.1 - self.view_height as f64 / 2.0 / self.zoom;
+        self.clamp();
+    }
+
+    /// Zoom in by `steps`, or out if `steps` is negative, keeping the cell under the pixel at
+    /// (`px`, `py`) where it is. Each step adds or removes a pixel per cell, or halves or doubles
+    /// the cells per pixel once zoomed out, and the view never zooms out further than the whole world.
+    pub fn zoom_at(&mut self, px: f64, py: f64, steps: i32) {
+        let (world_x, world_y) = (self.x + px / self.zoom, self.y + py / self.zoom);
+        let min_zoom = self.fit_zoom().min(1.0);
+        for _ in 0..steps.unsigned_abs() {
+            self.zoom = match (steps > 0, self.zoom >= 1.0) {
+                (true, true) => (self.zoom.floor() + 1.0).min(MAX_ZOOM),
+                (true, false) => (self.zoom * 2.0).min(1.0),
+                (false, true) if self.zoom > 1.0 => self.zoom.ceil() - 1.0,
+                (false, _) => (self.zoom / 2.0).max(min_zoom),
+            };
+        }
+        self.x = world_x - px / self.zoom;
+        self.y = world_y - py / self.zoom;
+        self.clamp();
+    }
+
+    /// Move the world by (`dx`, `dy`) pixels, as when it is dragged by the mouse.
+    pub fn pan(&mut self, dx: f64, dy: f64) {
+        self.x -= dx / self.zoom;
+        self.y -= dy / self.zoom;
+        self.clamp();
+    }
+
+    /// Keep the world in view: an axis the world does not fill is centred, and otherwise
+    /// the view cannot move past either edge of the world.
+    fn clamp(&mut self) {
+        let clamp_axis = |offset: f64, view: u32, world: usize| {
+            let span = view as f64 / self.zoom;
+            match span >= world as f64 {
+                true => (world as f64 - span) / 2.0,
+                false => offset.clamp(0.0, world as f64 - span),
+            }
+        };
+        self.x = clamp_axis(self.x, self.view_width, self.world_width);
+        self.y = clamp_axis(self.y, self.view_height, self.world_height);
+    }
+
+    /// The column or row of the world drawn at each pixel along an axis, if any.
+    fn cells_along(&self, offset: f64, view: u32, world: usize) -> Vec<Option<usize>> {
+        (0..view)
+            .map(|p| {
+                let cell = (offset + (p as f64 + 0.5) / self.zoom).floor();
+                (cell >= 0.0 && cell < world as f64).then_some(cell as usize)
+            })
+            .collect()
+    }
+
+    /// The pixel along an axis that each cell of the world falls in, if it is in view.
+    fn pixels_along(&self, offset: f64, view: u32, world: usize) -> Vec<Option<usize>> {
+        (0..world)
+            .map(|cell| {
+                let p = ((cell as f64 - offset) * self.zoom).floor();
+                (p >= 0.0 && p < view as f64).then_some(p as usize)
+            })
+            .collect()
+    }
+
+    /// Draw the world, given as whether each cell is alive row by row, into `pixels`,
+    /// an ARGB8888 buffer the size of the view, with white alive cells on black.
+    pub fn render(&self, cells: &[bool], pixels: &mut [u8]) {
+        self.render_with(|i| if cells[i] { ALIVE } else { DEAD }, pixels)
+    }
+
+    /// Draw the world into `pixels`, an ARGB8888 buffer the size of the view,
+    /// taking the colour of the cell at index `i`, counted row by row, from `colour(i)`.
+    pub fn render_with(&self, colour: impl Fn(usize) -> u32, pixels: &mut [u8]) {
+        let (view_width, view_height) = (self.view_width as usize, self.view_height as usize);
+        assert_eq!(pixels.len(), view_width * view_height * 4, "The pixels must be the size of the view");
+        let mut set = |px: usize, py: usize, argb: u32| {
+            let i = 4 * (py * view_width + px);
+            pixels[i..i + 4].copy_from_slice(&argb.to_ne_bytes());
+        };
+
+        if self.zoom >= 1.0 {
+            let columns = self.cells_along(self.x, self.view_width, self.world_width);
+            let rows = self.cells_along(self.y, self.view_height, self.world_height);
+            for (py, row) in rows.iter().enumerate() {
+                for (px, column) in columns.iter().enumerate() {
+                    let argb = match (row, column) {
+                        (Some(y), Some(x)) => colour(y * self.world_width + x),
+                        _ => BACKGROUND,
+                    };
+                    set(px, py, argb);
+                }
+            }
+            return
+        }
+
+        // zoomed out, add up the colours of the cells under every pixel, reading each visible cell once
+        let columns = self.pixels_along(self.x, self.view_width, self.world_width);
+        let rows = self.pixels_along(self.y, self.view_height, self.world_height);
+        let mut sums = vec![([0_u32; 3], 0_u32); view_width * view_height];
+        for (y, py) in rows.iter().enumerate() {
+            let Some(py) = py else { continue };
+            for (x, px) in columns.iter().enumerate() {
+                let Some(px) = px else { continue };
+                let argb = colour(y * self.world_width + x);
+                let (channels, total) = &mut sums[py * view_width + px];
+                for (channel, shift) in channels.iter_mut().zip([16, 8, 0]) {
+                    *channel += argb >> shift & 0xFF;
+                }
+                *total += 1;
+            }
+        }
+        for (i, (channels, total)) in sums.into_iter().enumerate() {
+            let argb = match total {
+                0 => BACKGROUND,
+                _ => channels.iter().zip([16, 8, 0])
+                    .fold(0xFF_00_00_00, |argb, (channel, shift)| argb | (channel / total) << shift),
+            };
+            set(i % view_width, i / view_width, argb);
+        }
+    }
+}
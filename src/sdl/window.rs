@@ -1,21 +1,38 @@
+use crate::gol::stats::TurnStats;
+use crate::sdl::colour::{ColourMode, ColourOptions};
+use crate::sdl::compare::CompareLayout;
+use crate::sdl::config::Theme;
+use crate::sdl::graph::Graph;
+use crate::sdl::help::Help;
+use crate::sdl::hud::Hud;
+use crate::sdl::offscreen::Offscreen;
+use crate::sdl::tools::Selection;
+use crate::sdl::viewport::window_size;
+use crate::util::cell::CellCoord;
 use anyhow::{anyhow, Result, Context};
 use sdl2::EventPump;
-use sdl2::pixels::{PixelFormatEnum, Color};
+use sdl2::clipboard::ClipboardUtil;
+use sdl2::pixels::PixelFormatEnum;
 use sdl2::render::{Texture, Canvas};
 use sdl2::video::Window as SdlWindow;
 
+/// The display size assumed when the display cannot be queried.
+const FALLBACK_DISPLAY: (u32, u32) = (1280, 1024);
+
+/// `Window` shows a world of cells through a `Viewport`, which can be zoomed and panned.
+/// The frames are rendered offscreen and copied to a streaming texture the size of the window
+/// rather than the world, so any world can be opened.
 pub struct Window {
-    width: u32,
-    height: u32,
-    pitch: u32,
     canvas: Canvas<SdlWindow>,
     texture: Option<Texture>,
     pump: Option<EventPump>,
-    pixels: Vec<u8>,
+    screen: Offscreen,
+    clipboard: ClipboardUtil,
 }
 
 #[allow(dead_code)]
 impl Window {
+    /// Open a window on a `width × height` world, sized to fit the display.
     pub fn new<T: AsRef<str>>(
         title: T,
         width: u32,
@@ -23,31 +40,78 @@ impl Window {
     ) -> Result<Self> {
         let context = sdl2::init().map_err(|e| anyhow!(e))?;
         let video = context.video().map_err(|e| anyhow!(e))?;
+        let display = video.display_usable_bounds(0)
+            .map(|bounds| (bounds.width(), bounds.height()))
+            .unwrap_or(FALLBACK_DISPLAY);
+        let (window_width, window_height) = window_size(width, height, display);
         let window = video
-            .window(title.as_ref(), width, height)
+            .window(title.as_ref(), window_width, window_height)
             .position_centered()
+            .resizable()
             .opengl()
             .allow_highdpi()
             .build()?;
         let pump = context.event_pump().map_err(|e| anyhow!(e))?;
         let canvas = window.into_canvas().build()?;
-        let texture = canvas.texture_creator().create_texture_streaming(
-            PixelFormatEnum::ARGB8888,
-            width,
-            height,
-        )?;
-        let pitch = width * 4 * std::mem::size_of::<u8>() as u32;
-        let pixels = vec![0_u8; (height * pitch) as usize];
+        let (view_width, view_height) = canvas.output_size().map_err(|e| anyhow!(e))?;
 
-        Ok(Window {
-            width,
-            height,
-            pitch,
+        let mut window = Window {
             canvas,
-            texture: Some(texture),
+            texture: None,
             pump: Some(pump),
-            pixels,
-        })
+            screen: Offscreen::new(width, height, view_width, view_height),
+            clipboard: video.clipboard(),
+        };
+        window.create_texture()?;
+        Ok(window)
+    }
+
+    /// Colour the cells with `colours` instead of white on black.
+    pub fn with_colours(mut self, colours: ColourOptions) -> Self {
+        self.screen.set_colours(colours);
+        self
+    }
+
+    /// Draw in the colours of `theme` instead of the default ones.
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.screen.set_theme(theme);
+        self
+    }
+
+    /// Show a second world next to or over the first, as `layout` says.
+    /// Side by side, the window widens to fit both worlds on the display.
+    pub fn with_comparison(mut self, layout: CompareLayout) -> Result<Self> {
+        if layout == CompareLayout::SideBySide {
+            let display = self.canvas.window().subsystem().display_usable_bounds(0)
+                .map(|bounds| (bounds.width(), bounds.height()))
+                .unwrap_or(FALLBACK_DISPLAY);
+            let (width, height) = (self.screen.world_width() as u32, self.screen.world_height() as u32);
+            let (window_width, window_height) = window_size(2 * width, height, display);
+            self.canvas.window_mut().set_size(window_width, window_height)?;
+        }
+        self.screen.set_comparison(layout);
+        self.resize()?;
+        Ok(self)
+    }
+
+    /// Plot the statistics of the turns in `graph`, below the cells when it is visible.
+    pub fn with_graph(mut self, graph: Graph) -> Self {
+        self.screen.set_graph(graph);
+        self
+    }
+
+    /// Create the streaming texture at the size of the frame, replacing any old texture.
+    fn create_texture(&mut self) -> Result<()> {
+        let (view_width, view_height) = self.screen.view_size();
+        let texture = self.canvas.texture_creator().create_texture_streaming(
+            PixelFormatEnum::ARGB8888,
+            view_width,
+            view_height,
+        )?;
+        if let Some(old) = self.texture.replace(texture) {
+            unsafe { old.destroy(); }
+        }
+        Ok(())
     }
 
     pub fn take_event_pump(&mut self) -> Result<EventPump> {
@@ -55,51 +119,158 @@ impl Window {
     }
 
     pub fn render_frame(&mut self) -> Result<()> {
+        let pitch = self.screen.view_size().0 as usize * 4;
+        let pixels = self.screen.render_frame();
         let texture = self.texture.as_mut().context("Missing texture")?;
-        texture.update(None, &self.pixels, self.pitch as usize)?;
+        texture.update(None, pixels, pitch)?;
         self.canvas.clear();
-        self.canvas.copy(&texture, None, None).map_err(|e| anyhow!(e))?;
+        self.canvas.copy(texture, None, None).map_err(|e| anyhow!(e))?;
         self.canvas.present();
         Ok(())
     }
 
-    pub fn set_pixel(&mut self, x: u32, y: u32, color: Color) {
-        self.pixels[4 * (y * self.width + x) as usize + 0] = color.a;
-        self.pixels[4 * (y * self.width + x) as usize + 1] = color.r;
-        self.pixels[4 * (y * self.width + x) as usize + 2] = color.g;
-        self.pixels[4 * (y * self.width + x) as usize + 3] = color.b;
+    /// Convert a position in window coordinates, as SDL reports the mouse, to pixels of the viewport,
+    /// which differ on high-DPI displays.
+    fn to_view(&self, x: i32, y: i32) -> (f64, f64) {
+        let (window_width, window_height) = self.canvas.window().size();
+        let (view_width, view_height) = self.screen.view_size();
+        (
+            x as f64 * view_width as f64 / window_width.max(1) as f64,
+            y as f64 * view_height as f64 / window_height.max(1) as f64,
+        )
+    }
+
+    /// Zoom in by `steps`, or out if negative, around the mouse at (`x`, `y`) in window coordinates.
+    pub fn zoom_at(&mut self, x: i32, y: i32, steps: i32) {
+        let (px, py) = self.to_view(x, y);
+        self.screen.zoom_at(px, py, steps);
+    }
+
+    /// Drag the world by (`dx`, `dy`) in window coordinates.
+    pub fn pan(&mut self, dx: i32, dy: i32) {
+        let (px, py) = self.to_view(dx, dy);
+        self.screen.pan(px, py);
+    }
+
+    /// The cell of the world under the mouse at (`x`, `y`) in window coordinates, if there is one.
+    pub fn cell_at(&self, x: i32, y: i32) -> Option<CellCoord> {
+        let (px, py) = self.to_view(x, y);
+        self.screen.cell_at(px, py)
+    }
+
+    pub fn selection(&self) -> Option<Selection> {
+        self.screen.selection()
+    }
+
+    pub fn set_selection(&mut self, selection: Option<Selection>) {
+        self.screen.set_selection(selection);
+    }
+
+    /// Whether each cell of the world is alive, row by row.
+    pub fn alive(&self) -> &[bool] {
+        self.screen.alive()
+    }
+
+    pub fn world_width(&self) -> usize {
+        self.screen.world_width()
+    }
+
+    pub fn world_height(&self) -> usize {
+        self.screen.world_height()
+    }
+
+    /// The text on the system clipboard, if there is any.
+    pub fn clipboard_text(&self) -> Option<String> {
+        self.clipboard.has_clipboard_text()
+            .then(|| self.clipboard.clipboard_text().ok())
+            .flatten()
+    }
+
+    pub fn set_clipboard_text(&self, text: &str) -> Result<()> {
+        self.clipboard.set_clipboard_text(text).map_err(|e| anyhow!(e))
+    }
+
+    /// Show the whole world again.
+    pub fn fit(&mut self) {
+        self.screen.fit();
+    }
+
+    /// Follow a change in the size of the window.
+    pub fn resize(&mut self) -> Result<()> {
+        let (view_width, view_height) = self.canvas.output_size().map_err(|e| anyhow!(e))?;
+        if self.screen.resize(view_width, view_height) {
+            self.create_texture()?;
+        }
+        Ok(())
+    }
+
+    /// Switch to the next colour mode, returning it.
+    pub fn cycle_colour_mode(&mut self) -> ColourMode {
+        self.screen.cycle_colour_mode()
+    }
+
+    /// Close the turn the flips so far belong to, which ages the cells and moves the activity window on.
+    /// Returns the statistics of the turn as far as the window saw its flips.
+    pub fn complete_turn(&mut self, completed_turns: u32) -> TurnStats {
+        self.screen.complete_turn(completed_turns)
+    }
+
+    /// Show the graph panel if it is hidden or hide it if it is shown, returning whether it is now shown.
+    pub fn toggle_graph(&mut self) -> bool {
+        self.screen.toggle_graph()
+    }
+
+    /// The graph of the statistics of the turns, which the window does not feed by itself.
+    pub fn graph_mut(&mut self) -> &mut Graph {
+        self.screen.graph_mut()
+    }
+
+    /// The overlay drawn over the cells. The window keeps its turn and population up to date.
+    pub fn hud_mut(&mut self) -> &mut Hud {
+        self.screen.hud_mut()
+    }
+
+    /// The overlay listing the key bindings, which is hidden until it is toggled.
+    pub fn help_mut(&mut self) -> &mut Help {
+        self.screen.help_mut()
+    }
+
+    /// The frames the window shows, as rendered in memory.
+    pub fn screen(&self) -> &Offscreen {
+        &self.screen
+    }
+
+    pub fn set_cell(&mut self, x: u32, y: u32, alive: bool) {
+        self.screen.set_cell(x, y, alive);
     }
 
     pub fn flip_pixel(&mut self, x: u32, y: u32) {
-        assert!(
-            x < self.width && y < self.height,
-            "Cell flipped at ({}, {}) is outside the bounds of the window.",
-            x, y
-        );
-        self.pixels[4 * (y * self.width + x) as usize + 0] =
-            !self.pixels[4 * (y * self.width + x) as usize + 0];
-        self.pixels[4 * (y * self.width + x) as usize + 1] =
-            !self.pixels[4 * (y * self.width + x) as usize + 1];
-        self.pixels[4 * (y * self.width + x) as usize + 2] =
-            !self.pixels[4 * (y * self.width + x) as usize + 2];
-        self.pixels[4 * (y * self.width + x) as usize + 3] =
-            !self.pixels[4 * (y * self.width + x) as usize + 3];
+        self.screen.flip_pixel(x, y);
+    }
+
+    /// Flip a cell of the second world, when comparing two.
+    pub fn flip_compared_pixel(&mut self, x: u32, y: u32) {
+        self.screen.flip_compared_pixel(x, y);
+    }
+
+    /// Close the turn of the second world, when comparing two.
+    pub fn complete_compared_turn(&mut self, completed_turns: u32) {
+        self.screen.complete_compared_turn(completed_turns);
     }
 
     pub fn count_pixels(&self) -> u32 {
-        self.pixels
-            .chunks(4)
-            .filter(|&chunk|
-                chunk.get(0).map_or(false, |&x| x == 0xFF_u8))
-            .count() as u32
+        self.screen.count_pixels()
     }
 
+    pub fn alive_cells(&self) -> Vec<CellCoord> {
+        self.screen.alive_cells()
+    }
 }
 
 impl Drop for Window {
     fn drop(&mut self) {
-        unsafe {
-            self.texture.take().unwrap().destroy();
+        if let Some(texture) = self.texture.take() {
+            unsafe { texture.destroy(); }
         }
     }
 }
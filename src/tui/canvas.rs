@@ -0,0 +1,246 @@
+use crate::sdl::colour::DEAD;
+use crate::util::cell::CellCoord;
+use clap::ValueEnum;
+use std::fmt::Write;
+
+/// The colour of the terminal around the world, when the world does not fill it.
+const BACKGROUND: u32 = 0xFF_20_20_20;
+
+/// The most cells per side that a dot can stand for when zoomed out.
+const MAX_SCALE: usize = 1 << 12;
+
+/// `Glyphs` is the kind of character the terminal frontend draws cells with.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Glyphs {
+    /// Braille characters, each showing 2×4 cells in a single colour.
+    #[default]
+    Braille,
+    /// Upper half blocks, each showing 1×2 cells in a colour of their own.
+    HalfBlock,
+}
+
+impl Glyphs {
+    /// The width and height in dots of a character.
+    pub fn size(self) -> (usize, usize) {
+        match self {
+            Glyphs::Braille => (2, 4),
+            Glyphs::HalfBlock => (1, 2),
+        }
+    }
+
+    pub fn next(self) -> Self {
+        match self {
+            Glyphs::Braille => Glyphs::HalfBlock,
+            Glyphs::HalfBlock => Glyphs::Braille,
+        }
+    }
+}
+
+/// `Glyph` is a character of the terminal with the ARGB colours it is drawn in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Glyph {
+    pub symbol: char,
+    pub foreground: u32,
+    pub background: u32,
+}
+
+/// `Canvas` maps a world of cells onto a grid of characters, each of which shows a few dots.
+/// A dot stands for a `scale × scale` block of cells, so a world larger than the terminal can be zoomed out of,
+/// and the top left cell of the view can be moved to pan across the world.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Canvas {
+    world_width: usize,
+    world_height: usize,
+    columns: usize,
+    rows: usize,
+    glyphs: Glyphs,
+    scale: usize,
+    x: usize,
+    y: usize,
+}
+
+impl Canvas {
+    /// A canvas of `columns × rows` characters showing the whole `world_width × world_height` world.
+    pub fn new(world_width: usize, world_height: usize, columns: usize, rows: usize, glyphs: Glyphs) -> Self {
+        let mut canvas = Canvas { world_width, world_height, columns, rows, glyphs, scale: 1, x: 0, y: 0 };
+        canvas.fit();
+        canvas
+    }
+
+    /// The width and height of the canvas in characters.
+    pub fn size(&self) -> (usize, usize) {
+        (self.columns, self.rows)
+    }
+
+    pub fn glyphs(&self) -> Glyphs {
+        self.glyphs
+    }
+
+    /// The number of cells per side that a dot stands for.
+    pub fn scale(&self) -> usize {
+        self.scale
+    }
+
+    /// The cell of the world in the top left corner of the canvas.
+    pub fn offset(&self) -> (usize, usize) {
+        (self.x, self.y)
+    }
+
+    /// The width and height of the canvas in dots.
+    fn dots(&self) -> (usize, usize) {
+        let (width, height) = self.glyphs.size();
+        (self.columns * width, self.rows * height)
+    }
+
+    /// Show the whole world, with as few cells per dot as it takes.
+    pub fn fit(&mut self) {
+        let (dots_width, dots_height) = self.dots();
+        self.scale = self.world_width.div_ceil(dots_width.max(1))
+            .max(self.world_height.div_ceil(dots_height.max(1)))
+            .clamp(1, MAX_SCALE);
+        self.x = 0;
+        self.y = 0;
+    }
+
+    /// Draw with `glyphs` from now on, showing the whole world again.
+    pub fn set_glyphs(&mut self, glyphs: Glyphs) {
+        self.glyphs = glyphs;
+        self.fit();
+    }
+
+    /// Follow a change in the size of the terminal, keeping the cells per dot.
+    pub fn resize(&mut self, columns: usize, rows: usize) {
+        self.columns = columns;
+        self.rows = rows;
+        self.clamp();
+    }
+
+    /// Zoom in by `steps`, or out if negative, halving or doubling the cells per dot
+    /// while keeping the cell at the centre of the canvas where it is.
+    pub fn zoom(&mut self, steps: i32) {
+        let centre = self.centre();
+        for _ in 0..steps.unsigned_abs() {
+            self.scale = match steps > 0 {
+                true => (self.scale / 2).max(1),
+                false => (self.scale * 2).min(MAX_SCALE),
+            };
+        }
+        let (dots_width, dots_height) = self.dots();
+        self.x = centre.x.saturating_sub(dots_width * self.scale / 2);
+        self.y = centre.y.saturating_sub(dots_height * self.scale / 2);
+        self.clamp();
+    }
+
+    /// Move the view by (`dx`, `dy`) characters.
+    pub fn pan(&mut self, dx: i32, dy: i32) {
+        let (width, height) = self.glyphs.size();
+        self.x = (self.x as i64 + dx as i64 * (width * self.scale) as i64).max(0) as usize;
+        self.y = (self.y as i64 + dy as i64 * (height * self.scale) as i64).max(0) as usize;
+        self.clamp();
+    }
+
+    /// The cell of the world at the centre of the canvas, or nearest to it.
+    pub fn centre(&self) -> CellCoord {
+        let (dots_width, dots_height) = self.dots();
+        CellCoord::new(
+            (self.x + dots_width * self.scale / 2).min(self.world_width.saturating_sub(1)),
+            (self.y + dots_height * self.scale / 2).min(self.world_height.saturating_sub(1)),
+        )
+    }
+
+    /// Keep the view from going past the far edges of the world.
+    fn clamp(&mut self) {
+        let (dots_width, dots_height) = self.dots();
+        self.x = self.x.min(self.world_width.saturating_sub(dots_width * self.scale));
+        self.y = self.y.min(self.world_height.saturating_sub(dots_height * self.scale));
+    }
+
+    /// Draw the world into rows of glyphs, given whether each cell is alive and the colour of each cell, row by row.
+    /// A half block is coloured by the average of the cells behind each of its dots.
+    /// A braille character lights the dots with any alive cell behind them, in the average colour of those cells.
+    pub fn draw(&self, alive: &[bool], colour: impl Fn(usize) -> u32) -> Vec<Vec<Glyph>> {
+        assert_eq!(alive.len(), self.world_width * self.world_height, "The cells must be the size of the world");
+        let (width, height) = self.glyphs.size();
+        (0..self.rows).map(|row| (0..self.columns).map(|column| {
+            let (dx, dy) = (column * width, row * height);
+            match self.glyphs {
+                Glyphs::HalfBlock => Glyph {
+                    symbol: '▀',
+                    foreground: average(self.cells(dx, dy).map(&colour)).unwrap_or(BACKGROUND),
+                    background: average(self.cells(dx, dy + 1).map(&colour)).unwrap_or(BACKGROUND),
+                },
+                Glyphs::Braille => {
+                    let mut bits = 0;
+                    let mut lit = Vec::new();
+                    let mut inside = false;
+                    for (bit, (x, y)) in BRAILLE_DOTS.iter().enumerate() {
+                        let mut cells = self.cells(dx + x, dy + y).peekable();
+                        inside |= cells.peek().is_some();
+                        let before = lit.len();
+                        lit.extend(cells.filter(|&i| alive[i]).map(&colour));
+                        if lit.len() > before {
+                            bits |= 1 << bit;
+                        }
+                    }
+                    Glyph {
+                        symbol: match bits {
+                            0 => ' ',
+                            _ => char::from_u32(0x2800 + bits).unwrap_or(' '),
+                        },
+                        foreground: average(lit.into_iter()).unwrap_or(DEAD),
+                        background: if inside { DEAD } else { BACKGROUND },
+                    }
+                },
+            }
+        }).collect()).collect()
+    }
+
+    /// The cells of the world, by index, behind the dot at (`dx`, `dy`) of the canvas.
+    fn cells(&self, dx: usize, dy: usize) -> impl Iterator<Item = usize> + '_ {
+        let (x, y) = (self.x + dx * self.scale, self.y + dy * self.scale);
+        let (right, bottom) = ((x + self.scale).min(self.world_width), (y + self.scale).min(self.world_height));
+        (y.min(bottom)..bottom).flat_map(move |y| (x.min(right)..right).map(move |x| y * self.world_width + x))
+    }
+}
+
+/// The dots of a braille character by their bit in its code point, as (x, y) within the character.
+const BRAILLE_DOTS: [(usize, usize); 8] = [(0, 0), (0, 1), (0, 2), (1, 0), (1, 1), (1, 2), (0, 3), (1, 3)];
+
+/// The average of each channel of `colours`, if there are any.
+fn average(colours: impl Iterator<Item = u32>) -> Option<u32> {
+    let mut total = 0;
+    let mut channels = [0_u64; 3];
+    for argb in colours {
+        total += 1;
+        for (channel, shift) in channels.iter_mut().zip([16, 8, 0]) {
+            *channel += (argb >> shift & 0xFF) as u64;
+        }
+    }
+    (total > 0).then(|| channels.iter().zip([16, 8, 0])
+        .fold(0xFF_00_00_00, |argb, (channel, shift)| argb | ((channel / total) as u32) << shift))
+}
+
+/// Encode rows of glyphs as the ANSI escape codes that draw them in 24-bit colour from the top left of the terminal.
+/// The colours are only given again when they change.
+pub fn to_ansi(frame: &[Vec<Glyph>]) -> String {
+    let mut ansi = String::new();
+    for (row, glyphs) in frame.iter().enumerate() {
+        let _ = write!(ansi, "\x1b[{};1H", row + 1);
+        let mut colours = None;
+        for glyph in glyphs {
+            if colours != Some((glyph.foreground, glyph.background)) {
+                let (fr, fg, fb) = rgb(glyph.foreground);
+                let (br, bg, bb) = rgb(glyph.background);
+                let _ = write!(ansi, "\x1b[38;2;{};{};{};48;2;{};{};{}m", fr, fg, fb, br, bg, bb);
+                colours = Some((glyph.foreground, glyph.background));
+            }
+            ansi.push(glyph.symbol);
+        }
+    }
+    ansi.push_str("\x1b[0m");
+    ansi
+}
+
+fn rgb(argb: u32) -> (u8, u8, u8) {
+    ((argb >> 16) as u8, (argb >> 8) as u8, argb as u8)
+}
@@ -0,0 +1,185 @@
+use crate::args::Args;
+use crate::gol::{edit::Edit, event::{Event, State}};
+use crate::sdl::colour::{CellHistory, ColourOptions};
+use crate::sdl::config::{Action, Config, KeyBindings};
+use crate::sdl::hud::Hud;
+use crate::sdl::r#loop::{record, start_logging, start_recording, stop_logging, stop_recording};
+use crate::sdl::tools::{stamp, Tool};
+use crate::tui::canvas::{to_ansi, Canvas};
+use crate::tui::terminal::{self, Key, Terminal};
+use crate::util::avgturns::AvgTurns;
+use crate::util::cell::CellCoord;
+use anyhow::Result;
+use flume::{Receiver, Sender};
+use sdl2::keyboard::Keycode;
+use tokio::select;
+use std::time::Duration;
+
+/// Show the simulation in the terminal, sending key presses on to the distributor like the SDL window does.
+/// The keys are those of the --config file for the actions the terminal has, except that the key of the
+/// population graph switches the glyphs, as there is no graph to show. The arrow keys pan and + and - zoom
+/// unless they are bound to an action, while the patterns are stamped at the centre of the terminal,
+/// as there is no mouse to point at a cell with.
+pub async fn run(
+    args: Args,
+    events: Receiver<Event>,
+    key_presses: Sender<Keycode>,
+    edits: Option<Sender<Edit>>,
+) -> Result<()> {
+    let (width, height) = (args.width(), args.height());
+    let bindings = match &args.config {
+        Some(path) => Config::load(path).map(|config| config.keys).unwrap_or_else(|e| {
+            log::error!(target: "Terminal", "{:#}", e);
+            KeyBindings::default()
+        }),
+        None => KeyBindings::default(),
+    };
+    let terminal = Terminal::enter()?;
+    let keys = terminal::read_keys();
+    let mut hud = Hud::default();
+    let mut size = terminal.size();
+    let mut canvas = Canvas::new(width, height, size.0, board_rows(size.1, &hud), args.tui_glyphs);
+    let mut cells = CellHistory::new(width * height);
+    let mut colours = ColourOptions::from(&args);
+    cells.follow(colours.mode);
+
+    let mut dirty = true;
+    let mut refresh_interval = tokio::time::interval(
+        Duration::from_secs_f64(1_f64 / args.fps as f64)
+    );
+    let mut avg_turns = AvgTurns::new();
+    let mut completed_turns = 0;
+    // a recording asked for with --record follows the events on its own, so only R starts one here
+    let mut recorder = None;
+    let mut logger = start_logging(&args);
+
+    'tui: loop {
+        select! {
+            _ = refresh_interval.tick() => {
+                if terminal.size() != size {
+                    size = terminal.size();
+                    canvas.resize(size.0, board_rows(size.1, &hud));
+                    dirty = true;
+                }
+                if dirty {
+                    hud.population = cells.population();
+                    let frame = canvas.draw(cells.alive(), |i| cells.colour(i, &colours));
+                    let status = hud.visible.then(|| hud.lines().join(" | "));
+                    terminal.draw(&to_ansi(&frame), status.as_deref())?;
+                    dirty = false;
+                }
+            },
+            Ok(key) = keys.recv_async() => {
+                let action = match key {
+                    Key::Interrupt => Some(Action::Quit),
+                    key => key.sdl().and_then(|(keycode, keymod)| bindings.action(keycode, keymod)),
+                };
+                match (action, key) {
+                    (Some(Action::Quit), _) => key_presses.send_async(Keycode::Q).await?,
+                    (Some(Action::Pause), _) => key_presses.send_async(Keycode::P).await?,
+                    (Some(Action::Output), _) => key_presses.send_async(Keycode::S).await?,
+                    (Some(Action::Kill), _) => key_presses.send_async(Keycode::K).await?,
+                    (Some(Action::Record), _) => match recorder.take() {
+                        Some(recording) => { tokio::spawn(stop_recording(recording)); },
+                        None => recorder = start_recording(&args, completed_turns, &alive_cells(&cells, width)),
+                    },
+                    (Some(Action::ColourMode), _) => {
+                        colours.mode = colours.mode.next();
+                        cells.follow(colours.mode);
+                        log::info!(target: "Terminal", "Colouring cells by {}", colours.mode);
+                    },
+                    (Some(Action::Hud), _) => {
+                        hud.visible ^= true;
+                        canvas.resize(size.0, board_rows(size.1, &hud));
+                    },
+                    (Some(Action::Graph), _) => canvas.set_glyphs(canvas.glyphs().next()),
+                    (Some(Action::Fit), _) => canvas.fit(),
+                    // the other actions need a graph, a help overlay or a selection, which the terminal has none of
+                    (Some(action), _) => if let Some(Tool::Stamp(pattern)) = action.tool() {
+                        match (pattern.board(), &edits) {
+                            (Ok(board), Some(edits)) => edits.send_async(stamp(&board, canvas.centre())).await?,
+                            (Ok(_), None) =>
+                                log::warn!(target: "Terminal", "Only a simulation running in this process can be edited"),
+                            (Err(e), _) => log::error!(target: "Terminal", "Cannot stamp a {:?}: {:#}", pattern, e),
+                        }
+                    },
+                    (None, Key::Char('+' | '=')) => canvas.zoom(1),
+                    (None, Key::Char('-' | '_')) => canvas.zoom(-1),
+                    (None, Key::Up) => canvas.pan(0, -1),
+                    (None, Key::Down) => canvas.pan(0, 1),
+                    (None, Key::Left) => canvas.pan(-1, 0),
+                    (None, Key::Right) => canvas.pan(1, 0),
+                    (None, _) => (),
+                }
+                dirty = true;
+            },
+            gol_event = events.recv_async() => {
+                if let Ok(event) = &gol_event {
+                    record(&mut recorder, event);
+                    if let Some(logger) = logger.as_mut() {
+                        logger.on_event(event);
+                    }
+                }
+                match gol_event {
+                    Ok(Event::CellFlipped { cell, .. }) =>
+                        cells.flip(cell.y * width + cell.x),
+                    Ok(Event::CellsFlipped { cells: flipped, .. }) => {
+                        flipped.iter().for_each(|cell| cells.flip(cell.y * width + cell.x));
+                        // edits flip cells without completing a turn, even while paused
+                        dirty = true;
+                    },
+                    Ok(Event::TurnComplete { completed_turns: turns }) => {
+                        completed_turns = turns;
+                        cells.complete_turn(turns, colours.activity_window);
+                        hud.completed_turns = turns;
+                        dirty = true;
+                    },
+                    Ok(Event::AliveCellsCount { completed_turns, .. }) => {
+                        let turns_per_second = avg_turns.get(completed_turns);
+                        hud.turns_per_second = turns_per_second;
+                        log::info!(target: "Event", "{} Avg{:>5} turns/s", gol_event?, turns_per_second);
+                    },
+                    Ok(Event::ImageOutputComplete { .. }) =>
+                        log::info!(target: "Event", "{}", gol_event?),
+                    Ok(Event::FinalTurnComplete { .. }) =>
+                        log::info!(target: "Event", "{}", gol_event?),
+                    Ok(Event::StateChange { new_state, .. }) => {
+                        log::info!(target: "Event", "{}", gol_event?);
+                        hud.state = new_state;
+                        dirty = true;
+                        if let State::Quitting = new_state {
+                            break 'tui
+                        }
+                    },
+                    Err(_) => break 'tui,
+                };
+            }
+        }
+    }
+
+    drop(terminal);
+    if let Some(recording) = recorder {
+        stop_recording(recording).await;
+    }
+    if let Some(logger) = logger {
+        stop_logging(logger).await;
+    }
+    Ok(())
+}
+
+/// The rows of the terminal left for the board, below which the status line takes one when it is shown.
+fn board_rows(rows: usize, hud: &Hud) -> usize {
+    match hud.visible {
+        true => rows.saturating_sub(1),
+        false => rows,
+    }
+}
+
+fn alive_cells(cells: &CellHistory, width: usize) -> Vec<CellCoord> {
+    cells.alive()
+        .iter()
+        .enumerate()
+        .filter(|(_, &alive)| alive)
+        .map(|(i, _)| CellCoord::new(i % width, i / width))
+        .collect()
+}
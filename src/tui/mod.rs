@@ -0,0 +1,3 @@
+pub mod canvas;
+pub mod r#loop;
+pub mod terminal;
@@ -0,0 +1,155 @@
+use anyhow::{bail, Result};
+use flume::Receiver;
+use sdl2::keyboard::{Keycode, Mod};
+use std::fmt::Write as _;
+use std::io::{Read, Write};
+
+/// The size assumed when the terminal cannot be queried.
+const FALLBACK_SIZE: (usize, usize) = (80, 24);
+
+/// `Key` is a key pressed in the terminal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Key {
+    Char(char),
+    Up,
+    Down,
+    Left,
+    Right,
+    Escape,
+    /// Ctrl+C, which arrives as a key rather than a signal in raw mode.
+    Interrupt,
+}
+
+impl Key {
+    /// The SDL key and modifiers that type the same, so the key bindings of the SDL window apply here as well.
+    /// Ctrl+C has none, as it always quits.
+    pub fn sdl(&self) -> Option<(Keycode, Mod)> {
+        let keycode = |c: char| Keycode::from_i32(c as i32);
+        Some(match *self {
+            Key::Char('\r' | '\n') => (Keycode::Return, Mod::NOMOD),
+            Key::Char('\t') => (Keycode::Tab, Mod::NOMOD),
+            Key::Char('\x08' | '\x7f') => (Keycode::Backspace, Mod::NOMOD),
+            // Ctrl and a letter arrive as the control character of that letter
+            Key::Char(c @ '\x01'..='\x1a') => (keycode((c as u8 - 1 + b'a') as char)?, Mod::LCTRLMOD),
+            Key::Char(c) if c.is_ascii_uppercase() => (keycode(c.to_ascii_lowercase())?, Mod::LSHIFTMOD),
+            Key::Char(c) if c.is_ascii_graphic() || c == ' ' => (keycode(c)?, Mod::NOMOD),
+            Key::Up => (Keycode::Up, Mod::NOMOD),
+            Key::Down => (Keycode::Down, Mod::NOMOD),
+            Key::Left => (Keycode::Left, Mod::NOMOD),
+            Key::Right => (Keycode::Right, Mod::NOMOD),
+            Key::Escape => (Keycode::Escape, Mod::NOMOD),
+            Key::Char(_) | Key::Interrupt => return None,
+        })
+    }
+}
+
+/// `Terminal` keeps the terminal in raw mode on the alternate screen for as long as it lives,
+/// so keys arrive as they are pressed, and leaves it as it was found when dropped.
+pub struct Terminal {
+    original: libc::termios,
+}
+
+impl Terminal {
+    pub fn enter() -> Result<Self> {
+        let mut original = unsafe { std::mem::zeroed::<libc::termios>() };
+        let interactive = unsafe {
+            libc::isatty(libc::STDIN_FILENO) == 1
+                && libc::isatty(libc::STDOUT_FILENO) == 1
+                && libc::tcgetattr(libc::STDIN_FILENO, &mut original) == 0
+        };
+        if !interactive {
+            bail!("The terminal frontend needs stdin and stdout to be a terminal");
+        }
+        let mut raw = original;
+        unsafe { libc::cfmakeraw(&mut raw) };
+        if unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &raw) } != 0 {
+            bail!("Cannot put the terminal into raw mode: {}", std::io::Error::last_os_error());
+        }
+        let terminal = Terminal { original };
+        // switch to the alternate screen, hide the cursor and clear the screen
+        terminal.write("\x1b[?1049h\x1b[?25l\x1b[2J")?;
+        Ok(terminal)
+    }
+
+    /// The width and height of the terminal in characters.
+    pub fn size(&self) -> (usize, usize) {
+        let mut size = unsafe { std::mem::zeroed::<libc::winsize>() };
+        match unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) } {
+            0 if size.ws_col > 0 && size.ws_row > 0 => (size.ws_col as usize, size.ws_row as usize),
+            _ => FALLBACK_SIZE,
+        }
+    }
+
+    /// Draw a frame of ANSI escape codes, with `status` in reverse video across the last row if there is one.
+    pub fn draw(&self, frame: &str, status: Option<&str>) -> Result<()> {
+        let mut ansi = frame.to_owned();
+        if let Some(status) = status {
+            let (columns, rows) = self.size();
+            let status = status.chars().take(columns).collect::<String>();
+            let _ = write!(ansi, "\x1b[{};1H\x1b[0;7m{:<columns$}\x1b[0m", rows, status, columns = columns);
+        }
+        self.write(&ansi)
+    }
+
+    fn write(&self, ansi: &str) -> Result<()> {
+        let mut stdout = std::io::stdout().lock();
+        stdout.write_all(ansi.as_bytes())?;
+        stdout.flush()?;
+        Ok(())
+    }
+}
+
+impl Drop for Terminal {
+    fn drop(&mut self) {
+        let _ = self.write("\x1b[0m\x1b[?25h\x1b[?1049l");
+        unsafe { libc::tcsetattr(libc::STDIN_FILENO, libc::TCSANOW, &self.original) };
+    }
+}
+
+/// Read the keys pressed in the terminal on a thread of their own, as reading stdin blocks.
+/// The thread is left waiting on stdin once the keys are no longer wanted, and ends with the process.
+pub fn read_keys() -> Receiver<Key> {
+    let (keys_tx, keys_rx) = flume::unbounded();
+    std::thread::spawn(move || {
+        let mut stdin = std::io::stdin().lock();
+        let mut buffer = [0_u8; 64];
+        while let Ok(read @ 1..) = stdin.read(&mut buffer) {
+            for key in parse_keys(&buffer[..read]) {
+                if keys_tx.send(key).is_err() {
+                    return
+                }
+            }
+        }
+    });
+    keys_rx
+}
+
+/// Split the bytes read from the terminal in one go into keys.
+/// An escape on its own is the Escape key, while the escape sequences of keys with no use here are left out.
+pub fn parse_keys(bytes: &[u8]) -> Vec<Key> {
+    let text = String::from_utf8_lossy(bytes);
+    let mut chars = text.chars().peekable();
+    let mut keys = Vec::new();
+    while let Some(c) = chars.next() {
+        let key = match c {
+            '\x1b' => match chars.peek() {
+                Some('[' | 'O') => {
+                    chars.next();
+                    // the parameters of the sequence run up to a final character between @ and ~
+                    match chars.by_ref().find(|c| ('@'..='~').contains(c)) {
+                        Some('A') => Key::Up,
+                        Some('B') => Key::Down,
+                        Some('C') => Key::Right,
+                        Some('D') => Key::Left,
+                        _ => continue,
+                    }
+                },
+                _ => Key::Escape,
+            },
+            '\x03' => Key::Interrupt,
+            c => Key::Char(c),
+        };
+        keys.push(key);
+    }
+    keys
+}
@@ -1,10 +1,12 @@
 use crate::util::traits::AsBytes;
 use std::fmt::Display;
+use std::str::FromStr;
 use bytemuck::NoUninit;
 use num_traits::PrimInt;
+use serde::{Deserialize, Serialize};
 
 /// CellCoord (Cell coordinate) represents the coordinate of a cell in the world.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub struct CellCoord<T = usize>
     where T: PrimInt
 {
@@ -27,6 +29,17 @@ impl<T> Display for CellCoord<T>
     }
 }
 
+impl FromStr for CellCoord {
+    type Err = String;
+
+    /// Parse a coordinate written as `x,y`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (x, y) = s.split_once(',').ok_or_else(|| format!("Cell {} should look like 3,4", s))?;
+        let parse = |n: &str| n.trim().parse::<usize>().map_err(|e| format!("Invalid coordinate '{}' in cell {}: {}", n, s, e));
+        Ok(CellCoord::new(parse(x)?, parse(y)?))
+    }
+}
+
 /// CellValue (Cell value) represents the value or status of a cell.
 /// It should be either `Dead` (0_u8) or `Alive` (255_u8).
 /// ## Examples
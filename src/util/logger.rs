@@ -1,13 +1,33 @@
+use anyhow::Result;
+use env_logger::Target;
 use log::Level;
+use std::fs::{create_dir_all, File};
+use std::path::Path;
 
 pub fn init(level: Level, backtrace: bool) {
+    // Logs always go to stderr, so stdout can carry boards in a pipeline.
+    init_with_target(level, backtrace, Target::Stderr);
+}
+
+/// Log to the file at `path` instead of stderr, for when the terminal is taken by the terminal frontend.
+pub fn init_to_file(level: Level, backtrace: bool, path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir)?;
+    }
+    init_with_target(level, backtrace, Target::Pipe(Box::new(File::create(path)?)));
+    Ok(())
+}
+
+fn init_with_target(level: Level, backtrace: bool, target: Target) {
     let level = std::env::var("RUST_LOG")
         .unwrap_or(level.to_string());
     let backtrace = std::env::var("RUST_BACKTRACE")
         .unwrap_or(if backtrace { "1".to_string() } else { "0".to_string() });
     std::env::set_var("RUST_LOG", &level);
     std::env::set_var("RUST_BACKTRACE", &backtrace);
-    let _ = env_logger::try_init();
+    let _ = env_logger::Builder::from_default_env()
+        .target(target)
+        .try_init();
 }
 
 pub fn set_panic_hook() {
@@ -0,0 +1,176 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use gol_rs::args::Args;
+use gol_rs::gol::{self, bus::{EventBus, Policy}, event::{Event, State}, Params};
+use gol_rs::util::{cell::CellCoord, logger};
+use log::Level;
+use sdl2::keyboard::Keycode;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+
+mod utils;
+
+#[tokio::main]
+async fn main() {
+    let start = std::time::Instant::now();
+    logger::set_panic_hook();
+    logger::init(Level::Debug, false);
+
+    let passed_tests = test_bus(Args::default().threads(1)).await.unwrap() + test_coalesced_frame().await.unwrap();
+
+    println!(
+        "\ntest result: {}. {} passed; finished in {:.2}s\n",
+        "ok".green(),
+        passed_tests,
+        start.elapsed().as_secs_f32()
+    );
+    std::process::exit(0);
+}
+
+/// Bus test runs a 64x64 image for 100 turns with three subscribers on the bus: one that blocks,
+/// one that drops the oldest events and is only read at the end, and one that coalesces and reads slowly.
+/// The blocking subscriber should see exactly the events of a run without the bus, without waiting for the others.
+async fn test_bus(args: Args) -> Result<usize> {
+    let args = args.turns(100).image_width(64).image_height(64);
+    log::debug!(target: "Test", "{} - {:?}", "Testing Event Bus".cyan(), Params::from(args.clone()));
+    let (_key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+    tokio::spawn(gol::run(args.clone(), events_tx, key_presses_rx));
+    let mut expected = Vec::new();
+    while let Ok(event) = events_rx.recv_async().await {
+        expected.push(event);
+    }
+
+    let mut bus = EventBus::new();
+    let blocking = bus.subscribe(Policy::Block);
+    let dropping = bus.subscribe(Policy::DropOldest(8));
+    let coalescing = bus.subscribe(Policy::Coalesce);
+    let (_key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    tokio::spawn(gol::run(args.clone(), bus.sender(), key_presses_rx));
+    tokio::spawn(bus.run());
+
+    // a slow subscriber, that takes 20ms over every event it is handed
+    let slow = tokio::spawn(async move {
+        let (mut alive, mut received) = (HashSet::<CellCoord>::new(), Vec::new());
+        while let Ok(event) = coalescing.recv_async().await {
+            if let Event::CellsFlipped { cells, .. } = &event {
+                cells.iter().for_each(|cell| if !alive.remove(cell) { alive.insert(*cell); });
+            }
+            received.push(event);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        (alive, received)
+    });
+
+    let started = Instant::now();
+    let mut received = Vec::new();
+    while let Ok(event) = blocking.recv_async().await {
+        received.push(event);
+    }
+    let blocked_for = started.elapsed();
+    assert_eq!(
+        format!("{:?}", received), format!("{:?}", expected),
+        "Expected the blocking subscriber to receive every event"
+    );
+    log::debug!(target: "Test", "{}", "The blocking subscriber received every event".cyan());
+
+    let kept = dropping.drain().collect::<Vec<Event>>();
+    assert_eq!(
+        format!("{:?}", kept), format!("{:?}", &expected[expected.len() - 8..]),
+        "Expected the dropping subscriber to keep the 8 newest events"
+    );
+    log::debug!(target: "Test", "{}", "The dropping subscriber kept the newest events".cyan());
+
+    let (alive, coalesced) = timeout(Duration::from_secs(30), slow).await
+        .context("The coalescing subscriber did not finish")??;
+    assert!(
+        blocked_for < Duration::from_millis(20) * expected.len() as u32 / 2,
+        "Expected the simulation not to wait for the slow subscriber, but it took {:?}", blocked_for
+    );
+    assert!(coalesced.len() < expected.len(), "Expected the flips of a slow subscriber to be coalesced");
+    let final_alive = expected.iter()
+        .find_map(|event| match event {
+            Event::FinalTurnComplete { alive, .. } => Some(alive.iter().copied().collect::<HashSet<CellCoord>>()),
+            _ => None,
+        })
+        .context("No FinalTurnComplete event was sent")?;
+    assert_eq!(alive, final_alive, "Expected the coalesced flips to reach the final board");
+    assert!(
+        matches!(coalesced.last(), Some(Event::StateChange { new_state: State::Quitting, .. })),
+        "Expected the coalescing subscriber to receive the last event"
+    );
+    log::debug!(target: "Test", "{}", "The coalescing subscriber reached the final board".cyan());
+
+    Ok(1)
+}
+
+/// Coalesced frame test publishes a blinker on a 5x5 board for 2 turns to a coalescing subscriber that
+/// reads only once both turns are queued, expecting them to arrive as a single frame that lists every
+/// flip of the blinker and leaves the cells alive as following the turns one by one would.
+async fn test_coalesced_frame() -> Result<usize> {
+    log::debug!(target: "Test", "{}", "Testing Coalesced Frame".cyan());
+    let cell = CellCoord::new;
+    let flips = vec![cell(2, 1), cell(1, 2), cell(3, 2), cell(2, 3)];
+    let loaded = [
+        Event::StateChange { completed_turns: 0, new_state: State::Executing },
+        Event::CellsFlipped { completed_turns: 0, cells: vec![cell(2, 1), cell(2, 2), cell(2, 3)] },
+    ];
+    let turns = [
+        Event::CellsFlipped { completed_turns: 1, cells: flips.clone() },
+        Event::TurnComplete { completed_turns: 1 },
+        Event::CellsFlipped { completed_turns: 2, cells: flips },
+        Event::TurnComplete { completed_turns: 2 },
+    ];
+
+    let mut bus = EventBus::new();
+    let coalescing = bus.subscribe(Policy::Coalesce);
+    let events_tx = bus.sender();
+    tokio::spawn(bus.run());
+    for event in loaded.iter().chain(&turns) {
+        events_tx.send_async(event.clone()).await?;
+        // the loaded board is handed over before the turns, which then wait in the mailbox together
+        if let Event::CellsFlipped { completed_turns: 0, .. } = event {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+    drop(events_tx);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let coalesced = timeout(Duration::from_secs(5), async {
+        let mut events = Vec::new();
+        while let Ok(event) = coalescing.recv_async().await {
+            events.push(event);
+        }
+        events
+    }).await.context("The coalescing subscriber did not finish")?;
+    assert_eq!(
+        coalesced.iter().filter(|event| matches!(event, Event::TurnComplete { .. })).count(), 1,
+        "Expected both turns in a single frame, but got {:?}", coalesced
+    );
+
+    let follow = |events: &[Event]| {
+        let mut alive = HashSet::new();
+        for event in events {
+            if let Event::CellsFlipped { cells, .. } = event {
+                cells.iter().for_each(|cell| if !alive.remove(cell) { alive.insert(*cell); });
+            }
+        }
+        alive
+    };
+    let expected = follow(&loaded.iter().chain(&turns).cloned().collect::<Vec<Event>>());
+    assert_eq!(follow(&coalesced), expected, "Expected the blinker back where it started");
+    let flips = coalesced.iter()
+        .filter_map(|event| match event {
+            Event::CellsFlipped { completed_turns, cells } if *completed_turns > 0 => Some(cells.len()),
+            _ => None,
+        })
+        .sum::<usize>();
+    assert_eq!(flips, 8, "Expected every flip of the blinker in the frame, but got {:?}", coalesced);
+    assert!(
+        matches!(coalesced.last(), Some(Event::TurnComplete { completed_turns: 2 })),
+        "Expected the frame to end with the last turn, but got {:?}", coalesced
+    );
+    log::debug!(target: "Test", "{}", "The coalesced frame keeps the history of the blinker".cyan());
+
+    Ok(1)
+}
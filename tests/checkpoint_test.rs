@@ -0,0 +1,187 @@
+use anyhow::Result;
+use colored::Colorize;
+use gol_rs::args::Args;
+use gol_rs::gol::{Params, self, event::{Event, State}};
+use gol_rs::gol::checkpoint::{Checkpoint, CheckpointHeader, CHECKPOINT_VERSION};
+use gol_rs::gol::{rule::Rule, topology::Topology};
+use gol_rs::util::{cell::{CellCoord, CellValue}, logger};
+use std::sync::Arc;
+use log::Level;
+use sdl2::keyboard::Keycode;
+use utils::{io::read_alive_cells, visualise::assert_eq_board};
+
+mod utils;
+
+#[tokio::main]
+async fn main() {
+    let start = std::time::Instant::now();
+    logger::set_panic_hook();
+    logger::init(Level::Debug, false);
+
+    let passed_tests = test_checkpoint(Args::default().threads(1)).await.unwrap()
+        + test_failed_checkpoint(Args::default().threads(1)).await.unwrap()
+        + test_bad_checksum().unwrap()
+        + test_unknown_version(Args::default()).await.unwrap()
+        + test_rule(Args::default()).await.unwrap();
+
+    println!(
+        "\ntest result: {}. {} passed; finished in {:.2}s\n",
+        "ok".green(),
+        passed_tests,
+        start.elapsed().as_secs_f32()
+    );
+    std::process::exit(0);
+}
+
+/// Checkpoint tests run 16x16, 64x64 and 512x512 images for 50 turns, write a checkpoint,
+/// and resume from it for another 50 turns, which should match the images after 100 turns.
+async fn test_checkpoint(args: Args) -> Result<usize> {
+    let mut passed_tests = 0;
+    let size = [(16_usize, 16_usize), (64, 64), (512, 512)];
+
+    for (width, height) in size {
+        let path = format!("check/images/{}x{}x100.pgm", width, height);
+        let expected_alive = read_alive_cells(path, width, height).unwrap();
+        let checkpoint = format!("out/{}x{}.ckpt", width, height);
+
+        let args = args.clone()
+            .turns(50)
+            .image_width(width)
+            .image_height(height)
+            .checkpoint(&checkpoint);
+        log::debug!(target: "Test", "{} - {:?}", "Testing Checkpoint".cyan(), Params::from(args.clone()));
+        let (completed_turns, _) = run(args.clone()).await;
+        assert_eq!(completed_turns, 50, "Expected completed turns is 50, but got {}", completed_turns);
+
+        let args = Args::default()
+            .threads(args.threads)
+            .turns(100)
+            .resume(&checkpoint);
+        log::debug!(target: "Test", "{} - {:?}", "Testing Resume".cyan(), Params::from(args.clone()));
+        let (completed_turns, alive) = run(args.clone()).await;
+        assert_eq!(completed_turns, 100, "Expected completed turns is 100, but got {}", completed_turns);
+        let args = args.image_width(width).image_height(height);
+        assert_eq_board(args.clone(), &alive, &expected_alive);
+
+        let output = read_alive_cells(format!("out/{}x{}x100.pgm", width, height), width, height).unwrap();
+        assert_eq_board(args, &output, &expected_alive);
+        passed_tests += 1;
+    }
+    Ok(passed_tests)
+}
+
+/// Failed checkpoint tests save a checkpoint every turn where none can be written,
+/// which should not stop the run from completing all 100 turns and writing its output.
+async fn test_failed_checkpoint(args: Args) -> Result<usize> {
+    let (width, height) = (16, 16);
+    let expected_alive = read_alive_cells(format!("check/images/{}x{}x100.pgm", width, height), width, height).unwrap();
+
+    // the directory of the checkpoint is a file, so it cannot be created
+    let args = args
+        .turns(100)
+        .image_width(width)
+        .image_height(height)
+        .checkpoint("Cargo.toml/16x16.ckpt")
+        .checkpoint_interval(0);
+    log::debug!(target: "Test", "{} - {:?}", "Testing Failed Checkpoint".cyan(), Params::from(args.clone()));
+    let (completed_turns, alive) = run(args.clone()).await;
+    assert_eq!(completed_turns, 100, "Expected completed turns is 100, but got {}", completed_turns);
+    assert_eq_board(args.clone(), &alive, &expected_alive);
+
+    let output = read_alive_cells(format!("out/{}x{}x100.pgm", width, height), width, height).unwrap();
+    assert_eq_board(args, &output, &expected_alive);
+    Ok(1)
+}
+
+/// Bad checksum test flips one byte of the board in a checkpoint, which should be rejected as corrupted.
+fn test_bad_checksum() -> Result<usize> {
+    log::debug!(target: "Test", "{}", "Testing Bad Checksum".cyan());
+    let mut bytes = glider_checkpoint().to_bytes()?;
+    assert!(Checkpoint::from_bytes(&bytes).is_ok(), "Expected the checkpoint to be read back");
+    let i = bytes.len() - 8;
+    bytes[i] ^= 1;
+    let error = Checkpoint::from_bytes(&bytes).expect_err("Expected a corrupted checkpoint to be rejected");
+    assert!(error.to_string().contains("checksum"), "Expected the checksum to be blamed, but got: {}", error);
+    Ok(1)
+}
+
+/// Unknown version test writes a checkpoint of a later version with a valid checksum,
+/// which should be rejected both when it is read and when a run is resumed from it.
+async fn test_unknown_version(args: Args) -> Result<usize> {
+    log::debug!(target: "Test", "{}", "Testing Unknown Version".cyan());
+    let mut bytes = glider_checkpoint().to_bytes()?;
+    bytes[8..10].copy_from_slice(&(CHECKPOINT_VERSION + 1).to_le_bytes());
+    let content = bytes.len() - 4;
+    let checksum = crc32fast::hash(&bytes[..content]);
+    bytes[content..].copy_from_slice(&checksum.to_le_bytes());
+
+    let error = Checkpoint::from_bytes(&bytes).expect_err("Expected a checkpoint of an unknown version to be rejected");
+    assert!(error.to_string().contains("version"), "Expected the version to be blamed, but got: {}", error);
+
+    let path = "out/unknown-version.ckpt";
+    std::fs::create_dir_all("out")?;
+    std::fs::write(path, &bytes)?;
+    let error = Params::from(args.resume(path)).resolve().await
+        .expect_err("Expected resuming from a checkpoint of an unknown version to fail");
+    assert!(format!("{:#}", error).contains("version"), "Expected the version to be blamed, but got: {:#}", error);
+    Ok(1)
+}
+
+/// Rule test writes a checkpoint of a HighLife run, which should read back with its rule,
+/// and should only be resumed by a run of the same rule.
+async fn test_rule(args: Args) -> Result<usize> {
+    log::debug!(target: "Test", "{}", "Testing Rule".cyan());
+    let high_life = "B36/S23".parse::<Rule>().map_err(anyhow::Error::msg)?;
+    let mut checkpoint = glider_checkpoint();
+    checkpoint.header.rule = high_life;
+    let bytes = checkpoint.to_bytes()?;
+    let read = Checkpoint::from_bytes(&bytes)?;
+    assert_eq!(read.header, checkpoint.header, "Expected the header to be read back");
+    assert_eq!(read.world, checkpoint.world, "Expected the world to be read back");
+
+    let path = "out/high-life.ckpt";
+    std::fs::create_dir_all("out")?;
+    std::fs::write(path, &bytes)?;
+    let params = Params::from(args.resume(path));
+    let error = params.clone().resolve().await
+        .expect_err("Expected resuming a HighLife checkpoint with Conway's rule to fail");
+    assert!(format!("{:#}", error).contains("B36/S23"), "Expected the rule to be blamed, but got: {:#}", error);
+    let params = Params { rule: high_life, ..params }.resolve().await?;
+    assert_eq!((params.image_width, params.image_height), (16, 16));
+    Ok(1)
+}
+
+/// A checkpoint of a glider in a 16x16 world after 10 turns of Conway's Game of Life.
+fn glider_checkpoint() -> Checkpoint {
+    let mut world = vec![CellValue::Dead; 16 * 16];
+    for (x, y) in [(1, 0), (2, 1), (0, 2), (1, 2), (2, 2)] {
+        world[y * 16 + x] = CellValue::Alive;
+    }
+    Checkpoint {
+        header: CheckpointHeader {
+            image_width: 16,
+            image_height: 16,
+            completed_turns: 10,
+            rule: Rule::default(),
+            topology: Topology::Torus,
+        },
+        world: Arc::new(world),
+    }
+}
+
+async fn run(args: Args) -> (u32, Vec<CellCoord>) {
+    let (_key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+    tokio::spawn(gol::run(args.clone(), events_tx, key_presses_rx));
+    let mut final_turn = None;
+    loop {
+        match events_rx.recv_async().await {
+            Ok(Event::FinalTurnComplete { completed_turns, alive }) =>
+                final_turn = Some((completed_turns, alive)),
+            Ok(Event::StateChange { new_state: State::Quitting, .. }) if final_turn.is_some() => break,
+            Err(_) => panic!("No FinalTurnComplete events received {:?}", Params::from(args)),
+            _ => (),
+        }
+    }
+    final_turn.unwrap()
+}
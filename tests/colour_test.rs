@@ -0,0 +1,172 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use gol_rs::args::Args;
+use gol_rs::gol::{bus::{EventBus, Policy}, event::{Event, State}};
+use gol_rs::sdl::colour::{CellHistory, ColourMode, ColourOptions, Gradient, ALIVE, DEAD};
+use gol_rs::util::{cell::CellCoord, logger};
+use log::Level;
+use std::time::Duration;
+use tokio::time::timeout;
+
+#[tokio::main]
+async fn main() {
+    let start = std::time::Instant::now();
+    logger::set_panic_hook();
+    logger::init(Level::Debug, false);
+
+    let passed_tests = test_gradients() + test_age() + test_activity() + test_coalesced_history().await.unwrap();
+
+    println!(
+        "\ntest result: {}. {} passed; finished in {:.2}s\n",
+        "ok".green(),
+        passed_tests,
+        start.elapsed().as_secs_f32()
+    );
+    std::process::exit(0);
+}
+
+/// Gradient test expects every palette to run from its first colour to its last, passing smoothly between them.
+fn test_gradients() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Gradients".cyan());
+    assert_eq!(Gradient::Grey.at(0.0), 0xFF404040, "Expected the grey palette to start dark grey");
+    assert_eq!(Gradient::Grey.at(0.5), 0xFFA0A0A0, "Expected the grey palette to pass through light grey");
+    assert_eq!(Gradient::Grey.at(1.0), 0xFFFFFFFF, "Expected the grey palette to end white");
+    assert_eq!(Gradient::Heat.at(-1.0), Gradient::Heat.at(0.0), "Expected values below 0 to clamp");
+    assert_eq!(Gradient::Heat.at(2.0), 0xFFFFFFFF, "Expected values above 1 to clamp");
+    1
+}
+
+/// Age test expects cells to be coloured by the turns since they were born once their age is followed,
+/// and dead cells to stay black.
+fn test_age() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Age Colouring".cyan());
+    let options = ColourOptions { mode: ColourMode::Age, age_palette: Gradient::Grey, ..ColourOptions::from(&Args::default()) };
+    let mut history = CellHistory::new(3);
+    history.flip(0);
+    assert_eq!(history.colour(0, &options), ALIVE, "Expected cells to be coloured plainly until their age is followed");
+    history.follow(options.mode);
+    history.complete_turn(990, options.activity_window);
+    history.flip(1);
+    history.complete_turn(1000, options.activity_window);
+    history.flip(2);
+
+    assert_eq!(history.colour(0, &options), Gradient::Grey.at(1.0), "Expected the oldest cell at the end of the palette");
+    assert!(
+        history.colour(1, &options) < history.colour(0, &options),
+        "Expected a younger cell to be earlier in the palette"
+    );
+    assert_eq!(history.colour(2, &options), Gradient::Grey.at(0.0), "Expected a newborn cell at the start of the palette");
+    history.flip(2);
+    assert_eq!(history.colour(2, &options), DEAD, "Expected a dead cell to be black");
+    assert_eq!(
+        history.colour(0, &ColourOptions { mode: ColourMode::Plain, ..options }), ALIVE,
+        "Expected a plain alive cell to be white"
+    );
+    1
+}
+
+/// Activity test flips a blinker cell every turn next to a still cell, expecting the blinker to
+/// reach the end of the palette while the still cell fades to its start once the window passes.
+fn test_activity() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Activity Colouring".cyan());
+    let options = ColourOptions {
+        mode: ColourMode::Activity,
+        activity_palette: Gradient::Grey,
+        activity_window: 4,
+        ..ColourOptions::from(&Args::default())
+    };
+    let mut history = CellHistory::new(3);
+    history.follow(options.mode);
+    history.flip(1);
+    for turn in 1..=8 {
+        history.flip(0);
+        history.complete_turn(turn, options.activity_window);
+    }
+    assert_eq!(history.colour(1, &options), Gradient::Grey.at(0.0), "Expected the still cell to have no activity");
+    assert_eq!(
+        history.colour(0, &options), 0xFF555555,
+        "Expected the blinker cell, dead after an even number of flips, to be dimmed"
+    );
+    history.flip(0);
+    assert_eq!(history.colour(0, &options), Gradient::Grey.at(1.0), "Expected the blinker cell to be fully active");
+    assert_eq!(history.colour(2, &options), DEAD, "Expected a cell that never flipped to be black");
+    1
+}
+
+/// Coalesced history test publishes a blinker on a 5x5 board for 2 turns to a coalescing subscriber of the
+/// event bus that reads only once both turns are queued, expecting the single frame they arrive in to leave
+/// the cells alive, active and aged as following the turns one by one would.
+async fn test_coalesced_history() -> Result<usize> {
+    log::debug!(target: "Test", "{}", "Testing Coalesced History".cyan());
+    let cell = CellCoord::new;
+    let flips = vec![cell(2, 1), cell(1, 2), cell(3, 2), cell(2, 3)];
+    let loaded = [
+        Event::StateChange { completed_turns: 0, new_state: State::Executing },
+        Event::CellsFlipped { completed_turns: 0, cells: vec![cell(2, 1), cell(2, 2), cell(2, 3)] },
+    ];
+    let turns = [
+        Event::CellsFlipped { completed_turns: 1, cells: flips.clone() },
+        Event::TurnComplete { completed_turns: 1 },
+        Event::CellsFlipped { completed_turns: 2, cells: flips },
+        Event::TurnComplete { completed_turns: 2 },
+    ];
+
+    let mut bus = EventBus::new();
+    let coalescing = bus.subscribe(Policy::Coalesce);
+    let events_tx = bus.sender();
+    tokio::spawn(bus.run());
+    for event in loaded.iter().chain(&turns) {
+        events_tx.send_async(event.clone()).await?;
+        // the loaded board is handed over before the turns, which then wait in the mailbox together
+        if let Event::CellsFlipped { completed_turns: 0, .. } = event {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    }
+    drop(events_tx);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    let coalesced = timeout(Duration::from_secs(5), async {
+        let mut events = Vec::new();
+        while let Ok(event) = coalescing.recv_async().await {
+            events.push(event);
+        }
+        events
+    }).await.context("The coalescing subscriber did not finish")?;
+    assert_eq!(
+        coalesced.iter().filter(|event| matches!(event, Event::TurnComplete { .. })).count(), 1,
+        "Expected both turns in a single frame, but got {:?}", coalesced
+    );
+
+    let options = ColourOptions::default();
+    let follow = |events: &[Event]| {
+        let mut history = CellHistory::new(5 * 5);
+        history.follow(ColourMode::Age);
+        for event in events {
+            match event {
+                Event::CellsFlipped { cells, .. } => cells.iter().for_each(|cell| history.flip(cell.y * 5 + cell.x)),
+                Event::TurnComplete { completed_turns } => history.complete_turn(*completed_turns, options.activity_window),
+                _ => (),
+            }
+        }
+        history
+    };
+    let expected = follow(&loaded.iter().chain(&turns).cloned().collect::<Vec<Event>>());
+    let history = follow(&coalesced);
+    assert_eq!(history.alive(), expected.alive(), "Expected the blinker back where it started");
+    for mode in [ColourMode::Age, ColourMode::Activity] {
+        let options = ColourOptions { mode, ..options };
+        for i in 0..5 * 5 {
+            assert_eq!(
+                history.colour(i, &options), expected.colour(i, &options),
+                "Expected cell {} to be coloured by {} as if the turns were followed one by one", i, mode
+            );
+        }
+    }
+    let activity = ColourOptions { mode: ColourMode::Activity, ..options };
+    assert_ne!(history.colour(2 * 5 + 1, &activity), DEAD, "Expected the ends of the blinker to be active");
+    let age = ColourOptions { mode: ColourMode::Age, ..options };
+    assert_ne!(
+        history.colour(5 + 2, &age), history.colour(2 * 5 + 2, &age),
+        "Expected the reborn end of the blinker to be younger than its middle"
+    );
+    Ok(1)
+}
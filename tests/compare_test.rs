@@ -0,0 +1,160 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use flume::Receiver;
+use gol_rs::args::Args;
+use gol_rs::gol::{self, edit::Edit, event::{Event, State}, Hooks, Params, World};
+use gol_rs::sdl::{colour::ALIVE, compare::{CompareLayout, DIFFERENT}, offscreen::Offscreen};
+use gol_rs::util::{cell::{CellCoord, CellValue}, logger};
+use log::Level;
+use sdl2::keyboard::Keycode;
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::timeout;
+use utils::{common::step, io::read_alive_cells, visualise::assert_eq_board};
+
+mod utils;
+
+#[tokio::main]
+async fn main() {
+    let start = std::time::Instant::now();
+    logger::set_panic_hook();
+    logger::init(Level::Debug, false);
+
+    let mut passed_tests = test_flip() + test_layout(CompareLayout::SideBySide) + test_layout(CompareLayout::Overlay);
+    for threads in [1, 4] {
+        passed_tests += test_lockstep(Args::default().threads(threads)).await.unwrap();
+    }
+
+    println!(
+        "\ntest result: {}. {} passed; finished in {:.2}s\n",
+        "ok".green(),
+        passed_tests,
+        start.elapsed().as_secs_f32()
+    );
+    std::process::exit(0);
+}
+
+/// Flip test flips a live and a dead cell, expecting both to change and cells outside the world to be left out.
+fn test_flip() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Flip Edit".cyan());
+    let mut world = vec![CellValue::Alive, CellValue::Dead, CellValue::Dead, CellValue::Dead];
+    let edit = Edit::Flip { cells: vec![CellCoord::new(0, 0), CellCoord::new(1, 1), CellCoord::new(2, 0)] };
+    let flipped = edit.apply(&mut world, 2, 2);
+    assert_eq!(flipped, [CellCoord::new(0, 0), CellCoord::new(1, 1)], "Expected the cells inside the world to flip");
+    assert_eq!(world, [CellValue::Dead, CellValue::Dead, CellValue::Dead, CellValue::Alive], "Expected the cells to swap");
+    1
+}
+
+/// Layout test shows two 8x8 worlds that agree on one cell and differ on another,
+/// expecting only the differing cell to be drawn in the highlight colour wherever it is shown.
+fn test_layout(layout: CompareLayout) -> usize {
+    log::debug!(target: "Test", "{} - {}", "Testing Comparison Layout".cyan(), layout);
+    let mut screen = Offscreen::new(8, 8, 160, 80).with_comparison(layout);
+    screen.hud_mut().visible = false;
+    screen.set_cell(1, 1, true);
+    screen.flip_compared_pixel(1, 1);
+    screen.flip_compared_pixel(5, 5);
+    assert_eq!(screen.differing(), Some(1), "Expected the worlds to differ on one cell");
+    screen.render_frame();
+
+    let (view_width, view_height) = screen.view_size();
+    let (mut different, mut alive) = (Vec::new(), Vec::new());
+    for (x, y) in (0..view_height).flat_map(|y| (0..view_width).map(move |x| (x, y))) {
+        let cell = screen.cell_at(x as f64 + 0.5, y as f64 + 0.5);
+        match screen.pixel(x, y) {
+            DIFFERENT => different.push((x, cell)),
+            ALIVE => alive.push((x, cell)),
+            _ => (),
+        }
+    }
+    assert!(different.iter().all(|&(_, cell)| cell == Some(CellCoord::new(5, 5))), "Expected only the differing cell to be highlighted");
+    assert!(alive.iter().all(|&(_, cell)| cell == Some(CellCoord::new(1, 1))), "Expected the agreed cell to be drawn as alive");
+    assert!(!different.is_empty() && !alive.is_empty(), "Expected both cells to be drawn");
+    if layout == CompareLayout::SideBySide {
+        let halves = |cells: &[(u32, Option<CellCoord>)]| (
+            cells.iter().any(|&(x, _)| x < view_width / 2),
+            cells.iter().any(|&(x, _)| x >= view_width / 2),
+        );
+        assert_eq!(halves(&different), (true, true), "Expected the differing cell in both worlds");
+        assert_eq!(halves(&alive), (true, true), "Expected the agreed cell in both worlds");
+    }
+    1
+}
+
+/// Lockstep test runs a 64x64 image next to a copy with one cell flipped, pausing, outputting and quitting both
+/// with single key presses. It expects them to pause on the same turn, with each world as though it had run alone.
+async fn test_lockstep(args: Args) -> Result<usize> {
+    let (width, height) = (64, 64);
+    let args = args.turns(100000000).image_width(width).image_height(height).output_dir("out/compare");
+    log::debug!(target: "Test", "{} - {:?}", "Testing Lockstep".cyan(), Params::from(args.clone()));
+    let flip = CellCoord::new(10, 10);
+    let params = Params::from(args.clone());
+    let compared_params = Params { output_dir: args.output_dir.join("compare"), ..params.clone() };
+    let (key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    let (events_tx, events_rx) = flume::unbounded::<Event>();
+    let (compared_events_tx, compared_events_rx) = flume::unbounded::<Event>();
+    let (flips_tx, flips_rx) = flume::unbounded::<Edit>();
+    flips_tx.send(Edit::Flip { cells: vec![flip] })?;
+    let worlds = vec![
+        World { params, events: events_tx, hooks: Hooks::default() },
+        World { params: compared_params.clone(), events: compared_events_tx, hooks: Hooks { edits: Some(flips_rx), ..Hooks::default() } },
+    ];
+    let running = tokio::spawn(gol::run_many(worlds, key_presses_rx));
+
+    wait_for(&events_rx, |event| matches!(event, Event::TurnComplete { completed_turns: 20.. })).await?;
+    key_presses_tx.send_async(Keycode::P).await?;
+    let paused = wait_for_pause(&events_rx).await?;
+    let compared_paused = wait_for_pause(&compared_events_rx).await?;
+    assert_eq!(paused, compared_paused, "Expected both worlds to pause on the same turn");
+
+    key_presses_tx.send_async(Keycode::S).await?;
+    for events in [&events_rx, &compared_events_rx] {
+        wait_for(events, |event| matches!(event, Event::ImageOutputComplete { .. })).await?;
+    }
+    let filename = format!("{}x{}x{}.pgm", width, height, paused);
+    let initial = read_alive_cells(format!("images/{}x{}.pgm", width, height), width, height)?;
+    let mut flipped = initial.iter().copied().filter(|&cell| cell != flip).collect::<Vec<_>>();
+    if !initial.contains(&flip) {
+        flipped.push(flip);
+    }
+    for (output_dir, initial) in [(&args.output_dir, initial), (&compared_params.output_dir, flipped)] {
+        let alive = read_alive_cells(Path::new(output_dir).join(&filename), width, height)?;
+        let expected = (0..paused).fold(initial, |alive, _| step(&alive, width, height));
+        assert_eq_board(args.clone(), &alive, &expected);
+    }
+
+    key_presses_tx.send_async(Keycode::Q).await?;
+    let quit = wait_for_final_turn(&events_rx).await?;
+    let compared_quit = wait_for_final_turn(&compared_events_rx).await?;
+    assert_eq!((quit, compared_quit), (paused, paused), "Expected both worlds to quit where they paused");
+    timeout(Duration::from_secs(10), running).await.context("The worlds did not stop")???;
+    Ok(1)
+}
+
+/// Wait for the first event that `found` is true of.
+async fn wait_for(events: &Receiver<Event>, found: impl Fn(&Event) -> bool) -> Result<Event> {
+    timeout(Duration::from_secs(10), async {
+        while let Ok(event) = events.recv_async().await {
+            if found(&event) {
+                return Ok(event)
+            }
+        }
+        anyhow::bail!("The simulation stopped first")
+    }).await.context("The event did not come")?
+}
+
+/// Wait for a world to pause, returning the turns it completed.
+async fn wait_for_pause(events: &Receiver<Event>) -> Result<u32> {
+    match wait_for(events, |event| matches!(event, Event::StateChange { new_state: State::Pause, .. })).await? {
+        Event::StateChange { completed_turns, .. } => Ok(completed_turns),
+        _ => unreachable!(),
+    }
+}
+
+/// Wait for a world to finish, returning the turns it completed.
+async fn wait_for_final_turn(events: &Receiver<Event>) -> Result<u32> {
+    match wait_for(events, |event| matches!(event, Event::FinalTurnComplete { .. })).await? {
+        Event::FinalTurnComplete { completed_turns, .. } => Ok(completed_turns),
+        _ => unreachable!(),
+    }
+}
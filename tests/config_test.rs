@@ -0,0 +1,176 @@
+use colored::Colorize;
+use gol_rs::sdl::config::{parse_colour, Action, Config, Key, KeyBindings, Theme};
+use gol_rs::sdl::{help::Help, offscreen::Offscreen, tools::Selection};
+use gol_rs::util::{cell::CellCoord, logger};
+use log::Level;
+use sdl2::keyboard::{Keycode, Mod};
+use std::path::Path;
+
+fn main() {
+    let start = std::time::Instant::now();
+    logger::set_panic_hook();
+    logger::init(Level::Debug, false);
+
+    let passed_tests = test_keys() + test_bindings() + test_config() + test_theme() + test_help();
+
+    println!(
+        "\ntest result: {}. {} passed; finished in {:.2}s\n",
+        "ok".green(),
+        passed_tests,
+        start.elapsed().as_secs_f32()
+    );
+    std::process::exit(0);
+}
+
+/// Keys test parses keys as a config file writes them, expecting them to be written back the same way.
+fn test_keys() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Keys".cyan());
+    let key = |s: &str| s.parse::<Key>().unwrap();
+    assert_eq!(key("Ctrl+C"), Key { keycode: Keycode::C, ctrl: true, shift: false }, "Expected Ctrl and a letter");
+    assert_eq!(key("shift+/"), Key { keycode: Keycode::Slash, ctrl: false, shift: true }, "Expected Shift and a symbol");
+    assert_eq!(key("cmd+escape"), key("Ctrl+Escape"), "Expected names in any case, with Cmd standing for Ctrl");
+    assert_eq!(key("+").keycode, Keycode::Plus, "Expected + on its own to be a key");
+    assert_eq!(key("Ctrl++"), Key { keycode: Keycode::Plus, ctrl: true, shift: false }, "Expected + after a modifier");
+    for written in ["Ctrl+C", "Shift+/", "?", "[", "1", "Escape", "F5", "PageUp", "Ctrl+Shift+Delete"] {
+        assert_eq!(key(written).to_string(), written, "Expected {} to be written back the same way", written);
+    }
+    for invalid in ["", "Alt+X", "Foo", "Ctrl+", "é"] {
+        assert!(invalid.parse::<Key>().is_err(), "Expected {:?} not to be a key", invalid);
+    }
+    1
+}
+
+/// Bindings test looks up the default keys, expecting Ctrl to tell the tools from the other actions
+/// and `?` to be found whether it comes as its own key or as Shift and /.
+fn test_bindings() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Key Bindings".cyan());
+    let keys = KeyBindings::default();
+    let shift = Mod::LSHIFTMOD;
+    assert!(keys.check().is_ok(), "Expected no key to be bound twice by default");
+    assert_eq!(keys.action(Keycode::P, Mod::NOMOD), Some(Action::Pause), "Expected P to pause");
+    assert_eq!(keys.action(Keycode::P, shift), Some(Action::Pause), "Expected P to pause with Shift held");
+    assert_eq!(keys.action(Keycode::C, Mod::NOMOD), Some(Action::ColourMode), "Expected C to change the colours");
+    assert_eq!(keys.action(Keycode::C, Mod::RCTRLMOD), Some(Action::Copy), "Expected Ctrl+C to copy");
+    assert_eq!(keys.action(Keycode::C, Mod::LGUIMOD), Some(Action::Copy), "Expected Cmd+C to copy");
+    assert_eq!(keys.action(Keycode::Slash, shift), Some(Action::Help), "Expected Shift+/ to show the help");
+    assert_eq!(keys.action(Keycode::Question, shift), Some(Action::Help), "Expected ? to show the help");
+    assert_eq!(keys.action(Keycode::Slash, Mod::NOMOD), None, "Expected / alone to do nothing");
+    assert_eq!(keys.action(Keycode::Escape, Mod::NOMOD), Some(Action::Quit), "Expected Escape to quit");
+    assert!(
+        Action::ALL.iter().all(|&action| !keys.keys(action).is_empty()),
+        "Expected every action to have a key by default"
+    );
+    assert_eq!(
+        Action::ALL.iter().filter(|action| action.tool().is_some()).count(), 12,
+        "Expected the selection tools to be actions"
+    );
+    1
+}
+
+/// Config test reads config files, expecting the keys they list to replace the defaults of only those actions,
+/// and files binding a key twice, with badly written colours or with unknown settings to be refused.
+fn test_config() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Config File".cyan());
+    let dir = Path::new("out/config");
+    std::fs::create_dir_all(dir).unwrap();
+    let load = |name: &str, json: &str| {
+        let path = dir.join(name);
+        std::fs::write(&path, json).unwrap();
+        Config::load(&path)
+    };
+
+    let config = load("config.json", r##"{
+        "keys": { "pause": ["Space", "Shift+P"], "help": ["F1"], "kill": [] },
+        "theme": { "alive": "#00FF80", "grid": "#202020" }
+    }"##).unwrap();
+    assert_eq!(config.keys.action(Keycode::Space, Mod::NOMOD), Some(Action::Pause), "Expected Space to pause");
+    assert_eq!(config.keys.action(Keycode::P, Mod::NOMOD), None, "Expected P alone to be unbound");
+    assert_eq!(config.keys.action(Keycode::F1, Mod::NOMOD), Some(Action::Help), "Expected F1 to show the help");
+    assert_eq!(config.keys.action(Keycode::K, Mod::NOMOD), None, "Expected an action to be unbound");
+    assert_eq!(config.keys.action(Keycode::Q, Mod::NOMOD), Some(Action::Quit), "Expected Q to keep quitting");
+    assert_eq!(
+        config.theme, Theme { alive: 0xFF_00_FF_80, grid: Some(0xFF_20_20_20), ..Theme::default() },
+        "Expected the colours given to replace the defaults"
+    );
+    assert_eq!(load("empty.json", "{}").unwrap(), Config::default(), "Expected both sections to be optional");
+
+    assert!(load("twice.json", r#"{ "keys": { "fit": ["C"] } }"#).is_err(), "Expected C to be bound twice");
+    assert!(load("action.json", r#"{ "keys": { "jump": ["J"] } }"#).is_err(), "Expected an unknown action");
+    assert!(load("key.json", r#"{ "keys": { "fit": ["Hyper+F"] } }"#).is_err(), "Expected an unknown key");
+    assert!(load("colour.json", r#"{ "theme": { "dead": "black" } }"#).is_err(), "Expected a badly written colour");
+    assert!(load("setting.json", r##"{ "theme": { "text": "#FFFFFF" } }"##).is_err(), "Expected an unknown colour");
+    assert!(Config::load(dir.join("missing.json")).is_err(), "Expected a missing file");
+    assert_eq!(parse_colour("#1a2B3c").unwrap(), 0xFF_1A_2B_3C, "Expected hex digits in any case");
+    1
+}
+
+/// Theme test draws an 8x8 world zoomed to 8 pixels per cell in the colours of a theme,
+/// expecting the cells, the grid between them and the selection outline to be drawn in those colours.
+fn test_theme() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Colour Theme".cyan());
+    let theme = Theme {
+        alive: 0xFF_00_FF_00,
+        dead: 0xFF_00_00_40,
+        grid: Some(0xFF_30_30_30),
+        selection: 0xFF_FF_00_00,
+        overlay: 0xFF_00_FF_FF,
+    };
+    let mut screen = Offscreen::new(8, 8, 64, 64).with_theme(theme);
+    screen.hud_mut().visible = false;
+    screen.set_cell(1, 1, true);
+    screen.set_selection(Some(Selection::between(CellCoord::new(4, 4), CellCoord::new(5, 5))));
+    screen.render_frame();
+    assert_eq!(screen.pixel(12, 12), theme.alive, "Expected an alive cell in the theme's colour");
+    assert_eq!(screen.pixel(28, 12), theme.dead, "Expected a dead cell in the theme's colour");
+    assert_eq!(screen.pixel(8, 12), theme.grid.unwrap(), "Expected the grid along the left of the cell");
+    assert_eq!(screen.pixel(12, 16), theme.grid.unwrap(), "Expected the grid along the top of the next cell");
+    assert_eq!(screen.pixel(40, 32), theme.selection, "Expected the selection over the grid");
+
+    screen.set_theme(Theme { grid: None, ..theme });
+    screen.render_frame();
+    assert_eq!(screen.pixel(8, 12), theme.alive, "Expected no grid without a grid colour");
+
+    screen.hud_mut().visible = true;
+    screen.render_frame();
+    let (width, height) = screen.view_size();
+    let text = (0..height).flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|&(x, y)| screen.pixel(x, y) == theme.overlay)
+        .count();
+    assert!(text > 0, "Expected the HUD in the theme's overlay colour");
+    1
+}
+
+/// Help test lists the default bindings and shows them over a frame,
+/// expecting every action with its keys and the overlay to be drawn in the middle only while toggled on.
+fn test_help() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Help Overlay".cyan());
+    let keys = KeyBindings::default();
+    let help = Help::new(&keys);
+    assert_eq!(help.lines().len(), Action::ALL.len() + 1, "Expected a title and a line per action");
+    assert_eq!(help.lines()[0], "Keys", "Expected a title");
+    assert!(
+        help.lines().iter().any(|line| line.starts_with("Show or hide this help") && line.ends_with("?, Shift+/")),
+        "Expected the help to list its own keys"
+    );
+    assert!(help.lines().iter().any(|line| line.ends_with("Escape, Q")), "Expected both keys that quit");
+
+    let text = |screen: &Offscreen| {
+        let (width, height) = screen.view_size();
+        (0..height).flat_map(|y| (0..width).map(move |x| (x, y)))
+            .filter(|&(x, y)| screen.pixel(x, y) == Theme::default().overlay)
+            .collect::<Vec<_>>()
+    };
+    let mut screen = Offscreen::new(64, 64, 512, 512);
+    screen.hud_mut().visible = false;
+    *screen.help_mut() = help;
+    screen.render_frame();
+    assert!(text(&screen).is_empty(), "Expected the help to be hidden until toggled");
+    screen.help_mut().visible = true;
+    screen.render_frame();
+    let text = text(&screen);
+    assert!(!text.is_empty(), "Expected the help to be drawn");
+    let (left, right) = (text.iter().map(|&(x, _)| x).min().unwrap(), text.iter().map(|&(x, _)| x).max().unwrap());
+    let (top, bottom) = (text.iter().map(|&(_, y)| y).min().unwrap(), text.iter().map(|&(_, y)| y).max().unwrap());
+    assert!((left + right).abs_diff(512) < 16 && (top + bottom).abs_diff(512) < 16, "Expected the help in the middle of the frame");
+    1
+}
@@ -0,0 +1,119 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use gol_rs::args::Args;
+use gol_rs::gol::{self, event::Event, format::Board};
+use gol_rs::net::server::{self, ServerOptions};
+use gol_rs::sdl;
+use gol_rs::util::logger;
+use log::Level;
+use sdl2::keyboard::Keycode;
+use std::{process::Stdio, time::Duration};
+use tokio::io::{AsyncBufReadExt, BufReader};
+use tokio::{net::TcpListener, process::Command, time::timeout};
+
+mod utils;
+
+#[tokio::main]
+async fn main() {
+    let start = std::time::Instant::now();
+    logger::set_panic_hook();
+    logger::init(Level::Debug, false);
+
+    let passed_tests = test_ctl(Args::default().threads(1)).await.unwrap();
+
+    println!(
+        "\ntest result: {}. {} passed; finished in {:.2}s\n",
+        "ok".green(),
+        passed_tests,
+        start.elapsed().as_secs_f32()
+    );
+    std::process::exit(0);
+}
+
+/// Control test runs a 64x64 image behind a server and drives it with `gol-ctl`:
+/// one `gol-ctl watch` follows the events while others pause, snapshot and quit the simulation.
+async fn test_ctl(args: Args) -> Result<usize> {
+    let args = args.turns(100000000).image_width(64).image_height(64).headless(true);
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?.to_string();
+
+    let (key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+    let (events_forward_tx, events_forward_rx) = flume::bounded::<Event>(1000);
+    tokio::spawn(gol::run(args.clone(), events_tx, key_presses_rx));
+    tokio::spawn(sdl::r#loop::run_headless(args.clone(), events_forward_rx));
+    let options = ServerOptions::from(&args);
+    tokio::spawn(server::serve(listener, options, events_rx, events_forward_tx, key_presses_tx));
+
+    let mut watch = Command::new(env!("CARGO_BIN_EXE_gol-ctl"))
+        .args(["--server", &addr, "watch"])
+        .stdout(Stdio::piped())
+        .kill_on_drop(true)
+        .spawn()?;
+    // the watcher only prints events once it has subscribed, so nothing happens before its first line
+    let mut watched = BufReader::new(watch.stdout.take().context("gol-ctl watch has no stdout")?).lines();
+    let mut lines = vec![
+        timeout(Duration::from_secs(10), watched.next_line()).await
+            .context("gol-ctl watch printed nothing")??
+            .context("gol-ctl watch stopped before printing anything")?
+    ];
+
+    // the server takes the simulation to be paused once it passes the key press on,
+    // but the board only stops changing once the simulation says it has paused
+    ctl(&addr, &["pause"]).await?;
+    timeout(Duration::from_secs(10), async {
+        while let Some(line) = watched.next_line().await? {
+            let paused = line.trim_end().ends_with("Pause");
+            lines.push(line);
+            if paused {
+                return Ok(())
+            }
+        }
+        bail!("gol-ctl watch stopped before the simulation paused")
+    }).await.context("The simulation did not pause")??;
+    let status = ctl(&addr, &["status"]).await?;
+    assert!(status.trim_end().ends_with("Pause"), "Expected the simulation to be paused, but got {}", status);
+    log::debug!(target: "Test", "{}", "Paused with gol-ctl".cyan());
+
+    let population = status_field(&status, "Alive Cells")?;
+    let rle = ctl(&addr, &["snapshot", "--format", "rle"]).await?;
+    let board = Board::decode(rle.as_bytes())?;
+    assert_eq!((board.width, board.height), (64, 64), "Expected a 64x64 snapshot");
+    assert_eq!(board.alive_cells().len(), population, "Expected the snapshot to have the population of the status");
+    log::debug!(target: "Test", "{}", "Took a snapshot with gol-ctl".cyan());
+
+    ctl(&addr, &["resume"]).await?;
+    ctl(&addr, &["quit"]).await?;
+    timeout(Duration::from_secs(10), async {
+        while let Some(line) = watched.next_line().await? {
+            lines.push(line);
+        }
+        watch.wait().await
+    }).await.context("gol-ctl watch did not stop after quitting")??;
+    let watched = lines.join("\n");
+    for expected in ["Pause", "Executing", "Final Turn Complete", "Quitting"] {
+        assert!(watched.contains(expected), "Expected gol-ctl watch to print {}, but got\n{}", expected, watched);
+    }
+    log::debug!(target: "Test", "{}", "Watched and quit with gol-ctl".cyan());
+
+    Ok(1)
+}
+
+/// Run `gol-ctl` against the server on `addr` and return what it printed.
+async fn ctl(addr: &str, args: &[&str]) -> Result<String> {
+    let output = timeout(
+        Duration::from_secs(10),
+        Command::new(env!("CARGO_BIN_EXE_gol-ctl")).arg("--server").arg(addr).args(args).output(),
+    ).await.with_context(|| format!("gol-ctl {} did not finish", args.join(" ")))??;
+    if !output.status.success() {
+        bail!("gol-ctl {} failed: {}", args.join(" "), String::from_utf8_lossy(&output.stderr));
+    }
+    Ok(String::from_utf8(output.stdout)?)
+}
+
+/// Read the number that follows `name` in a status printed by `gol-ctl status`.
+fn status_field(status: &str, name: &str) -> Result<usize> {
+    let (_, rest) = status.split_once(name).with_context(|| format!("The status {} has no {}", status, name))?;
+    let value = rest.split_whitespace().next().with_context(|| format!("The status {} has no {}", status, name))?;
+    value.parse().with_context(|| format!("The {} of the status {} is not a number", name, status))
+}
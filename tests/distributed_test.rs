@@ -0,0 +1,218 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use gol_rs::{args::Args, gol::{self, event::{Event, State}, Params}, util::logger};
+use gol_rs::gol::{format::{Board, Format}, rule::Rule};
+use gol_rs::util::cell::CellValue;
+use log::Level;
+use sdl2::keyboard::Keycode;
+use utils::{visualise::assert_eq_board, io::read_alive_cells, worker, http::{free_addr, request}};
+
+mod utils;
+
+/// The number of worker processes started for the tests.
+const WORKERS: usize = 3;
+
+#[tokio::main]
+async fn main() {
+    let start = std::time::Instant::now();
+    logger::set_panic_hook();
+    logger::init(Level::Debug, false);
+
+    let mut workers = Vec::new();
+    for _ in 0..WORKERS {
+        workers.push(worker::spawn().await.unwrap());
+    }
+    let addrs = workers.iter().map(|(_, addr)| addr.clone()).collect::<Vec<String>>();
+    let mut passed_tests = test_gol(Args::default(), &addrs).await.unwrap();
+    passed_tests += test_event_sequence(Args::default(), &addrs).await.unwrap();
+    passed_tests += test_halo_exchange(Args::default(), &addrs).await.unwrap();
+    passed_tests += test_worker_failure(Args::default()).await.unwrap();
+
+    // exiting skips the destructors, so the workers have to be stopped here
+    for (mut child, _) in workers {
+        child.kill().await.unwrap();
+    }
+
+    println!(
+        "\ntest result: {}. {} passed; finished in {:.2}s\n",
+        "ok".green(),
+        passed_tests,
+        start.elapsed().as_secs_f32()
+    );
+    std::process::exit(0);
+}
+
+/// Gol tests 16x16, 64x64 and 512x512 images on 0, 1 and 100 turns using 1-3 worker processes,
+/// as the gol tests do with worker threads.
+async fn test_gol(args: Args, addrs: &[String]) -> Result<usize> {
+    let mut passed_tests = 0;
+    let size = [(16_usize, 16_usize), (64, 64), (512, 512)];
+    let turns = [0_usize, 1, 100];
+
+    for (width, height) in size {
+        for expected_turns in turns {
+            let path = format!("check/images/{}x{}x{}.pgm", width, height, expected_turns);
+            let expected_alive = read_alive_cells(path, width, height)?;
+            for workers in 1..=addrs.len() {
+                let args = args.clone()
+                    .turns(expected_turns)
+                    .image_width(width)
+                    .image_height(height)
+                    .workers(&addrs[..workers]);
+                log::debug!(target: "Test", "{} - {:?}", "Testing Gol".cyan(), Params::from(args.clone()));
+                let (_key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+                let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+                tokio::spawn(gol::run(args.clone(), events_tx, key_presses_rx));
+                let mut final_turn_complete = false;
+                loop {
+                    match events_rx.recv_async().await {
+                        Ok(Event::FinalTurnComplete { completed_turns, alive }) => {
+                            final_turn_complete = true;
+                            assert_eq!(
+                                completed_turns, expected_turns as u32,
+                                "Expected completed turns is {}, but got {}", expected_turns, completed_turns
+                            );
+                            assert_eq_board(args.clone(), &alive, &expected_alive);
+                        },
+                        Ok(Event::StateChange { new_state: State::Quitting, .. }) if final_turn_complete => break,
+                        Err(_) => panic!("No FinalTurnComplete events received {:?}", Params::from(args)),
+                        _ => (),
+                    };
+                }
+                passed_tests += 1;
+            }
+        }
+    }
+    Ok(passed_tests)
+}
+
+/// Event sequence test runs a 64x64 image for 20 turns locally and across the workers,
+/// which should send exactly the same events.
+async fn test_event_sequence(args: Args, addrs: &[String]) -> Result<usize> {
+    let args = args.turns(20).image_width(64).image_height(64);
+    log::debug!(target: "Test", "{} - {:?}", "Testing Event Sequence".cyan(), Params::from(args.clone()));
+    let local = collect_events(args.clone()).await;
+    let distributed = collect_events(args.workers(addrs)).await;
+    assert_eq!(local.len(), distributed.len(), "Expected as many events from the workers as from a local run");
+    for (local, distributed) in local.iter().zip(&distributed) {
+        assert_eq!(local, distributed, "Expected the same events from the workers as from a local run");
+    }
+    Ok(1)
+}
+
+/// Halo exchange test runs small worlds of awkward sizes for 30 turns,
+/// split between threads and between workers that swap their halo rows directly,
+/// which should send exactly the same events as a single thread.
+async fn test_halo_exchange(args: Args, addrs: &[String]) -> Result<usize> {
+    let mut passed_tests = 0;
+    let size = [(1_usize, 1_usize), (3, 2), (7, 3), (13, 9), (20, 31)];
+    for (width, height) in size {
+        // a fixed scattering of alive cells, so every run starts from the same board
+        let cells = (0..width * height)
+            .map(|i| if (i * 7919 + 13) % 5 < 2 { CellValue::Alive } else { CellValue::Dead })
+            .collect();
+        let board = Board { width, height, cells };
+        std::fs::create_dir_all(&args.output_dir)?;
+        let path = args.output_dir.join(format!("halo-{}x{}.pgm", width, height));
+        std::fs::write(&path, board.encode(Format::Pgm, &Rule::default()))?;
+
+        let args = args.clone().turns(30).input(&path);
+        log::debug!(target: "Test", "{} - {:?}", "Testing Halo Exchange".cyan(), Params::from(args.clone()));
+        let single = collect_events(args.clone().threads(1)).await;
+        for threads in [2, 3, height] {
+            let threaded = collect_events(args.clone().threads(threads)).await;
+            assert_eq!(single, threaded, "Expected the same events from {} threads as from one", threads);
+        }
+        let distributed = collect_events(args.clone().workers(addrs)).await;
+        assert_eq!(single, distributed, "Expected the same events from the workers as from one thread");
+        passed_tests += 1;
+    }
+    Ok(passed_tests)
+}
+
+/// Run the Game of Life and collect every event it sends, formatted for comparison.
+/// The alive cells are counted every 2 seconds rather than every few turns, so their counts are left out.
+async fn collect_events(args: Args) -> Vec<String> {
+    let (_key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+    tokio::spawn(gol::run(args, events_tx, key_presses_rx));
+    let mut events = Vec::new();
+    while let Ok(event) = events_rx.recv_async().await {
+        if !matches!(event, Event::AliveCellsCount { .. }) {
+            events.push(format!("{:?}", event));
+        }
+    }
+    events
+}
+
+/// Worker failure test runs a 512x512 image for 10 turns locally, then resumes it for another 90 turns
+/// across three workers of its own, paused before its first turn. One worker is killed while it is paused,
+/// the run is stepped a turn to pause again, and the broker should have reassigned the dead worker's strip
+/// by then. The others should finish every turn exactly once.
+async fn test_worker_failure(args: Args) -> Result<usize> {
+    let mut workers = Vec::new();
+    for _ in 0..WORKERS {
+        workers.push(worker::spawn().await?);
+    }
+    let addrs = workers.iter().map(|(_, addr)| addr.clone()).collect::<Vec<String>>();
+    let checkpoint = args.output_dir.join("worker-failure.ckpt");
+    let args = args.image_width(512).image_height(512);
+    collect_events(args.clone().turns(10).checkpoint(&checkpoint)).await;
+
+    let metrics = free_addr()?;
+    let args = args.turns(100).resume(&checkpoint).workers(&addrs).worker_timeout(5).metrics(&metrics);
+    log::debug!(target: "Test", "{} - {:?}", "Testing Worker Failure".cyan(), Params::from(args.clone()));
+    let expected_alive = read_alive_cells("check/images/512x512x100.pgm", 512, 512)?;
+
+    // the distributor pauses before stepping, so however far ahead it could get, the kill lands mid-run
+    let (key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    key_presses_tx.send_async(Keycode::P).await?;
+    let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+    tokio::spawn(gol::run(args.clone(), events_tx, key_presses_rx));
+    let mut last_turn = 10;
+    let mut final_turn_complete = false;
+    loop {
+        match events_rx.recv_async().await {
+            Ok(Event::TurnComplete { completed_turns }) => {
+                assert_eq!(completed_turns, last_turn + 1, "Expected every turn to complete exactly once");
+                last_turn = completed_turns;
+            },
+            Ok(Event::StateChange { completed_turns: 10, new_state: State::Pause }) => {
+                workers[1].0.kill().await?;
+                // the first P resumes the run, and the second pauses it again after one turn
+                key_presses_tx.send_async(Keycode::P).await?;
+                key_presses_tx.send_async(Keycode::P).await?;
+            },
+            Ok(Event::StateChange { completed_turns: 11, new_state: State::Pause }) => {
+                let reassignments = scrape_reassignments(&metrics).await?;
+                assert!(reassignments >= 1.0, "Expected the strip of the killed worker to be reassigned");
+                key_presses_tx.send_async(Keycode::P).await?;
+            },
+            Ok(Event::FinalTurnComplete { completed_turns, alive }) => {
+                final_turn_complete = true;
+                assert_eq!(completed_turns, 100, "Expected completed turns is 100, but got {}", completed_turns);
+                assert_eq_board(args.clone(), &alive, &expected_alive);
+            },
+            Ok(Event::StateChange { new_state: State::Quitting, .. }) if final_turn_complete => break,
+            Err(_) => panic!("No FinalTurnComplete events received {:?}", Params::from(args)),
+            _ => (),
+        };
+    }
+    assert_eq!(last_turn, 100, "Expected the run to be stepped after the worker was killed");
+
+    for (mut child, _) in workers {
+        child.kill().await?;
+    }
+    Ok(1)
+}
+
+/// Read how many workers had their strips reassigned from the metrics exported on `addr`.
+async fn scrape_reassignments(addr: &str) -> Result<f64> {
+    let (code, body) = request(addr, "GET", "/metrics").await?;
+    assert_eq!(code, 200, "Expected the metrics, but got {}", code);
+    let metrics = String::from_utf8(body)?;
+    let sample = metrics.lines()
+        .find_map(|line| line.strip_prefix("gol_worker_reassignments_total "))
+        .context("The metrics have no gol_worker_reassignments_total")?;
+    Ok(sample.parse()?)
+}
@@ -0,0 +1,209 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use flume::Receiver;
+use gol_rs::args::Args;
+use gol_rs::gol::{self, edit::Edit, event::{Event, State}, format::{Board, Format}, rule::Rule, Hooks, Params};
+use gol_rs::sdl::tools::{place_file, stamp, Pattern, Placement, Selection, Transform};
+use gol_rs::util::{cell::{CellCoord, CellValue}, logger};
+use log::Level;
+use sdl2::keyboard::Keycode;
+use std::collections::HashSet;
+use std::path::Path;
+use std::time::Duration;
+use tokio::time::timeout;
+use utils::{common::step, io::read_alive_cells, visualise::assert_eq_board};
+
+mod utils;
+
+#[tokio::main]
+async fn main() {
+    let start = std::time::Instant::now();
+    logger::set_panic_hook();
+    logger::init(Level::Debug, false);
+
+    let mut passed_tests = test_tools() + test_drop().unwrap();
+    for threads in [1, 2] {
+        passed_tests += test_edit(Args::default().threads(threads)).await.unwrap();
+    }
+
+    println!(
+        "\ntest result: {}. {} passed; finished in {:.2}s\n",
+        "ok".green(),
+        passed_tests,
+        start.elapsed().as_secs_f32()
+    );
+    std::process::exit(0);
+}
+
+/// Tools test copies, transforms and stamps an L shaped selection,
+/// expecting every tool to leave the world as drawn by hand.
+fn test_tools() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Selection Tools".cyan());
+    // a 4x4 world with an L in its top left 2x3 cells
+    let (width, height) = (4, 4);
+    let mut alive = vec![false; width * height];
+    for (x, y) in [(0, 0), (0, 1), (0, 2), (1, 2)] {
+        alive[y * width + x] = true;
+    }
+    let apply = |edit: &Edit| {
+        let mut world = alive.iter().map(|&alive| if alive { CellValue::Alive } else { CellValue::Dead }).collect::<Vec<_>>();
+        edit.apply(&mut world, width, height);
+        Board { width, height, cells: world }.alive_cells().into_iter().map(|cell| (cell.x, cell.y)).collect::<HashSet<_>>()
+    };
+
+    let selection = Selection::between(CellCoord::new(1, 2), CellCoord::new(0, 0));
+    assert_eq!(selection, Selection { x: 0, y: 0, width: 2, height: 3 }, "Expected the selection to span both corners");
+    let copied = selection.copy(&alive, width);
+    assert_eq!(copied.alive_cells().len(), 4, "Expected the L to be copied");
+    assert!(apply(&selection.clear()).is_empty(), "Expected clearing to kill the L");
+
+    let (rotated, moved) = selection.transform(&alive, width, Transform::Rotate);
+    assert_eq!(moved, Selection { x: 0, y: 0, width: 3, height: 2 }, "Expected the selection to turn on its side");
+    assert_eq!(apply(&rotated), HashSet::from([(0, 0), (1, 0), (2, 0), (0, 1)]), "Expected the L to turn clockwise");
+    let (flipped, _) = selection.transform(&alive, width, Transform::FlipHorizontal);
+    assert_eq!(apply(&flipped), HashSet::from([(1, 0), (1, 1), (1, 2), (0, 2)]), "Expected the L to be mirrored");
+    let (flipped, _) = selection.transform(&alive, width, Transform::FlipVertical);
+    assert_eq!(apply(&flipped), HashSet::from([(0, 0), (1, 0), (0, 1), (0, 2)]), "Expected the L to be upside down");
+
+    // stamping overwrites the cells under the pattern, leaving out what falls outside the world
+    let stamped = apply(&stamp(&copied, CellCoord::new(3, 2)));
+    assert_eq!(stamped, HashSet::from([(0, 0), (0, 1), (0, 2), (1, 2), (3, 2), (3, 3)]), "Expected the L to be stamped at the edge");
+    for pattern in [Pattern::Glider, Pattern::Lwss, Pattern::GosperGliderGun] {
+        let board = pattern.board().unwrap();
+        assert!(!board.alive_cells().is_empty(), "Expected the {:?} to decode", pattern);
+    }
+    assert_eq!(Pattern::GosperGliderGun.board().unwrap().alive_cells().len(), 36, "Expected the whole gun");
+    1
+}
+
+/// Drop test writes a glider as every format a pattern file can be dropped in, expecting each file
+/// to paste the glider at a cell or replace a 6x6 world with it centred, and bad or oversized files
+/// to be refused with an error saying why.
+fn test_drop() -> Result<usize> {
+    log::debug!(target: "Test", "{}", "Testing Dropped Pattern Files".cyan());
+    let dir = Path::new("out/edit/drop");
+    std::fs::create_dir_all(dir)?;
+    let glider = Pattern::Glider.board()?;
+    let (width, height) = (6, 6);
+    let alive_cells = |edit: &Edit| {
+        let mut world = vec![CellValue::Dead; width * height];
+        edit.apply(&mut world, width, height);
+        Board { width, height, cells: world }.alive_cells().into_iter().map(|cell| (cell.x, cell.y)).collect::<HashSet<_>>()
+    };
+    let moved = |dx: usize, dy: usize| glider.alive_cells().into_iter().map(|cell| (cell.x + dx, cell.y + dy)).collect::<HashSet<_>>();
+
+    for format in [Format::Rle, Format::Cells] {
+        let path = dir.join(format!("glider.{}", format.extension()));
+        std::fs::write(&path, glider.encode(format, &Rule::default()))?;
+        let pasted = place_file(&path, Placement::At(CellCoord::new(3, 2)), width, height)?;
+        assert_eq!(alive_cells(&pasted), moved(3, 2), "Expected the {:?} glider to be pasted at the cell", format);
+        let replaced = place_file(&path, Placement::Replace, width, height)?;
+        assert_eq!(alive_cells(&replaced), moved(1, 1), "Expected the {:?} glider to be centred", format);
+        assert_eq!(replaced, stamp(&glider.clone().fit(width, height, format)?, CellCoord::new(0, 0)), "Expected every cell to be set");
+    }
+
+    // an image replaces the world only when it is the size of the world
+    let path = dir.join("glider.pgm");
+    std::fs::write(&path, glider.clone().fit(width, height, Format::Rle)?.encode(Format::Pgm, &Rule::default()))?;
+    assert_eq!(alive_cells(&place_file(&path, Placement::Replace, width, height)?), moved(1, 1), "Expected the image to replace the world");
+    assert!(place_file(&path, Placement::Replace, 8, 8).is_err(), "Expected an image of another size to be refused");
+
+    let path = dir.join("broken.rle");
+    std::fs::write(&path, "x = 3, y = 3\nbo$2bo$3?!")?;
+    assert!(place_file(&path, Placement::Replace, width, height).is_err(), "Expected a broken pattern to be refused");
+    let refused = |name: &str, rle: &str| -> Result<String> {
+        let path = dir.join(name);
+        std::fs::write(&path, rle)?;
+        let error = place_file(&path, Placement::At(CellCoord::new(0, 0)), width, height).err()
+            .with_context(|| format!("Expected {} to be refused", name))?;
+        Ok(format!("{:#}", error))
+    };
+    let message = refused("huge.rle", "x = 100000, y = 100000\nbo$2bo$3o!")?;
+    assert!(message.contains("more than"), "Expected a pattern too large to hold to be refused, but got {:?}", message);
+    let message = refused("long.rle", "x = 3, y = 3\n99999999999999999999999bo$2bo$3o!")?;
+    assert!(message.contains("run longer"), "Expected a run too long to count to be refused, but got {:?}", message);
+    let message = refused("wide.rle", "x = 3, y = 3\n9999999bo$2bo$3o!")?;
+    assert!(message.contains("larger than its header"), "Expected a run past the header to be refused, but got {:?}", message);
+    assert!(place_file(&dir.join("glider.gif"), Placement::Replace, width, height).is_err(), "Expected other files to be refused");
+    assert!(place_file(&dir.join("missing.rle"), Placement::Replace, width, height).is_err(), "Expected a missing file to be refused");
+    Ok(1)
+}
+
+/// Edit test pauses a 64x64 image, clears it and stamps a glider through the distributor,
+/// expecting the output while paused to be the glider. It then runs on and pauses again,
+/// expecting the glider to have moved on as though it had always been the whole world.
+async fn test_edit(args: Args) -> Result<usize> {
+    let (width, height) = (64, 64);
+    let args = args.turns(100000000).image_width(width).image_height(height).output_dir("out/edit");
+    log::debug!(target: "Test", "{} - {:?}", "Testing Edits".cyan(), Params::from(args.clone()));
+    let (key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+    let (edits_tx, edits_rx) = flume::unbounded::<Edit>();
+    let hooks = Hooks { edits: Some(edits_rx), ..Hooks::default() };
+    tokio::spawn(gol::run_with(args.clone(), events_tx, key_presses_rx, hooks));
+
+    key_presses_tx.send_async(Keycode::P).await?;
+    let paused = wait_for_pause(&events_rx).await?;
+    let glider = Pattern::Glider.board()?;
+    let at = CellCoord::new(10, 10);
+    edits_tx.send_async(Selection { x: 0, y: 0, width, height }.clear()).await?;
+    edits_tx.send_async(stamp(&glider, at)).await?;
+    let expected = glider.alive_cells().into_iter()
+        .map(|cell| CellCoord::new(cell.x + at.x, cell.y + at.y))
+        .collect::<Vec<CellCoord>>();
+    let alive = read_output(&events_rx, &key_presses_tx, &args, paused).await?;
+    assert_eq_board(args.clone(), &alive, &expected);
+    log::debug!(target: "Test", "{}", "The edits reached the world while paused".cyan());
+
+    key_presses_tx.send_async(Keycode::P).await?;
+    timeout(Duration::from_secs(30), async {
+        while let Ok(event) = events_rx.recv_async().await {
+            if let Event::TurnComplete { completed_turns } = event {
+                if completed_turns >= paused + 10 {
+                    break
+                }
+            }
+        }
+    }).await.context("The simulation did not run on after the edits")?;
+    key_presses_tx.send_async(Keycode::P).await?;
+    let paused_again = wait_for_pause(&events_rx).await?;
+    let alive = read_output(&events_rx, &key_presses_tx, &args, paused_again).await?;
+    let expected = (paused..paused_again).fold(expected, |alive, _| step(&alive, width, height));
+    assert_eq_board(args.clone(), &alive, &expected);
+    log::debug!(target: "Test", "{}", "The edited world runs on".cyan());
+
+    key_presses_tx.send_async(Keycode::Q).await?;
+    Ok(1)
+}
+
+/// Wait for the simulation to pause, returning the turns it completed.
+async fn wait_for_pause(events: &Receiver<Event>) -> Result<u32> {
+    timeout(Duration::from_secs(10), async {
+        while let Ok(event) = events.recv_async().await {
+            if let Event::StateChange { completed_turns, new_state: State::Pause } = event {
+                return Ok(completed_turns)
+            }
+        }
+        anyhow::bail!("The simulation stopped before pausing")
+    }).await.context("The simulation did not pause")?
+}
+
+/// Ask for the output of the paused world after `turns` turns and read it back.
+async fn read_output(
+    events: &Receiver<Event>,
+    key_presses: &flume::Sender<Keycode>,
+    args: &Args,
+    turns: u32,
+) -> Result<Vec<CellCoord>> {
+    key_presses.send_async(Keycode::S).await?;
+    timeout(Duration::from_secs(10), async {
+        while let Ok(event) = events.recv_async().await {
+            if let Event::ImageOutputComplete { .. } = event {
+                return Ok(())
+            }
+        }
+        anyhow::bail!("The simulation stopped before writing the output")
+    }).await.context("No output was written")??;
+    let path = args.output_dir.join(format!("{}x{}x{}.pgm", args.width(), args.height(), turns));
+    read_alive_cells(path, args.width(), args.height())
+}
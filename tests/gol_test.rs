@@ -3,7 +3,7 @@ use core::panic;
 use clap::{Command, Arg, value_parser};
 use colored::Colorize;
 use log::Level;
-use gol_rs::{args::Args, gol::{self, event::{Event, State}, Params}, util::logger};
+use gol_rs::{args::Args, gol::{self, event::{Event, State}, Hooks, Params}, util::logger};
 use sdl2::keyboard::Keycode;
 use utils::{visualise::assert_eq_board, io::read_alive_cells};
 
@@ -26,7 +26,7 @@ async fn main() {
     assert!(threads > 0, "Threads for testing should be greater than 0");
     let args = Args::default().threads(threads);
 
-    let passed_tests = test_gol(args).await.unwrap();
+    let passed_tests = test_gol(args.clone()).await.unwrap() + test_without_flips(args).await.unwrap();
 
     println!(
         "\ntest result: {}. {} passed; finished in {:.2}s\n",
@@ -87,3 +87,27 @@ async fn test_gol(args: Args) -> Result<usize> {
     }
     Ok(passed_tests)
 }
+
+/// Without flips test runs a 64x64 image for 100 turns with nothing following the cells, expecting the
+/// alive cells of the loaded image and then only the completed turns, with the final board still right.
+async fn test_without_flips(args: Args) -> Result<usize> {
+    let args = args.turns(100).image_width(64).image_height(64);
+    log::debug!(target: "Test", "{} - {:?}", "Testing Without Flips".cyan(), Params::from(args.clone()));
+    let expected_alive = read_alive_cells("check/images/64x64x100.pgm", 64, 64)?;
+    let (_key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+    let hooks = Hooks { without_flips: true, ..Hooks::default() };
+    tokio::spawn(gol::run_with(args.clone(), events_tx, key_presses_rx, hooks));
+    let mut turns_completed = 0;
+    while let Ok(event) = events_rx.recv_async().await {
+        match event {
+            Event::CellsFlipped { completed_turns, .. } =>
+                assert_eq!(completed_turns, 0, "Expected only the loaded image to be reported cell by cell"),
+            Event::TurnComplete { .. } => turns_completed += 1,
+            Event::FinalTurnComplete { alive, .. } => assert_eq_board(args.clone(), &alive, &expected_alive),
+            _ => (),
+        }
+    }
+    assert_eq!(turns_completed, 100, "Expected every turn to complete");
+    Ok(1)
+}
@@ -0,0 +1,131 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use gol_rs::args::Args;
+use gol_rs::gol::{self, event::{Event, State}, stats::{Statistics, TurnStats}, Hooks, Params};
+use gol_rs::sdl::{graph::Graph, offscreen::Offscreen};
+use gol_rs::util::logger;
+use log::Level;
+use sdl2::keyboard::Keycode;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::timeout;
+use utils::io::read_alive_counts;
+
+mod utils;
+
+#[tokio::main]
+async fn main() {
+    let start = std::time::Instant::now();
+    logger::set_panic_hook();
+    logger::init(Level::Debug, false);
+
+    let mut passed_tests = test_graph() + test_panel();
+    for threads in [1, 4] {
+        passed_tests += test_statistics(Args::default().threads(threads)).await.unwrap();
+    }
+
+    println!(
+        "\ntest result: {}. {} passed; finished in {:.2}s\n",
+        "ok".green(),
+        passed_tests,
+        start.elapsed().as_secs_f32()
+    );
+    std::process::exit(0);
+}
+
+fn turn(completed_turns: u32, population: usize, births: usize, deaths: usize) -> TurnStats {
+    TurnStats { completed_turns, population, births, deaths }
+}
+
+/// Graph test feeds a graph turns by hand, expecting its time window, y-axis and CSV to follow them.
+fn test_graph() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Population Graph".cyan());
+    let mut graph = Graph::new(true, 16);
+    assert_eq!(graph.scale(), 1, "Expected an empty graph to have a y-axis");
+    (0..40).for_each(|t| graph.push(turn(t, 10 + t as usize, 3, 2)));
+    assert_eq!(graph.turns(), (23, 39), "Expected the time window to end at the latest turn");
+    assert_eq!(graph.shown().count(), 17, "Expected the turns within the time window to be shown");
+    assert_eq!(graph.scale(), 50, "Expected the y-axis to round up the largest value shown");
+    graph.zoom(1);
+    assert_eq!(graph.window(), 32, "Expected the time window to widen");
+    graph.zoom(-4);
+    assert_eq!(graph.window(), 16, "Expected the time window not to narrow past its minimum");
+
+    let csv = graph.to_csv();
+    let mut lines = csv.lines();
+    assert_eq!(lines.next(), Some("completed_turns,population,births,deaths"), "Expected a CSV header");
+    assert_eq!(lines.next(), Some("0,10,3,2"), "Expected the first turn to be exported");
+    assert_eq!(csv.lines().count(), 41, "Expected every turn to be exported");
+
+    graph.push(turn(0, 5, 0, 0));
+    assert_eq!(graph.history().len(), 1, "Expected a turn going back to start the series over");
+    1
+}
+
+/// Panel test shows the graph below the cells of an offscreen frame,
+/// expecting the cells to make room for it and the plot to be drawn in it.
+fn test_panel() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Graph Panel".cyan());
+    let mut screen = Offscreen::new(64, 64, 256, 256);
+    screen.hud_mut().visible = false;
+    assert!(screen.cell_at(128.0, 250.0).is_some(), "Expected the cells to fill the frame");
+    assert!(screen.toggle_graph(), "Expected the graph to be shown");
+    assert!(screen.cell_at(128.0, 250.0).is_none(), "Expected no cells under the graph");
+    assert!(screen.cell_at(128.0, 100.0).is_some(), "Expected the cells above the graph");
+
+    let population = 100;
+    for t in 0..100 {
+        screen.graph_mut().push(turn(t, population, 0, 0));
+    }
+    screen.render_frame();
+    let (width, height) = screen.view_size();
+    let count = |argb: u32| (height * 3 / 4..height)
+        .flat_map(|y| (0..width).map(move |x| (x, y)))
+        .filter(|&(x, y)| screen.pixel(x, y) == argb)
+        .count();
+    assert!(count(0xFF_FF_FF_FF) > width as usize / 2, "Expected the population to be plotted across the panel");
+    assert!(count(0xFF_10_10_10) > 0, "Expected the panel to have a background");
+
+    assert!(!screen.toggle_graph(), "Expected the graph to be hidden");
+    assert!(screen.cell_at(128.0, 250.0).is_some(), "Expected the cells to fill the frame again");
+    1
+}
+
+/// Statistics test runs a 64x64 image for 100 turns with the distributor keeping statistics,
+/// expecting a population for every turn matching the check and births and deaths that add up to it.
+async fn test_statistics(args: Args) -> Result<usize> {
+    let (width, height, turns) = (64, 64, 100);
+    let args = args.turns(turns).image_width(width).image_height(height).output_dir("out/graph");
+    log::debug!(target: "Test", "{} - {:?}", "Testing Turn Statistics".cyan(), Params::from(args.clone()));
+    let (_key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+    let statistics = Arc::new(Statistics::default());
+    let hooks = Hooks { statistics: Some(statistics.clone()), ..Hooks::default() };
+    tokio::spawn(gol::run_with(args.clone(), events_tx, key_presses_rx, hooks));
+
+    timeout(Duration::from_secs(60), async {
+        while let Ok(event) = events_rx.recv_async().await {
+            if let Event::StateChange { new_state: State::Quitting, .. } = event {
+                break
+            }
+        }
+    }).await.context("The simulation did not finish")?;
+
+    let stats = statistics.take();
+    let expected = read_alive_counts(width as u32, height as u32)?;
+    assert_eq!(stats.len(), turns + 1, "Expected statistics for the initial world and every turn");
+    for (t, stats) in stats.iter().enumerate() {
+        assert_eq!(stats.completed_turns, t as u32, "Expected the statistics in turn order");
+        if let Some(&alive) = expected.get(&stats.completed_turns) {
+            assert_eq!(stats.population, alive as usize, "Expected the population of turn {} to match the check", t);
+        }
+    }
+    for pair in stats.windows(2) {
+        assert_eq!(
+            pair[1].population, pair[0].population + pair[1].births - pair[1].deaths,
+            "Expected the births and deaths of turn {} to add up to its population", pair[1].completed_turns
+        );
+    }
+    assert!(statistics.take().is_empty(), "Expected the statistics to be taken once");
+    Ok(1)
+}
@@ -0,0 +1,193 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use gol_rs::args::Args;
+use gol_rs::gol::{self, event::Event, format::Board, Params};
+use gol_rs::util::logger;
+use log::Level;
+use sdl2::keyboard::Keycode;
+use serde_json::Value;
+use std::{process::Stdio, time::Duration};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::{net::TcpStream, process::Command, time::timeout};
+use utils::http::{free_addr, request, request_when_listening};
+
+mod utils;
+
+#[tokio::main]
+async fn main() {
+    let start = std::time::Instant::now();
+    logger::set_panic_hook();
+    logger::init(Level::Debug, false);
+
+    let passed_tests = test_http(Args::default().threads(1)).await.unwrap() + test_http_stdin().await.unwrap();
+
+    println!(
+        "\ntest result: {}. {} passed; finished in {:.2}s\n",
+        "ok".green(),
+        passed_tests,
+        start.elapsed().as_secs_f32()
+    );
+    std::process::exit(0);
+}
+
+/// HTTP test runs a 512x512 image with the HTTP API enabled, checks the status while it executes,
+/// pauses it, lets the frontend resume it and pauses it again, downloads the board in every format
+/// and quits, expecting the final board to be the one downloaded while paused.
+async fn test_http(args: Args) -> Result<usize> {
+    let addr = free_addr()?;
+    let args = args.turns(100000000).image_width(512).image_height(512).http(&addr);
+    log::debug!(target: "Test", "{} - {:?}", "Testing HTTP".cyan(), Params::from(args.clone()));
+
+    let (key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+    tokio::spawn(gol::run(args, events_tx, key_presses_rx));
+    let final_alive = tokio::spawn(async move {
+        while let Ok(event) = events_rx.recv_async().await {
+            if let Event::FinalTurnComplete { alive, .. } = event {
+                return Some(alive)
+            }
+        }
+        None
+    });
+
+    // the turns per second are measured every 2 seconds
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    let executing = status(&addr).await?;
+    assert_eq!(executing["state"], "Executing", "Expected the simulation to be executing, but got {}", executing);
+    assert_eq!(executing["image_width"], 512, "Expected a 512x512 board, but got {}", executing);
+    assert!(executing["completed_turns"].as_u64() > Some(0), "Expected turns to complete, but got {}", executing);
+    assert!(executing["turns_per_second"].as_u64() > Some(0), "Expected turns to be measured, but got {}", executing);
+    log::debug!(target: "Test", "{}", "The status follows the simulation".cyan());
+
+    let code = request_with_long_header(&addr).await?;
+    assert_eq!(code, "431", "Expected a request head longer than the limit to be refused");
+    status(&addr).await?;
+    log::debug!(target: "Test", "{}", "Refused a request head that is too long".cyan());
+
+    let (code, _) = request(&addr, "POST", "/pause").await?;
+    assert_eq!(code, 202, "Expected the pause to be accepted");
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let paused = status(&addr).await?;
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(paused["state"], "Pause", "Expected the simulation to be paused, but got {}", paused);
+    assert_eq!(
+        status(&addr).await?["completed_turns"], paused["completed_turns"],
+        "Expected no turns to complete while paused"
+    );
+    log::debug!(target: "Test", "{}", "Paused over HTTP".cyan());
+
+    // the frontend resumes with its own P, which the API should follow rather than pause again
+    key_presses_tx.send_async(Keycode::P).await?;
+    wait_for_state(&addr, "Executing").await?;
+    let (code, _) = request(&addr, "POST", "/resume").await?;
+    assert_eq!(code, 202, "Expected the resume to be accepted");
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert_eq!(status(&addr).await?["state"], "Executing", "Expected resuming to leave the simulation executing");
+    let (code, _) = request(&addr, "POST", "/pause").await?;
+    assert_eq!(code, 202, "Expected the pause to be accepted");
+    wait_for_state(&addr, "Pause").await?;
+    let paused = status(&addr).await?;
+    log::debug!(target: "Test", "{}", "Followed a resume from the frontend".cyan());
+
+    let (_, pgm) = request(&addr, "GET", "/board?format=pgm").await?;
+    let board = Board::decode(&pgm)?;
+    assert_eq!(
+        board.alive_cells().len() as u64, paused["population"].as_u64().unwrap(),
+        "Expected the board to have as many alive cells as the population"
+    );
+    let (_, rle) = request(&addr, "GET", "/board?format=rle").await?;
+    assert_eq!(Board::decode(&rle)?, board, "Expected the RLE board to match the PGM board");
+    let (_, png) = request(&addr, "GET", "/board?format=png").await?;
+    let png = image::load_from_memory(&png)?.into_luma8();
+    assert_eq!(
+        png.as_raw(), &pgm[pgm.len() - 512 * 512..],
+        "Expected the PNG board to match the PGM board"
+    );
+    let (code, _) = request(&addr, "GET", "/board?format=gif").await?;
+    assert_eq!(code, 400, "Expected an unknown format to be rejected");
+    log::debug!(target: "Test", "{}", "Downloaded the board over HTTP".cyan());
+
+    let (code, _) = request(&addr, "POST", "/quit").await?;
+    assert_eq!(code, 202, "Expected the quit to be accepted");
+    let mut final_alive = timeout(Duration::from_secs(10), final_alive).await
+        .context("No FinalTurnComplete event received within 10 seconds")??
+        .context("No FinalTurnComplete event received")?;
+    let mut expected_alive = board.alive_cells();
+    final_alive.sort_by_key(|cell| (cell.y, cell.x));
+    expected_alive.sort_by_key(|cell| (cell.y, cell.x));
+    assert_eq!(final_alive, expected_alive, "Expected the final board to be the one downloaded while paused");
+    log::debug!(target: "Test", "{}", "Quit over HTTP".cyan());
+
+    Ok(1)
+}
+
+/// HTTP stdin test pipes a 24x16 glider into `gol-rs` with the HTTP API enabled,
+/// expecting the status and the board to take the dimensions of the pattern rather than none at all.
+async fn test_http_stdin() -> Result<usize> {
+    let addr = free_addr()?;
+    log::debug!(target: "Test", "{}", "Testing HTTP with a board from stdin".cyan());
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gol-rs"))
+        .args(["--headless", "--turns", "100000000", "--threads", "1", "--http", &addr])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut stdin = child.stdin.take().context("The simulation has no stdin")?;
+    stdin.write_all(b"x = 24, y = 16\nbo$2bo$3o!\n").await?;
+    drop(stdin);
+
+    // the server only listens once the board has been read
+    request_when_listening(&addr, "GET", "/status").await?;
+    let executing = status(&addr).await?;
+    assert_eq!(executing["image_width"], 24, "Expected a 24x16 board, but got {}", executing);
+    assert_eq!(executing["image_height"], 16, "Expected a 24x16 board, but got {}", executing);
+    assert_eq!(executing["population"], 5, "Expected the glider to be followed, but got {}", executing);
+    let (_, pgm) = request(&addr, "GET", "/board?format=pgm").await?;
+    let board = Board::decode(&pgm)?;
+    assert_eq!((board.width, board.height), (24, 16), "Expected the board to be 24x16");
+    assert_eq!(board.alive_cells().len(), 5, "Expected the glider on the board");
+
+    let (code, _) = request(&addr, "POST", "/quit").await?;
+    assert_eq!(code, 202, "Expected the quit to be accepted");
+    let exit = timeout(Duration::from_secs(10), child.wait()).await
+        .context("The simulation did not stop within 10 seconds")??;
+    assert!(exit.success(), "Expected the simulation to quit cleanly, but got {}", exit);
+    Ok(1)
+}
+
+/// Send a request with a 16 KiB header and return the status code of the response.
+/// The server stops reading once the head is too long, so the connection may be reset after the status line.
+async fn request_with_long_header(addr: &str) -> Result<String> {
+    let mut stream = TcpStream::connect(addr).await?;
+    let padding = "a".repeat(16 * 1024);
+    stream.write_all(format!("GET /status HTTP/1.1\r\nX-Padding: {}\r\n\r\n", padding).as_bytes()).await?;
+    let mut response = Vec::new();
+    let mut buffer = [0_u8; 1024];
+    while !response.windows(2).any(|window| window == b"\r\n") {
+        match timeout(Duration::from_secs(10), stream.read(&mut buffer)).await? {
+            Ok(0) | Err(_) => break,
+            Ok(len) => response.extend_from_slice(&buffer[..len]),
+        }
+    }
+    let response = String::from_utf8_lossy(&response);
+    Ok(response.split_whitespace().nth(1).context("The response has no status code")?.to_owned())
+}
+
+/// Poll the status until the simulation is in `state`, for up to 10 seconds.
+async fn wait_for_state(addr: &str, state: &str) -> Result<()> {
+    timeout(Duration::from_secs(10), async {
+        while status(addr).await?["state"] != state {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        Ok(())
+    }).await.with_context(|| format!("The simulation did not reach {} within 10 seconds", state))?
+}
+
+async fn status(addr: &str) -> Result<Value> {
+    let (code, body) = request(addr, "GET", "/status").await?;
+    if code != 200 {
+        bail!("Expected the status, but got {} {}", code, String::from_utf8_lossy(&body));
+    }
+    Ok(serde_json::from_slice(&body)?)
+}
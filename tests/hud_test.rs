@@ -0,0 +1,78 @@
+use colored::Colorize;
+use gol_rs::gol::{event::State, rule::Rule};
+use gol_rs::sdl::{font::{draw_text, text_size}, hud::Hud};
+use gol_rs::util::logger;
+use log::Level;
+
+fn main() {
+    let start = std::time::Instant::now();
+    logger::set_panic_hook();
+    logger::init(Level::Debug, false);
+
+    let passed_tests = test_font() + test_hud();
+
+    println!(
+        "\ntest result: {}. {} passed; finished in {:.2}s\n",
+        "ok".green(),
+        passed_tests,
+        start.elapsed().as_secs_f32()
+    );
+    std::process::exit(0);
+}
+
+/// Read the pixel at (`x`, `y`) of an ARGB8888 buffer `width` pixels wide.
+fn pixel(pixels: &[u8], width: usize, x: usize, y: usize) -> u32 {
+    let i = 4 * (y * width + x);
+    u32::from_ne_bytes(pixels[i..i + 4].try_into().unwrap())
+}
+
+/// Font test draws a line of text, expecting the glyphs to be scaled and to be left out past the buffer edges.
+fn test_font() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Font".cyan());
+    let lines = vec!["T1".to_owned(), "-".to_owned()];
+    assert_eq!(text_size(&lines, 1), (11, 17), "Expected two glyphs wide and two lines high");
+    assert_eq!(text_size(&lines, 3), (33, 51), "Expected the size to scale");
+
+    let mut pixels = vec![0; 20 * 10 * 4];
+    draw_text(&mut pixels, 20, 0, 0, 2, &lines, 0xFFFFFFFF);
+    // the top bar of the T spans the whole glyph, 10 pixels at twice the size
+    assert!((0..10).all(|x| pixel(&pixels, 20, x, 0) == 0xFFFFFFFF), "Expected the top of the T");
+    assert_eq!(pixel(&pixels, 20, 5, 9), 0xFFFFFFFF, "Expected the stem of the T");
+    assert_eq!(pixel(&pixels, 20, 0, 9), 0, "Expected nothing beside the stem of the T");
+    assert_eq!(pixel(&pixels, 20, 10, 0), 0, "Expected a gap between the glyphs");
+    1
+}
+
+/// HUD test draws the overlay over a white view, expecting its panel to darken the view behind the text
+/// and nothing to be drawn once it is hidden.
+fn test_hud() -> usize {
+    log::debug!(target: "Test", "{}", "Testing HUD".cyan());
+    let hud = Hud {
+        completed_turns: 42,
+        population: 1234,
+        turns_per_second: 99,
+        state: State::Pause,
+        rule: "B36/S23".parse::<Rule>().unwrap(),
+        ..Hud::default()
+    };
+    assert_eq!(
+        hud.lines(), ["Turn 42", "Population 1234", "Turns/s 99", "Paused", "Rule B36/S23"],
+        "Expected the status of the simulation"
+    );
+    let noted = Hud { message: Some("Cannot load glider.rle".to_owned()), ..hud.clone() };
+    assert_eq!(noted.lines().last().unwrap(), "Cannot load glider.rle", "Expected the message below the status");
+
+    let width = 512;
+    let white = vec![0xFF_u8; width * 512 * 4];
+    let mut pixels = white.clone();
+    hud.draw(&mut pixels, width);
+    assert_eq!(pixel(&pixels, width, 0, 0), 0xFF3F3F3F, "Expected the panel to darken the view");
+    assert_eq!(pixel(&pixels, width, 511, 511), 0xFFFFFFFF, "Expected the view outside the panel to be untouched");
+    // the T of the first line starts after the padding, at twice the size in a wide view
+    assert_eq!(pixel(&pixels, width, 8, 8), 0xFFFFFFFF, "Expected the text to be drawn");
+
+    let mut pixels = white.clone();
+    Hud { visible: false, ..hud }.draw(&mut pixels, width);
+    assert!(pixels == white, "Expected a hidden overlay to draw nothing");
+    1
+}
@@ -0,0 +1,135 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use gol_rs::args::Args;
+use gol_rs::gol::{self, event::{Event, State}, Params};
+use gol_rs::util::logger;
+use log::Level;
+use sdl2::keyboard::Keycode;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::{process::Stdio, time::Duration};
+use tokio::{io::AsyncWriteExt, process::Command};
+use utils::http::{free_addr, request, request_when_listening};
+
+mod utils;
+
+#[tokio::main]
+async fn main() {
+    let start = std::time::Instant::now();
+    logger::set_panic_hook();
+    logger::init(Level::Debug, false);
+
+    let passed_tests = test_metrics(Args::default().threads(2)).await.unwrap() + test_metrics_stdin().await.unwrap();
+
+    println!(
+        "\ntest result: {}. {} passed; finished in {:.2}s\n",
+        "ok".green(),
+        passed_tests,
+        start.elapsed().as_secs_f32()
+    );
+    std::process::exit(0);
+}
+
+/// Metrics test runs a 512x512 image on two threads with the exporter enabled, scrapes it while
+/// it executes, then pauses it and saves the board, expecting the metrics to agree with the events.
+async fn test_metrics(args: Args) -> Result<usize> {
+    let addr = free_addr()?;
+    let args = args.turns(100000000).image_width(512).image_height(512).metrics(&addr);
+    log::debug!(target: "Test", "{} - {:?}", "Testing Metrics".cyan(), Params::from(args.clone()));
+
+    let (key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+    tokio::spawn(gol::run(args, events_tx, key_presses_rx));
+    // follow the population and the state through the events, as the exporter should
+    let followed = Arc::new(Mutex::new((0_i64, State::Executing)));
+    let following = Arc::clone(&followed);
+    tokio::spawn(async move {
+        while let Ok(event) = events_rx.recv_async().await {
+            let mut followed = following.lock().unwrap();
+            match event {
+                Event::CellsFlipped { cells, .. } => followed.0 += cells.len() as i64,
+                Event::StateChange { new_state, .. } => followed.1 = new_state,
+                _ => (),
+            }
+        }
+    });
+
+    // the turns per second are measured every 2 seconds
+    tokio::time::sleep(Duration::from_millis(2500)).await;
+    let executing = scrape(&addr).await?;
+    assert!(executing["gol_completed_turns"] > 0.0, "Expected turns to complete, but got {:?}", executing);
+    assert!(executing["gol_turns_per_second"] > 0.0, "Expected turns to be measured, but got {:?}", executing);
+    assert_eq!(executing["gol_state{state=\"Executing\"}"], 1.0, "Expected the simulation to be executing");
+    assert!(executing["gol_births_total"] > 0.0, "Expected cells to be born, but got {:?}", executing);
+    for thread in ["thread-0", "thread-1"] {
+        let compute = format!("gol_worker_compute_seconds_total{{worker=\"{}\"}}", thread);
+        assert!(executing.get(&compute) > Some(&0.0), "Expected {} to compute, but got {:?}", thread, executing);
+    }
+    assert_eq!(executing["gol_io_bytes_written_total"], 0.0, "Expected nothing to be written yet");
+    log::debug!(target: "Test", "{}", "The metrics follow the simulation".cyan());
+
+    key_presses_tx.send_async(Keycode::P).await?;
+    key_presses_tx.send_async(Keycode::S).await?;
+    tokio::time::sleep(Duration::from_millis(1000)).await;
+    let paused = scrape(&addr).await?;
+    assert_eq!(paused["gol_state{state=\"Pause\"}"], 1.0, "Expected the simulation to be paused");
+    assert_eq!(
+        paused["gol_io_bytes_written_total"], (b"P5\n512 512\n255\n".len() + 512 * 512) as f64,
+        "Expected the saved board to be counted"
+    );
+    // the first flips are the cells alive at the start, and every later flip is a birth or a death,
+    // so all the flips add up to the population and twice the deaths
+    let followed = *followed.lock().unwrap();
+    assert_eq!(followed.1, State::Pause, "Expected the events to have reached the pause");
+    assert_eq!(
+        followed.0 as f64, paused["gol_population"] + 2.0 * paused["gol_deaths_total"],
+        "Expected every flip to be a birth or a death, but got {:?}", paused
+    );
+    log::debug!(target: "Test", "{}", "The metrics count births, deaths and output".cyan());
+
+    let (code, _) = request(&addr, "GET", "/board").await?;
+    assert_eq!(code, 404, "Expected only the metrics to be served");
+    key_presses_tx.send_async(Keycode::Q).await?;
+
+    Ok(1)
+}
+
+/// Metrics stdin test pipes a 24x16 glider into `gol-rs` with the exporter enabled,
+/// expecting the population to follow the glider on a board the size of the pattern.
+async fn test_metrics_stdin() -> Result<usize> {
+    let addr = free_addr()?;
+    log::debug!(target: "Test", "{}", "Testing Metrics with a board from stdin".cyan());
+    let mut child = Command::new(env!("CARGO_BIN_EXE_gol-rs"))
+        .args(["--headless", "--turns", "100000000", "--threads", "1", "--metrics", &addr])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+    let mut stdin = child.stdin.take().context("The simulation has no stdin")?;
+    stdin.write_all(b"x = 24, y = 16\nbo$2bo$3o!\n").await?;
+    drop(stdin);
+
+    // the exporter only listens once the board has been read
+    request_when_listening(&addr, "GET", "/metrics").await?;
+    let executing = scrape(&addr).await?;
+    assert!(executing["gol_completed_turns"] > 0.0, "Expected turns to complete, but got {:?}", executing);
+    assert_eq!(executing["gol_population"], 5.0, "Expected the glider to be followed, but got {:?}", executing);
+    assert!(executing["gol_births_total"] > 0.0, "Expected the glider to move, but got {:?}", executing);
+
+    child.kill().await?;
+    Ok(1)
+}
+
+/// Scrape the metrics, keyed by their name and labels.
+async fn scrape(addr: &str) -> Result<HashMap<String, f64>> {
+    let (code, body) = request(addr, "GET", "/metrics").await?;
+    assert_eq!(code, 200, "Expected the metrics, but got {}", code);
+    String::from_utf8(body)?.lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(|line| {
+            let (name, value) = line.rsplit_once(' ').with_context(|| format!("Invalid sample {}", line))?;
+            Ok((name.to_owned(), value.parse()?))
+        })
+        .collect()
+}
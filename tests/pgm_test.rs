@@ -69,7 +69,7 @@ async fn test_pgm(args: Args) -> Result<usize> {
                     }
                 }
                 let path = format!("out/{}x{}x{}.pgm", width, height, expected_turns);
-                let output = read_alive_cells(path, args.image_width, args.image_height).unwrap();
+                let output = read_alive_cells(path, args.width(), args.height()).unwrap();
                 assert_eq_board(args, &output, &expected_alive);
                 passed_test += 1;
             }
@@ -0,0 +1,147 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use gol_rs::args::Args;
+use gol_rs::gol::{self, event::Event, Params};
+use gol_rs::record::gif::Palette;
+use gol_rs::sdl;
+use gol_rs::util::{cell::CellCoord, logger};
+use log::Level;
+use sdl2::keyboard::Keycode;
+use std::{fs::File, path::Path, time::Duration};
+use tokio::{time::timeout, try_join};
+use utils::{common::step, io::read_alive_cells, visualise::assert_eq_board};
+
+mod utils;
+
+#[tokio::main]
+async fn main() {
+    let start = std::time::Instant::now();
+    logger::set_panic_hook();
+    logger::init(Level::Debug, false);
+
+    let passed_tests = test_record(Args::default().threads(1)).await.unwrap()
+        + test_max_secs(Args::default().threads(1)).await.unwrap();
+
+    println!(
+        "\ntest result: {}. {} passed; finished in {:.2}s\n",
+        "ok".green(),
+        passed_tests,
+        start.elapsed().as_secs_f32()
+    );
+    std::process::exit(0);
+}
+
+/// Record tests run 16x16 and 64x64 images headless for 100 turns, recording every 10th turn
+/// at 3 pixels a cell in the inverted palette. The GIF should have a frame for the loaded image
+/// and one for every 10 turns, the last of which should match the image after 100 turns.
+async fn test_record(args: Args) -> Result<usize> {
+    let mut passed_tests = 0;
+    let size = [(16_usize, 16_usize), (64, 64)];
+
+    for (width, height) in size {
+        let args = args.clone()
+            .turns(100)
+            .image_width(width)
+            .image_height(height)
+            .output_dir("out/record")
+            .record(true)
+            .record_stride(10)
+            .record_scale(3)
+            .record_palette(Palette::Inverted);
+        log::debug!(target: "Test", "{} - {:?}", "Testing Record".cyan(), Params::from(args.clone()));
+        let recording = record(args.clone()).await?;
+        assert_eq!(
+            (recording.width, recording.height), (width * 3, height * 3),
+            "Expected the recording to be scaled 3 times"
+        );
+        assert_eq!(recording.palette, Palette::Inverted.colours(), "Expected the inverted palette");
+        assert_eq!(recording.frames.len(), 11, "Expected 11 frames, but got {}", recording.frames.len());
+
+        let first = recording.cells(0, 3);
+        let expected_first = read_alive_cells(format!("check/images/{}x{}x0.pgm", width, height), width, height)?;
+        assert_eq_board(args.clone(), &first, &expected_first);
+        let last = recording.cells(10, 3);
+        let expected_last = read_alive_cells(format!("check/images/{}x{}x100.pgm", width, height), width, height)?;
+        assert_eq_board(args, &last, &expected_last);
+        passed_tests += 1;
+    }
+    Ok(passed_tests)
+}
+
+/// Max seconds test records every turn of a 16x16 image for 100 turns at 50 frames per second,
+/// but for at most a second, which should stop the recording after 50 frames on the board after 49 turns.
+async fn test_max_secs(args: Args) -> Result<usize> {
+    let (width, height) = (16, 16);
+    let args = args
+        .turns(100)
+        .image_width(width)
+        .image_height(height)
+        .output_dir("out/record")
+        .record(true)
+        .fps(50)
+        .record_max_secs(1);
+    log::debug!(target: "Test", "{} - {:?}", "Testing Record Max Seconds".cyan(), Params::from(args.clone()));
+    let recording = record(args.clone()).await?;
+    assert_eq!((recording.width, recording.height), (width, height), "Expected the recording to be unscaled");
+    assert_eq!(recording.palette, Palette::Classic.colours(), "Expected the classic palette");
+    assert_eq!(recording.frames.len(), 50, "Expected 50 frames, but got {}", recording.frames.len());
+
+    let expected = (0..49).try_fold(
+        read_alive_cells(format!("check/images/{}x{}x0.pgm", width, height), width, height)?,
+        |alive, _| anyhow::Ok(step(&alive, width, height)),
+    )?;
+    assert_eq_board(args, &recording.cells(49, 1), &expected);
+    Ok(1)
+}
+
+/// `Recording` is a decoded GIF, with the palette index of every pixel of every frame.
+struct Recording {
+    width: usize,
+    height: usize,
+    palette: Vec<u8>,
+    frames: Vec<Vec<u8>>,
+}
+
+impl Recording {
+    fn decode<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let mut options = gif::DecodeOptions::new();
+        options.set_color_output(gif::ColorOutput::Indexed);
+        let mut decoder = options.read_info(File::open(path)?)?;
+        let (width, height) = (decoder.width() as usize, decoder.height() as usize);
+        let palette = decoder.global_palette().context("The recording has no palette")?.to_vec();
+        let mut frames = Vec::new();
+        while let Some(frame) = decoder.read_next_frame()? {
+            assert_eq!(
+                (frame.width as usize, frame.height as usize), (width, height),
+                "Expected every frame to cover the whole recording"
+            );
+            frames.push(frame.buffer.to_vec());
+        }
+        Ok(Recording { width, height, palette, frames })
+    }
+
+    /// The alive cells of a frame recorded at `scale` pixels a cell, whose pixels should all match their cell.
+    fn cells(&self, frame: usize, scale: usize) -> Vec<CellCoord> {
+        let pixels = &self.frames[frame];
+        let cell = |x: usize, y: usize| pixels[y * scale * self.width + x * scale];
+        for (i, &pixel) in pixels.iter().enumerate() {
+            let (x, y) = (i % self.width / scale, i / self.width / scale);
+            assert_eq!(pixel, cell(x, y), "Expected every pixel of cell ({}, {}) in frame {} to match", x, y, frame);
+        }
+        (0..self.height / scale)
+            .flat_map(|y| (0..self.width / scale).map(move |x| (x, y)))
+            .filter(|&(x, y)| cell(x, y) == 1)
+            .map(|(x, y)| CellCoord::new(x, y))
+            .collect()
+    }
+}
+
+/// Run the Game of Life headless with --record and decode the recording once it is saved.
+async fn record(args: Args) -> Result<Recording> {
+    let (_key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+    timeout(Duration::from_secs(30), async {
+        try_join!(gol::run(args.clone(), events_tx, key_presses_rx), sdl::r#loop::run_headless(args.clone(), events_rx))
+    }).await.context("The run was not recorded within 30 seconds")??;
+    Recording::decode(args.output_dir.join(format!("{}x{}x0.gif", args.width(), args.height())))
+}
@@ -0,0 +1,85 @@
+use anyhow::{Context, Result};
+use colored::Colorize;
+use gol_rs::args::Args;
+use gol_rs::gol::{self, event::Event, Params};
+use gol_rs::record::event_log;
+use gol_rs::sdl;
+use gol_rs::util::logger;
+use log::Level;
+use sdl2::keyboard::Keycode;
+use std::time::{Duration, Instant};
+use tokio::time::timeout;
+
+mod utils;
+
+#[tokio::main]
+async fn main() {
+    let start = std::time::Instant::now();
+    logger::set_panic_hook();
+    logger::init(Level::Debug, false);
+
+    let passed_tests = test_replay(Args::default().threads(1)).await.unwrap();
+
+    println!(
+        "\ntest result: {}. {} passed; finished in {:.2}s\n",
+        "ok".green(),
+        passed_tests,
+        start.elapsed().as_secs_f32()
+    );
+    std::process::exit(0);
+}
+
+/// Replay test logs the events of a 64x64 image over 50 turns and replays the log,
+/// without delays and at twice the logged speed, which should send exactly the same events.
+async fn test_replay(args: Args) -> Result<usize> {
+    let path = "out/64x64.events.jsonl";
+    let args = args.turns(50).image_width(64).image_height(64).headless(true).event_log(path);
+    log::debug!(target: "Test", "{} - {:?}", "Testing Replay".cyan(), Params::from(args.clone()));
+
+    // the test sits between the simulation and the headless loop, which logs the events
+    let (_key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+    let (events_forward_tx, events_forward_rx) = flume::bounded::<Event>(1000);
+    tokio::spawn(gol::run(args.clone(), events_tx, key_presses_rx));
+    let logging = tokio::spawn(sdl::r#loop::run_headless(args.clone(), events_forward_rx));
+    let started = Instant::now();
+    let mut original = Vec::new();
+    while let Ok(event) = events_rx.recv_async().await {
+        original.push(format!("{:?}", event));
+        events_forward_tx.send_async(event).await?;
+    }
+    let logged_duration = started.elapsed();
+    timeout(Duration::from_secs(10), logging).await.context("The event log was not saved")???;
+
+    let header = event_log::read_header(path).await?;
+    assert_eq!((header.image_width, header.image_height), (64, 64), "Expected the log to be of a 64x64 board");
+
+    let replayed = replay(path, 0.0).await?;
+    assert_eq!(replayed, original, "Expected the replay to send exactly the logged events");
+    log::debug!(target: "Test", "{}", "Replayed without delays".cyan());
+
+    let started = Instant::now();
+    let replayed = replay(path, 2.0).await?;
+    assert_eq!(replayed, original, "Expected the replay to send exactly the logged events");
+    assert!(
+        started.elapsed() >= logged_duration / 3,
+        "Expected the replay to take about half as long as the run, but it took {:?} instead of {:?}",
+        started.elapsed(), logged_duration
+    );
+    log::debug!(target: "Test", "{}", "Replayed at twice the speed".cyan());
+
+    Ok(1)
+}
+
+/// Replay the log at `path` and collect every event it sends, formatted for comparison.
+async fn replay(path: &str, speed: f64) -> Result<Vec<String>> {
+    let (_key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+    let replaying = tokio::spawn(event_log::replay(path.to_owned(), speed, events_tx, key_presses_rx));
+    let mut events = Vec::new();
+    while let Ok(event) = events_rx.recv_async().await {
+        events.push(format!("{:?}", event));
+    }
+    replaying.await??;
+    Ok(events)
+}
@@ -7,7 +7,7 @@ use gol_rs::{args::Args, gol::{self, event::{Event, State}, Params}, util::{cell
 use log::Level;
 use sdl2::keyboard::Keycode;
 use tokio::select;
-use utils::{common::deadline, io::{read_alive_cells, read_alive_counts}, sdl, visualise::assert_eq_board};
+use utils::{common::deadline, io::{read_alive_cells, read_alive_counts}, sdl::{self, Frame}, visualise::assert_eq_board};
 
 mod utils;
 
@@ -38,7 +38,8 @@ async fn main() {
     std::process::exit(0);
 }
 
-/// Sdl tests program behaviour on key presses
+/// Sdl tests program behaviour on key presses.
+/// Without a display, the window is rendered offscreen and every frame is checked against the board.
 async fn test_sdl(args: Args) -> Result<usize> {
     let args = args
         .turns(100000000)
@@ -53,6 +54,7 @@ async fn test_sdl(args: Args) -> Result<usize> {
     let (events_tx, events_rx) = flume::bounded::<Event>(1000);
     let (events_forward_tx, events_forward_rx) = flume::bounded::<Event>(1000);
     let (gol_done_tx, gol_done_rx) = flume::bounded::<()>(1);
+    let (frames_tx, frames_rx) = flume::bounded::<Frame>(1);
 
     let gol = tokio::spawn({
         let args = args.clone();
@@ -63,13 +65,16 @@ async fn test_sdl(args: Args) -> Result<usize> {
         }
     });
     let tester = tokio::spawn(
-        Tester::start(args.clone(), key_presses_tx, events_forward_rx, gol_done_rx));
+        Tester::start(args.clone(), key_presses_tx, events_forward_rx, frames_rx, gol_done_rx));
     let (gol, sdl, tester) = if args.headless {
         let sdl = sdl::run_headless(
+            args.clone(),
+            &[1, 100],
             events_rx,
             key_presses_rx,
             events_forward_tx,
-            key_presses_forward_tx
+            key_presses_forward_tx,
+            frames_tx
         );
         tokio::join!(gol, sdl, tester)
     } else {
@@ -93,6 +98,8 @@ struct Tester {
     events_watcher: Receiver<Event>,
     turn: u32,
     world: Vec<Vec<CellValue>>,
+    /// The board as the frames have drawn it so far.
+    drawn: Vec<Vec<CellValue>>,
     alive_map: HashMap<u32, u32>,
 }
 
@@ -101,6 +108,7 @@ impl Tester {
         args: Args,
         key_presses: Sender<Keycode>,
         events: Receiver<Event>,
+        frames: Receiver<Frame>,
         gol_done: Receiver<()>,
     ) -> Result<()> {
         let (watcher_tx, watcher_rx) = flume::unbounded::<Event>();
@@ -110,8 +118,9 @@ impl Tester {
             events,
             events_watcher: watcher_rx,
             turn: 0,
-            world: vec![vec![CellValue::Dead; args.image_width]; args.image_height],
-            alive_map: read_alive_counts(args.image_width as u32, args.image_height as u32)?,
+            world: vec![vec![CellValue::Dead; args.width()]; args.height()],
+            drawn: vec![vec![CellValue::Dead; args.width()]; args.height()],
+            alive_map: read_alive_counts(args.width() as u32, args.height() as u32)?,
         };
 
         tokio::spawn(tester.test_pause(Duration::from_secs(3)));
@@ -127,6 +136,9 @@ impl Tester {
 
         loop {
             select! {
+                // a frame is sent once the events of its turn have been, and none follow until it is checked,
+                // so taking the events first leaves the board at the turn of the frame
+                biased;
                 gol_event = tester.events.recv_async() => {
                     match gol_event {
                         Ok(Event::CellFlipped { completed_turns, cell }) => {
@@ -167,6 +179,7 @@ impl Tester {
                         },
                     }
                 },
+                Ok(frame) = frames.recv_async() => tester.test_frame(frame),
             }
         }
 
@@ -188,30 +201,51 @@ impl Tester {
         if self.turn == 0 || self.turn == 1 || self.turn == 100 {
             let path = format!(
                 "check/images/{}x{}x{}.pgm",
-                self.args.image_width,
-                self.args.image_height,
+                self.args.width(),
+                self.args.height(),
                 self.turn
             );
             let expected_alive = read_alive_cells(
                 path,
-                self.args.image_width,
-                self.args.image_height
+                self.args.width(),
+                self.args.height()
             ).unwrap();
 
-            let alive_cells = self.world.iter().enumerate()
-                .flat_map(|(y, row)|
-                    row.iter().enumerate()
-                        .filter(|&(_, &cell)| cell.is_alive())
-                        .map(move |(x, _)| CellCoord::new(x, y)))
-                .collect::<Vec<CellCoord>>();
-            assert_eq_board(self.args.clone(), &alive_cells, &expected_alive);
+            assert_eq_board(self.args.clone(), &self.alive_cells(), &expected_alive);
         }
     }
 
+    /// Expect the frame shown after a turn to draw exactly the alive cells of the board, also once dumped.
+    /// The frame only carries the pixels that changed, so they are applied to the board drawn so far.
+    fn test_frame(&mut self, frame: Frame) {
+        assert_eq!(frame.completed_turns, self.turn,
+            "Expected a frame of turn {}, got turn {} instead", self.turn, frame.completed_turns);
+        frame.changed.iter().for_each(|cell| self.drawn[cell.y][cell.x].flip());
+        let alive_count = self.world.iter().flatten().filter(|&&cell| cell.is_alive()).count();
+        assert_eq!(
+            frame.count as usize, alive_count,
+            "At turn {} expected the window to count {} alive cells, got {} instead",
+            self.turn, alive_count, frame.count
+        );
+        // the boards only need comparing cell by cell when they differ
+        if self.drawn != self.world {
+            assert_eq_board(self.args.clone(), &alive_cells(&self.drawn), &self.alive_cells());
+        }
+        if let Some(png) = frame.png {
+            let dumped = read_alive_cells(png, self.args.width(), self.args.height()).unwrap();
+            assert_eq_board(self.args.clone(), &dumped, &self.alive_cells());
+        }
+        let _ = frame.checked.send(());
+    }
+
+    fn alive_cells(&self) -> Vec<CellCoord> {
+        alive_cells(&self.world)
+    }
+
     fn test_output(&self, delay: Duration) -> impl Future<Output = ()> {
         let key_presses = self.key_presses.clone();
         let event_watcher = self.events_watcher.clone();
-        let (width, height) = (self.args.image_width, self.args.image_height);
+        let (width, height) = (self.args.width(), self.args.height());
         async move {
             tokio::time::sleep(delay).await;
             log::debug!(target: "Test", "{}", "Testing image output".cyan());
@@ -290,3 +324,12 @@ impl Tester {
     }
 
 }
+
+fn alive_cells(board: &[Vec<CellValue>]) -> Vec<CellCoord> {
+    board.iter().enumerate()
+        .flat_map(|(y, row)|
+            row.iter().enumerate()
+                .filter(|&(_, &cell)| cell.is_alive())
+                .map(move |(x, _)| CellCoord::new(x, y)))
+        .collect()
+}
@@ -0,0 +1,194 @@
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use gol_rs::args::Args;
+use gol_rs::gol::{self, event::{Event, State}};
+use gol_rs::net::{protocol::{read_message, Connection, Message, Request, Response}, server::{self, ServerOptions}};
+use gol_rs::sdl;
+use gol_rs::util::{cell::CellCoord, logger};
+use log::Level;
+use sdl2::keyboard::Keycode;
+use std::{collections::HashSet, time::Duration};
+use tokio::{io::{AsyncWriteExt, BufReader}, net::{TcpListener, TcpStream}, time::timeout};
+
+mod utils;
+
+#[tokio::main]
+async fn main() {
+    let start = std::time::Instant::now();
+    logger::set_panic_hook();
+    logger::init(Level::Debug, false);
+
+    let mut passed_tests = test_server(Args::default().threads(1)).await.unwrap();
+    passed_tests += test_persistent(Args::default().threads(1)).await.unwrap();
+
+    println!(
+        "\ntest result: {}. {} passed; finished in {:.2}s\n",
+        "ok".green(),
+        passed_tests,
+        start.elapsed().as_secs_f32()
+    );
+    std::process::exit(0);
+}
+
+/// Server test runs a 512x512 image behind a server on the loopback interface.
+/// Two controllers subscribe to the events, one of them disconnects mid-run, a third sends a line too long
+/// to be a request, and the other pauses, snapshots, resumes and quits the simulation.
+async fn test_server(args: Args) -> Result<usize> {
+    let args = args.turns(100000000).image_width(512).image_height(512).headless(true);
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+
+    let (key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+    let (events_forward_tx, events_forward_rx) = flume::bounded::<Event>(1000);
+    tokio::spawn(gol::run(args.clone(), events_tx, key_presses_rx));
+    tokio::spawn(sdl::r#loop::run_headless(args, events_forward_rx));
+    let serving = tokio::spawn(server::serve(listener, ServerOptions::default(), events_rx, events_forward_tx, key_presses_tx));
+
+    let mut controller = Connection::connect(addr).await?;
+    let mut observer = Connection::connect(addr).await?;
+    for connection in [&mut controller, &mut observer] {
+        connection.send(&Request::Subscribe).await?;
+        expect(connection, "a turn", |event| matches!(event, Event::TurnComplete { .. })).await?;
+    }
+    log::debug!(target: "Test", "{}", "Both controllers receive events".cyan());
+
+    // the simulation carries on when a controller goes away
+    drop(observer);
+    expect(&mut controller, "a turn", |event| matches!(event, Event::TurnComplete { .. })).await?;
+
+    // a line longer than any request is refused without losing the requests after it
+    let (reader, mut writer) = TcpStream::connect(addr).await?.into_split();
+    let mut reader = BufReader::new(reader);
+    let mut lines = vec![b'x'; 4 * Request::MAX_LEN as usize];
+    lines.extend_from_slice(b"\n{\"command\":\"unsubscribe\"}\n");
+    writer.write_all(&lines).await?;
+    match timeout(Duration::from_secs(10), read_message::<_, Response>(&mut reader)).await?? {
+        Some(Response::Error { message }) if message.contains("longer than") => (),
+        response => bail!("Expected a long line to be refused, but got {:?}", response),
+    }
+    match timeout(Duration::from_secs(10), read_message::<_, Response>(&mut reader)).await?? {
+        Some(Response::Ok) => (),
+        response => bail!("Expected the next request to be answered after a long line, but got {:?}", response),
+    }
+    log::debug!(target: "Test", "{}", "A long line is refused".cyan());
+
+    controller.send(&Request::Pause).await?;
+    let paused = expect(&mut controller, "the paused state", |event|
+        matches!(event, Event::StateChange { new_state: State::Pause, .. })).await?;
+    log::debug!(target: "Test", "{}", "Paused by a controller".cyan());
+
+    controller.send(&Request::Snapshot).await?;
+    let snapshot = expect(&mut controller, "an image output", |event|
+        matches!(event, Event::ImageOutputComplete { .. })).await?;
+    assert_eq!(
+        snapshot.get_completed_turns(), paused.get_completed_turns(),
+        "Expected the snapshot to be taken while paused"
+    );
+
+    controller.send(&Request::Resume).await?;
+    expect(&mut controller, "the executing state", |event|
+        matches!(event, Event::StateChange { new_state: State::Executing, .. })).await?;
+    expect(&mut controller, "a turn", |event| matches!(event, Event::TurnComplete { .. })).await?;
+    log::debug!(target: "Test", "{}", "Resumed by a controller".cyan());
+
+    controller.send(&Request::Quit).await?;
+    expect(&mut controller, "the final turn", |event| matches!(event, Event::FinalTurnComplete { .. })).await?;
+    expect(&mut controller, "the quitting state", |event|
+        matches!(event, Event::StateChange { new_state: State::Quitting, .. })).await?;
+    timeout(Duration::from_secs(5), serving).await.context("The server did not stop after quitting")???;
+    log::debug!(target: "Test", "{}", "Quit by a controller".cyan());
+
+    Ok(1)
+}
+
+/// Persistent test runs a 64x64 image behind a persistent server with no controller attached.
+/// A controller attaches mid-run, detaches, and another one attaches later and follows the board
+/// from its snapshot to the final turn. The server keeps serving the final board until it is shut down.
+async fn test_persistent(args: Args) -> Result<usize> {
+    let args = args.turns(100000000).image_width(64).image_height(64).headless(true).persistent(true);
+    let listener = TcpListener::bind("127.0.0.1:0").await?;
+    let addr = listener.local_addr()?;
+
+    let (key_presses_tx, key_presses_rx) = flume::bounded::<Keycode>(10);
+    let (events_tx, events_rx) = flume::bounded::<Event>(1000);
+    let (events_forward_tx, events_forward_rx) = flume::bounded::<Event>(1000);
+    tokio::spawn(gol::run(args.clone(), events_tx, key_presses_rx));
+    tokio::spawn(sdl::r#loop::run_headless(args.clone(), events_forward_rx));
+    let options = ServerOptions::from(&args);
+    let serving = tokio::spawn(server::serve(listener, options, events_rx, events_forward_tx, key_presses_tx));
+
+    let mut first = Connection::connect(addr).await?;
+    let (_, first_turn) = attach(&mut first).await?;
+    drop(first);
+    log::debug!(target: "Test", "{}", "A controller attaches and detaches".cyan());
+
+    // the simulation carries on with no controller attached
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let mut controller = Connection::connect(addr).await?;
+    let (mut alive, turn) = attach(&mut controller).await?;
+    assert!(turn > first_turn, "Expected the simulation to carry on while detached");
+    log::debug!(target: "Test", "{}", "Another controller attaches later".cyan());
+
+    controller.send(&Request::Quit).await?;
+    let final_alive = loop {
+        match expect(&mut controller, "the final turn", |_| true).await? {
+            Event::CellsFlipped { cells, .. } => cells.iter().for_each(|cell| {
+                if !alive.remove(cell) {
+                    alive.insert(*cell);
+                }
+            }),
+            Event::FinalTurnComplete { alive, .. } => break alive,
+            _ => (),
+        }
+    };
+    assert_eq!(
+        alive, final_alive.into_iter().collect(),
+        "Expected the snapshot and the flipped cells to add up to the final board"
+    );
+    expect(&mut controller, "the quitting state", |event|
+        matches!(event, Event::StateChange { new_state: State::Quitting, .. })).await?;
+    log::debug!(target: "Test", "{}", "The snapshot follows the board".cyan());
+
+    // the server outlives the simulation until it is shut down
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    assert!(!serving.is_finished(), "Expected the server to keep running after the simulation quit");
+    let mut late = Connection::connect(addr).await?;
+    let (late_alive, _) = attach(&mut late).await?;
+    assert_eq!(late_alive, alive, "Expected the final board when attaching after the simulation quit");
+    late.send(&Request::Shutdown).await?;
+    timeout(Duration::from_secs(5), serving).await.context("The server did not stop after a shutdown")???;
+    log::debug!(target: "Test", "{}", "Shut down by a controller".cyan());
+
+    Ok(1)
+}
+
+/// Attach a connection and collect the snapshot of the board and the turn it was taken on.
+async fn attach(connection: &mut Connection) -> Result<(HashSet<CellCoord>, u32)> {
+    connection.send(&Request::Attach).await?;
+    match timeout(Duration::from_secs(10), connection.recv()).await?? {
+        Some(Response::Attached { image_width: 64, image_height: 64, .. }) => (),
+        response => bail!("Expected the server to attach a 64x64 board, but got {:?}", response),
+    }
+    match expect(connection, "a snapshot", |_| true).await? {
+        Event::CellsFlipped { completed_turns, cells } => Ok((cells.into_iter().collect(), completed_turns)),
+        event => bail!("Expected a snapshot, but got {:?}", event),
+    }
+}
+
+/// Skip the responses of a connection until an event matching `predicate` arrives.
+async fn expect<F>(connection: &mut Connection, description: &str, predicate: F) -> Result<Event>
+where
+    F: Fn(&Event) -> bool,
+{
+    timeout(Duration::from_secs(10), async {
+        loop {
+            match connection.recv().await? {
+                Some(Response::Event { event }) if predicate(&event) => return Ok(event),
+                Some(Response::Error { message }) => bail!("The server replied with an error: {}", message),
+                Some(_) => (),
+                None => bail!("The server closed the connection while waiting for {}", description),
+            }
+        }
+    }).await.with_context(|| format!("No {} received within 10 seconds", description))?
+}
@@ -0,0 +1,119 @@
+use colored::Colorize;
+use gol_rs::sdl::colour::{ALIVE, DEAD};
+use gol_rs::sdl::config::{Action, KeyBindings};
+use gol_rs::tui::canvas::{to_ansi, Canvas, Glyph, Glyphs};
+use gol_rs::tui::terminal::{parse_keys, Key};
+use gol_rs::util::logger;
+use log::Level;
+
+fn main() {
+    let start = std::time::Instant::now();
+    logger::set_panic_hook();
+    logger::init(Level::Debug, false);
+
+    let passed_tests = test_glyphs() + test_view() + test_ansi() + test_keys();
+
+    println!(
+        "\ntest result: {}. {} passed; finished in {:.2}s\n",
+        "ok".green(),
+        passed_tests,
+        start.elapsed().as_secs_f32()
+    );
+    std::process::exit(0);
+}
+
+/// A world of `width × height` cells with only `alive` alive.
+fn world(width: usize, height: usize, alive: &[(usize, usize)]) -> Vec<bool> {
+    let mut world = vec![false; width * height];
+    alive.iter().for_each(|&(x, y)| world[y * width + x] = true);
+    world
+}
+
+fn plain(alive: &[bool]) -> impl Fn(usize) -> u32 + '_ {
+    |i| if alive[i] { ALIVE } else { DEAD }
+}
+
+/// Glyphs test draws a few cells with braille and half blocks,
+/// expecting every cell in its dot and nothing drawn past the world.
+fn test_glyphs() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Glyphs".cyan());
+    let alive = world(4, 4, &[(0, 0), (1, 3)]);
+    let canvas = Canvas::new(4, 4, 3, 1, Glyphs::Braille);
+    assert_eq!(canvas.scale(), 1, "Expected a cell per dot");
+    let frame = canvas.draw(&alive, plain(&alive));
+    assert_eq!(frame[0][0], Glyph { symbol: '⢁', foreground: ALIVE, background: DEAD }, "Expected the top left and bottom right dots");
+    assert_eq!(frame[0][1], Glyph { symbol: ' ', foreground: DEAD, background: DEAD }, "Expected no dots");
+    assert_eq!(frame[0][2].symbol, ' ', "Expected nothing past the world");
+    assert_ne!(frame[0][2].background, DEAD, "Expected the terminal past the world to stand out from dead cells");
+
+    let alive = world(2, 2, &[(0, 0)]);
+    let frame = Canvas::new(2, 2, 2, 1, Glyphs::HalfBlock).draw(&alive, plain(&alive));
+    assert_eq!(frame[0][0], Glyph { symbol: '▀', foreground: ALIVE, background: DEAD }, "Expected an alive top half");
+    assert_eq!(frame[0][1], Glyph { symbol: '▀', foreground: DEAD, background: DEAD }, "Expected a dead column");
+
+    // zoomed out to 2×2 cells per dot, a dot is lit by any of its cells
+    let alive = world(4, 8, &[(3, 7)]);
+    let canvas = Canvas::new(4, 8, 1, 1, Glyphs::Braille);
+    assert_eq!(canvas.scale(), 2, "Expected the world to fit in one character");
+    assert_eq!(canvas.draw(&alive, plain(&alive))[0][0].symbol, '⢀', "Expected the bottom right dot");
+    1
+}
+
+/// View test zooms and pans across a world four times the size of the terminal,
+/// expecting the view to stay centred when zoomed and within the world when panned.
+fn test_view() -> usize {
+    log::debug!(target: "Test", "{}", "Testing View".cyan());
+    // 8×4 braille characters are 16×16 dots
+    let mut canvas = Canvas::new(64, 64, 8, 4, Glyphs::Braille);
+    assert_eq!((canvas.scale(), canvas.offset()), (4, (0, 0)), "Expected the whole world");
+    canvas.zoom(1);
+    assert_eq!((canvas.scale(), canvas.offset()), (2, (16, 16)), "Expected to zoom into the centre");
+    canvas.pan(1, -1);
+    assert_eq!(canvas.offset(), (20, 8), "Expected to move by a character");
+    canvas.pan(100, 100);
+    assert_eq!(canvas.offset(), (32, 32), "Expected to stop at the far edges");
+    canvas.pan(-100, 0);
+    assert_eq!(canvas.offset(), (0, 32), "Expected to stop at the near edges");
+    canvas.set_glyphs(Glyphs::HalfBlock);
+    assert_eq!((canvas.scale(), canvas.offset()), (8, (0, 0)), "Expected half blocks to fit the whole world");
+    canvas.resize(64, 32);
+    canvas.fit();
+    assert_eq!(canvas.scale(), 1, "Expected a cell per dot in a larger terminal");
+    1
+}
+
+/// ANSI test encodes two rows of glyphs, expecting the colours only when they change.
+fn test_ansi() -> usize {
+    log::debug!(target: "Test", "{}", "Testing ANSI".cyan());
+    let lit = Glyph { symbol: '⣿', foreground: ALIVE, background: DEAD };
+    let frame = vec![vec![lit, lit], vec![Glyph { symbol: ' ', foreground: DEAD, background: DEAD }]];
+    assert_eq!(
+        to_ansi(&frame),
+        "\x1b[1;1H\x1b[38;2;255;255;255;48;2;0;0;0m⣿⣿\x1b[2;1H\x1b[38;2;0;0;0;48;2;0;0;0m \x1b[0m",
+        "Expected each row to start in its first column"
+    );
+    1
+}
+
+/// Keys test reads a burst of keys, expecting the arrows, a lone escape and Ctrl+C,
+/// and the sequences of other keys to be left out. The keys are then looked up in the default key bindings,
+/// whatever their case and with Ctrl arriving as a control character.
+fn test_keys() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Keys".cyan());
+    assert_eq!(
+        parse_keys(b"p\x1b[A\x1b\x03\x1b[1;5C\x1b[Z\x1bOBq"),
+        [Key::Char('p'), Key::Up, Key::Escape, Key::Interrupt, Key::Right, Key::Down, Key::Char('q')],
+        "Expected every key in order"
+    );
+
+    let bindings = KeyBindings::default();
+    let action = |key: Key| key.sdl().and_then(|(keycode, keymod)| bindings.action(keycode, keymod));
+    assert_eq!(action(Key::Char('p')), Some(Action::Pause), "Expected p to pause");
+    assert_eq!(action(Key::Char('Q')), Some(Action::Quit), "Expected Q to quit");
+    assert_eq!(action(Key::Escape), Some(Action::Quit), "Expected Escape to quit");
+    assert_eq!(action(Key::Char('?')), Some(Action::Help), "Expected ? to show the help");
+    assert_eq!(action(Key::Char('\x0e')), Some(Action::FillRandom), "Expected Ctrl+N to fill the selection");
+    assert_eq!(action(Key::Up), None, "Expected the arrows to be left to pan");
+    assert_eq!(Key::Interrupt.sdl(), None, "Expected Ctrl+C to be left to quit");
+    2
+}
@@ -23,7 +23,8 @@ pub mod io {
             "Incorrect height"
         );
 
-        Ok(pgm.into_bytes().chunks(width).enumerate()
+        // frames dumped as PNG are read in grey too, where alive cells are white
+        Ok(pgm.to_luma8().into_raw().chunks(width).enumerate()
             .flat_map(|(y, row)|
                 row.iter().enumerate()
                     .filter(|&(_, &cell)| CellValue::from(cell).is_alive())
@@ -69,8 +70,8 @@ pub mod visualise {
             return
         }
 
-        if args.image_width == 16 && args.image_height == 16 {
-            let mut input_matrix = vec![vec![CellValue::Dead; args.image_width]; args.image_height];
+        if args.width() == 16 && args.height() == 16 {
+            let mut input_matrix = vec![vec![CellValue::Dead; args.width()]; args.height()];
             let mut expected_matrix = input_matrix.clone();
             input_cells.iter().for_each(|cell| input_matrix[cell.y][cell.x] = CellValue::Alive);
             expected_cells.iter().for_each(|cell| expected_matrix[cell.y][cell.x] = CellValue::Alive);
@@ -127,11 +128,12 @@ pub mod visualise {
 
 #[allow(dead_code)]
 pub mod sdl {
-    use std::time::Duration;
+    use std::{path::PathBuf, time::Duration};
     use anyhow::Result;
     use flume::{Receiver, Sender};
     use sdl2::keyboard::Keycode;
-    use gol_rs::{args::Args, gol::event::{Event, State}, sdl::window::Window, util::avgturns::AvgTurns};
+    use gol_rs::{args::Args, gol::event::{Event, State}, util::{avgturns::AvgTurns, cell::CellCoord}};
+    use gol_rs::sdl::{colour::ALIVE, offscreen::Offscreen, window::Window};
     use tokio::select;
 
     pub async fn run<T: AsRef<str>>(
@@ -144,8 +146,8 @@ pub mod sdl {
     ) -> Result<()> {
         let mut sdl = Window::new(
             title,
-            args.image_width as u32,
-            args.image_height as u32,
+            args.width() as u32,
+            args.height() as u32,
         )?;
         let fps = 60;
         let mut event_pump = sdl.take_event_pump()?;
@@ -200,12 +202,35 @@ pub mod sdl {
         Ok(())
     }
 
+    /// `Frame` is what the window showed after a turn, rendered offscreen.
+    pub struct Frame {
+        pub completed_turns: u32,
+        pub count: u32,
+        /// The cells drawn alive in this frame but not in the previous one or the other way round,
+        /// read back from the pixels of the frame.
+        pub changed: Vec<CellCoord>,
+        /// Where the frame was dumped, if it was.
+        pub png: Option<PathBuf>,
+        /// Told once the frame has been checked, which holds the events of the next turn back until then.
+        pub checked: Sender<()>,
+    }
+
+    /// Render every turn offscreen like the window would, sending the frames on to be checked
+    /// and dumping those of `png_turns` to the output directory.
+    /// Only the pixels that changed since the previous frame are sent, which keeps the hand-off cheap.
     pub async fn run_headless(
+        args: Args,
+        png_turns: &[u32],
         events: Receiver<Event>,
         key_presses: Receiver<Keycode>,
         events_forward: Sender<Event>,
         key_presses_forward: Sender<Keycode>,
+        frames: Sender<Frame>,
     ) -> Result<()> {
+        let (width, height) = (args.width() as u32, args.height() as u32);
+        let mut screen = Offscreen::new(width, height, width, height);
+        let mut drawn = vec![false; (width * height) as usize];
+        screen.hud_mut().visible = false;
         let mut avg_turns = AvgTurns::new();
         'sdl: loop {
             select! {
@@ -219,6 +244,33 @@ pub mod sdl {
                         events_forward.send_async(e.clone()).await?;
                     }
                     match gol_event {
+                        Ok(Event::CellFlipped { cell, .. }) =>
+                            screen.flip_pixel(cell.x as u32, cell.y as u32),
+                        Ok(Event::CellsFlipped { cells, ..}) =>
+                            cells.iter().for_each(|cell| screen.flip_pixel(cell.x as u32, cell.y as u32)),
+                        Ok(Event::TurnComplete { completed_turns }) => {
+                            screen.complete_turn(completed_turns);
+                            screen.render_frame();
+                            let changed = (0..height)
+                                .flat_map(|y| (0..width).map(move |x| (x, y)))
+                                .filter(|&(x, y)| (screen.pixel(x, y) == ALIVE) != drawn[(y * width + x) as usize])
+                                .map(|(x, y)| CellCoord::new(x as usize, y as usize))
+                                .collect::<Vec<CellCoord>>();
+                            changed.iter().for_each(|cell| drawn[cell.y * width as usize + cell.x] ^= true);
+                            let png = png_turns.contains(&completed_turns).then(|| args.output_dir
+                                .join("frames")
+                                .join(format!("{}x{}x{}.png", width, height, completed_turns)));
+                            if let Some(png) = &png {
+                                screen.save_png(png)?;
+                            }
+                            let count = screen.count_pixels();
+                            let (checked_tx, checked_rx) = flume::bounded::<()>(1);
+                            let frame = Frame { completed_turns, count, changed, png, checked: checked_tx };
+                            // the tester may have gone once it has seen the last turn it checks
+                            if frames.send_async(frame).await.is_ok() {
+                                let _ = checked_rx.recv_async().await;
+                            }
+                        },
                         Ok(Event::AliveCellsCount { completed_turns, .. }) =>
                             log::info!(target: "Test", "{} Avg{:>5} turns/s", gol_event?, avg_turns.get(completed_turns)),
                         Ok(Event::ImageOutputComplete { .. }) =>
@@ -232,7 +284,6 @@ pub mod sdl {
                             }
                         },
                         Err(_) => break 'sdl,
-                        _ => (),
                     }
                 },
             }
@@ -243,7 +294,8 @@ pub mod sdl {
 
 #[allow(dead_code)]
 pub mod common {
-    use std::{time::Duration, fmt::Display};
+    use gol_rs::util::cell::CellCoord;
+    use std::{collections::HashSet, time::Duration, fmt::Display};
     use tokio::task::JoinHandle;
 
     pub fn deadline<T>(ddl: Duration, msg: T) -> JoinHandle<()>
@@ -255,4 +307,87 @@ pub mod common {
             panic!("{}", msg);
         })
     }
+
+    /// Compute the next turn of the alive cells on a torus, by the standard rule.
+    pub fn step(alive: &[CellCoord], width: usize, height: usize) -> Vec<CellCoord> {
+        let alive = alive.iter().copied().collect::<HashSet<CellCoord>>();
+        let mut next = Vec::new();
+        for y in 0..height {
+            for x in 0..width {
+                let neighbours = [width - 1, 0, 1].iter()
+                    .flat_map(|dx| [height - 1, 0, 1].map(|dy| (dx, dy)))
+                    .filter(|&(&dx, dy)| (dx, dy) != (0, 0))
+                    .filter(|&(dx, dy)| alive.contains(&CellCoord::new((x + dx) % width, (y + dy) % height)))
+                    .count();
+                let cell = CellCoord::new(x, y);
+                if neighbours == 3 || (neighbours == 2 && alive.contains(&cell)) {
+                    next.push(cell);
+                }
+            }
+        }
+        next
+    }
+}
+
+#[allow(dead_code)]
+pub mod worker {
+    use anyhow::{Context, Result};
+    use std::process::Stdio;
+    use tokio::io::{AsyncBufReadExt, BufReader};
+    use tokio::process::{Child, Command};
+
+    /// Start a `gol-rs worker` process on a free port of the loopback interface.
+    pub async fn spawn() -> Result<(Child, String)> {
+        let mut child = Command::new(env!("CARGO_BIN_EXE_gol-rs"))
+            .args(["worker", "--listen", "127.0.0.1:0"])
+            .stdout(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        let stdout = child.stdout.take().context("The worker has no stdout")?;
+        let addr = BufReader::new(stdout).lines().next_line().await?
+            .context("The worker stopped before listening")?;
+        Ok((child, addr))
+    }
+}
+
+#[allow(dead_code)]
+pub mod http {
+    use anyhow::{Context, Result};
+    use std::{io::ErrorKind, time::{Duration, Instant}};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::{net::TcpStream, time::timeout};
+
+    /// Find a port on the loopback interface that nothing is listening on.
+    pub fn free_addr() -> Result<String> {
+        let listener = std::net::TcpListener::bind("127.0.0.1:0")?;
+        Ok(listener.local_addr()?.to_string())
+    }
+
+    /// Send a request like `request`, trying again for up to 10 seconds while nothing listens on `addr` yet.
+    pub async fn request_when_listening(addr: &str, method: &str, path: &str) -> Result<(u16, Vec<u8>)> {
+        let deadline = Instant::now() + Duration::from_secs(10);
+        loop {
+            match request(addr, method, path).await {
+                Err(e) if Instant::now() < deadline && e.downcast_ref::<std::io::Error>()
+                    .is_some_and(|e| e.kind() == ErrorKind::ConnectionRefused) =>
+                    tokio::time::sleep(Duration::from_millis(50)).await,
+                response => return response,
+            }
+        }
+    }
+
+    /// Send a request without a body and return the status code and body of the response.
+    pub async fn request(addr: &str, method: &str, path: &str) -> Result<(u16, Vec<u8>)> {
+        timeout(Duration::from_secs(10), async {
+            let mut stream = TcpStream::connect(addr).await?;
+            stream.write_all(format!("{} {} HTTP/1.1\r\nHost: {}\r\n\r\n", method, path, addr).as_bytes()).await?;
+            let mut response = Vec::new();
+            stream.read_to_end(&mut response).await?;
+            let head_end = response.windows(4).position(|window| window == b"\r\n\r\n")
+                .context("The response has no end of head")?;
+            let head = String::from_utf8_lossy(&response[..head_end]).to_string();
+            let code = head.split_whitespace().nth(1).context("The response has no status code")?.parse()?;
+            Ok((code, response[head_end + 4..].to_vec()))
+        }).await.with_context(|| format!("No response to {} {} within 10 seconds", method, path))?
+    }
 }
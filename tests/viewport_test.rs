@@ -0,0 +1,118 @@
+use colored::Colorize;
+use gol_rs::sdl::viewport::{window_size, Viewport};
+use gol_rs::util::logger;
+use log::Level;
+
+fn main() {
+    let start = std::time::Instant::now();
+    logger::set_panic_hook();
+    logger::init(Level::Debug, false);
+
+    let passed_tests = test_window_size() + test_upscale() + test_downsample() + test_zoom_and_pan();
+
+    println!(
+        "\ntest result: {}. {} passed; finished in {:.2}s\n",
+        "ok".green(),
+        passed_tests,
+        start.elapsed().as_secs_f32()
+    );
+    std::process::exit(0);
+}
+
+/// Read the pixel at (`x`, `y`) of an ARGB8888 buffer `width` pixels wide.
+fn pixel(pixels: &[u8], width: u32, x: u32, y: u32) -> u32 {
+    let i = 4 * (y * width + x) as usize;
+    u32::from_ne_bytes(pixels[i..i + 4].try_into().unwrap())
+}
+
+/// Window size test expects small worlds to be upscaled by whole pixels and large worlds to fit the display.
+fn test_window_size() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Window Size".cyan());
+    let display = (1920, 1080);
+    assert_eq!(window_size(16, 16, display), (512, 512), "Expected a 16x16 world to be upscaled 32 times");
+    assert_eq!(window_size(100, 30, display), (500, 150), "Expected a 100x30 world to be upscaled 5 times");
+    assert_eq!(window_size(512, 512, display), (512, 512), "Expected a 512x512 world to be shown as it is");
+    assert_eq!(window_size(16384, 16384, display), (972, 972), "Expected a 16384x16384 world to fit the display");
+    assert_eq!(window_size(4000, 1000, display), (1728, 432), "Expected a wide world to keep its aspect ratio");
+    1
+}
+
+/// Upscale test fits a 16x16 world with a single alive cell into a 512x512 view,
+/// expecting the cell to be drawn as a 32x32 block of white pixels.
+fn test_upscale() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Upscaling".cyan());
+    let viewport = Viewport::new(16, 16, 512, 512);
+    assert_eq!(viewport.zoom(), 32.0, "Expected every cell to take 32x32 pixels");
+    let mut cells = vec![false; 16 * 16];
+    cells[2 * 16 + 1] = true;
+    let mut pixels = vec![0; 512 * 512 * 4];
+    viewport.render(&cells, &mut pixels);
+    for (x, y) in [(32, 64), (63, 64), (32, 95), (63, 95)] {
+        assert_eq!(pixel(&pixels, 512, x, y), 0xFFFFFFFF, "Expected the alive cell at ({}, {})", x, y);
+    }
+    for (x, y) in [(31, 64), (64, 64), (32, 63), (32, 96), (0, 0)] {
+        assert_eq!(pixel(&pixels, 512, x, y), 0xFF000000, "Expected a dead cell at ({}, {})", x, y);
+    }
+
+    // a world narrower than the view is centred, with the background on either side
+    let viewport = Viewport::new(8, 16, 512, 512);
+    assert_eq!(viewport.offset(), (-4.0, 0.0), "Expected the narrow world to be centred");
+    let mut pixels = vec![0; 512 * 512 * 4];
+    viewport.render(&[false; 8 * 16], &mut pixels);
+    assert_eq!(pixel(&pixels, 512, 0, 0), 0xFF202020, "Expected the background left of the world");
+    assert_eq!(pixel(&pixels, 512, 128, 0), 0xFF000000, "Expected the world in the middle of the view");
+    1
+}
+
+/// Downsample test fits a 64x64 world into a 16x16 view, expecting every pixel to show
+/// the density of the 4x4 cells it covers as a grey level.
+fn test_downsample() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Downsampling".cyan());
+    let viewport = Viewport::new(64, 64, 16, 16);
+    assert_eq!(viewport.zoom(), 0.25, "Expected every pixel to cover 4x4 cells");
+    let mut cells = vec![false; 64 * 64];
+    // the first 4x4 block is full, the second is half full and the third has a single cell
+    for y in 0..4 {
+        for x in 0..4 {
+            cells[y * 64 + x] = true;
+        }
+        for x in 4..6 {
+            cells[y * 64 + x] = true;
+        }
+    }
+    cells[8] = true;
+    let mut pixels = vec![0; 16 * 16 * 4];
+    viewport.render(&cells, &mut pixels);
+    assert_eq!(pixel(&pixels, 16, 0, 0), 0xFFFFFFFF, "Expected a full block to be white");
+    assert_eq!(pixel(&pixels, 16, 1, 0), 0xFF7F7F7F, "Expected a half full block to be mid grey");
+    assert_eq!(pixel(&pixels, 16, 2, 0), 0xFF0F0F0F, "Expected a single cell to be dark grey");
+    assert_eq!(pixel(&pixels, 16, 3, 0), 0xFF000000, "Expected an empty block to be black");
+    1
+}
+
+/// Zoom and pan test zooms into a 1000x1000 world shown in a 100x100 view,
+/// expecting the cell under the mouse to stay put and the view to stay within the world.
+fn test_zoom_and_pan() -> usize {
+    log::debug!(target: "Test", "{}", "Testing Zoom and Pan".cyan());
+    let mut viewport = Viewport::new(1000, 1000, 100, 100);
+    assert_eq!(viewport.zoom(), 0.1, "Expected the whole world to fit");
+    viewport.zoom_at(50.0, 50.0, 4);
+    assert_eq!(viewport.zoom(), 1.0, "Expected zooming in to stop at a pixel per cell first");
+    assert_eq!(viewport.offset(), (450.0, 450.0), "Expected the centre of the world to stay under the mouse");
+    viewport.zoom_at(0.0, 0.0, 2);
+    assert_eq!(viewport.zoom(), 3.0, "Expected zooming in to add whole pixels per cell");
+    assert_eq!(viewport.offset(), (450.0, 450.0), "Expected the corner of the view to stay put");
+
+    viewport.pan(30.0, -60.0);
+    assert_eq!(viewport.offset(), (440.0, 470.0), "Expected dragging to move the world with the mouse");
+    viewport.pan(-100000.0, 100000.0);
+    let span = 100.0 / 3.0;
+    assert_eq!(viewport.offset(), (1000.0 - span, 0.0), "Expected the view to stop at the edges of the world");
+
+    viewport.zoom_at(50.0, 50.0, -100);
+    assert_eq!(viewport.zoom(), 0.1, "Expected zooming out to stop at the whole world");
+    viewport.zoom_at(50.0, 50.0, 3);
+    viewport.fit();
+    assert_eq!(viewport, Viewport::new(1000, 1000, 100, 100), "Expected fitting to show the whole world again");
+    1
+}